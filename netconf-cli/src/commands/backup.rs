@@ -0,0 +1,67 @@
+use super::diff::{get_config, normalize};
+use super::file_name;
+use clap::Args;
+use netconf_rust::error::Result;
+use netconf_rust::message::Datastore;
+use netconf_rust::Connection;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Debug, Args, Clone)]
+pub(crate) struct BackupArgs {
+    #[arg(
+        long,
+        help = "Git repository for configuration files, initialized if it does not exist"
+    )]
+    repo: PathBuf,
+}
+
+/// Writes running configuration of host to `<repo>/<host>.xml`, returning the file name.
+/// Files are committed by [`commit`] once all hosts are done, since git does not allow
+/// concurrent commits.
+pub(crate) fn run(address: &str, args: &BackupArgs, connection: &mut Connection) -> Result<String> {
+    let config = normalize(&get_config(connection, Datastore::Running, None)?)?;
+    let file = format!("{}.xml", file_name(address));
+    fs::create_dir_all(&args.repo)?;
+    fs::write(args.repo.join(&file), config)?;
+    Ok(file)
+}
+
+/// Commits `file` of `host` with message containing host and `timestamp`,
+/// unchanged configuration is not committed
+pub(crate) fn commit(args: &BackupArgs, host: &str, file: &str, timestamp: &str) -> io::Result<()> {
+    if !args.repo.join(".git").exists() {
+        git(&args.repo, &["init", "--quiet"])?;
+    }
+    git(&args.repo, &["add", "--", file])?;
+    if git(&args.repo, &["diff", "--cached", "--quiet", "--", file]).is_ok() {
+        log::info!(target: host, "Configuration unchanged since last backup");
+        return Ok(());
+    }
+    let message = format!("Backup of {} at {}", host, timestamp);
+    git(
+        &args.repo,
+        &["commit", "--quiet", "-m", &message, "--", file],
+    )?;
+    log::info!(target: host, "Committed backup {}", file);
+    Ok(())
+}
+
+/// Runs git in `repo`, non-zero exit status is an error with git's output
+fn git(repo: &Path, args: &[&str]) -> io::Result<()> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .output()?;
+    if output.status.success() {
+        return Ok(());
+    }
+    Err(io::Error::other(format!(
+        "git {} failed: {}",
+        args[0],
+        String::from_utf8_lossy(&output.stderr).trim()
+    )))
+}
@@ -0,0 +1,110 @@
+use crate::output::{self, OutputFormat};
+use clap::Args;
+use netconf_rust::error::Result;
+use netconf_rust::Connection;
+use serde_json::{json, Value};
+
+#[derive(Debug, Args, Clone, Default)]
+pub(crate) struct CapabilitiesArgs {
+    #[arg(
+        short,
+        long,
+        help = "Lists YANG modules with revisions instead of capability URIs"
+    )]
+    modules: bool,
+}
+
+/// YANG module advertised as `namespace?module=name&revision=date` capability
+#[derive(Debug, PartialEq)]
+struct Module<'a> {
+    name: &'a str,
+    revision: Option<&'a str>,
+    namespace: &'a str,
+}
+
+impl<'a> Module<'a> {
+    fn parse(capability: &'a str) -> Option<Module<'a>> {
+        let (namespace, query) = capability.split_once('?')?;
+        let mut name = None;
+        let mut revision = None;
+        for (key, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+            match key {
+                "module" => name = Some(value),
+                "revision" => revision = Some(value),
+                _ => {}
+            }
+        }
+        Some(Module {
+            name: name?,
+            revision,
+            namespace,
+        })
+    }
+
+    fn to_json(&self) -> Value {
+        json!({ "module": self.name, "revision": self.revision, "namespace": self.namespace })
+    }
+}
+
+pub(crate) fn run(
+    address: &str,
+    args: &CapabilitiesArgs,
+    format: OutputFormat,
+    connection: &mut Connection,
+) -> Result<()> {
+    let capabilities = connection.capabilities();
+    let modules = || capabilities.iter().filter_map(|c| Module::parse(c));
+    match (format, args.modules) {
+        (OutputFormat::Text, false) => {
+            output::print_text(
+                address,
+                log::Level::Info,
+                "Capabilities",
+                capabilities.join("\n"),
+            );
+        }
+        (OutputFormat::Text, true) => {
+            let width = modules().map(|m| m.name.len()).max().unwrap_or(0);
+            let rows: Vec<String> = modules()
+                .map(|m| {
+                    let revision = m.revision.unwrap_or("-");
+                    format!("{:width$}  {:10}  {}", m.name, revision, m.namespace)
+                })
+                .collect();
+            output::print_text(address, log::Level::Info, "Modules", rows.join("\n"));
+        }
+        (OutputFormat::Json, false) => {
+            output::print_host_json(address, "capabilities", json!(capabilities));
+        }
+        (OutputFormat::Json, true) => {
+            let modules: Vec<Value> = modules().map(|m| m.to_json()).collect();
+            output::print_host_json(address, "modules", Value::Array(modules));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_module() {
+        let capability = "urn:ietf:params:xml:ns:yang:ietf-interfaces?module=ietf-interfaces&revision=2018-02-20&features=pre-provisioning";
+        assert_eq!(
+            Module::parse(capability),
+            Some(Module {
+                name: "ietf-interfaces",
+                revision: Some("2018-02-20"),
+                namespace: "urn:ietf:params:xml:ns:yang:ietf-interfaces",
+            })
+        );
+        assert_eq!(
+            Module::parse(
+                "urn:ietf:params:netconf:capability:with-defaults:1.0?basic-mode=explicit"
+            ),
+            None
+        );
+        assert_eq!(Module::parse("urn:ietf:params:netconf:base:1.1"), None);
+    }
+}
@@ -0,0 +1,78 @@
+use clap::Args;
+use netconf_rust::error::Result;
+use netconf_rust::message::CommitOptions;
+use netconf_rust::Connection;
+use std::time::Duration;
+
+#[derive(Debug, Args, Clone, Default)]
+pub(crate) struct CommitArgs {
+    #[arg(
+        long,
+        requires = "persist",
+        help = "Confirmed commit, reverted unless confirmed in time. Requires --persist, since the session is closed after commit"
+    )]
+    confirmed: bool,
+    #[arg(
+        long,
+        requires = "confirmed",
+        help = "Seconds to wait for confirmation"
+    )]
+    confirm_timeout: Option<u64>,
+    #[arg(
+        long,
+        requires = "confirmed",
+        help = "Id for confirming the commit later"
+    )]
+    persist: Option<String>,
+    #[arg(long, help = "Id of earlier persistent confirmed commit")]
+    persist_id: Option<String>,
+    #[arg(
+        long,
+        conflicts_with = "confirmed",
+        requires = "persist_id",
+        help = "Confirms earlier persistent confirmed commit"
+    )]
+    confirm: bool,
+    #[arg(
+        long,
+        conflicts_with_all = ["confirmed", "confirm"],
+        requires = "persist_id",
+        help = "Cancels earlier persistent confirmed commit, reverting its changes"
+    )]
+    cancel: bool,
+}
+
+impl CommitArgs {
+    fn options(&self) -> Option<CommitOptions> {
+        let options = if self.confirmed {
+            let timeout = self.confirm_timeout.map(Duration::from_secs);
+            CommitOptions::confirmed(timeout)
+        } else if self.confirm || self.persist_id.is_some() {
+            CommitOptions::default()
+        } else {
+            return None;
+        };
+        let options = match &self.persist {
+            Some(id) => options.persist(id),
+            None => options,
+        };
+        match &self.persist_id {
+            Some(id) => Some(options.persist_id(id)),
+            None => Some(options),
+        }
+    }
+}
+
+pub(crate) fn run(address: &str, args: &CommitArgs, connection: &mut Connection) -> Result<()> {
+    if let (true, Some(persist_id)) = (args.cancel, &args.persist_id) {
+        connection.cancel_persisted(persist_id)?;
+        log::info!(target: address, "Cancel-commit rpc success");
+        return Ok(());
+    }
+    match args.options() {
+        Some(options) => connection.commit_with(options)?,
+        None => connection.commit()?,
+    };
+    log::info!(target: address, "Commit rpc success");
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use clap::Args;
+use netconf_rust::error::Result;
+use netconf_rust::Connection;
+use std::io;
+
+#[derive(Debug, Args, Clone, Default)]
+pub(crate) struct CopyArgs {
+    #[arg(long, default_value = "running", help = "Source datastore or url")]
+    source: String,
+    #[arg(long, help = "Target datastore or url")]
+    target: String,
+}
+
+impl CopyArgs {
+    /// Asks for confirmation before overwriting startup datastore, `None` for other targets
+    pub(crate) fn confirm(&self) -> io::Result<Option<bool>> {
+        if !self.target.eq_ignore_ascii_case("startup") {
+            return Ok(None);
+        }
+        let question = format!(
+            "Overwrite startup datastore of all hosts with {}?",
+            self.source
+        );
+        super::confirm(&question).map(Some)
+    }
+}
+
+pub(crate) fn run(address: &str, args: &CopyArgs, connection: &mut Connection) -> Result<()> {
+    connection.copy_config(&args.source, &args.target)?;
+    log::info!(target: address, "Copied {} to {}", args.source, args.target);
+    Ok(())
+}
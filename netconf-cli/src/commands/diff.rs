@@ -0,0 +1,184 @@
+use clap::Args;
+use netconf_rust::error::Result;
+use netconf_rust::message::{Datastore, Filter, Rpc, RpcContent, Source};
+use netconf_rust::Connection;
+use quick_xml::events::Event;
+use quick_xml::{Reader, Writer};
+use similar::TextDiff;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+#[derive(Debug, Args, Clone, Default)]
+pub(crate) struct DiffArgs {
+    #[arg(long, help = "Subtree filter XML applied to both datastores")]
+    subtree: Option<String>,
+    #[arg(
+        long,
+        conflicts_with = "subtree",
+        help = "XPath filter applied to both datastores"
+    )]
+    xpath: Option<String>,
+    #[arg(
+        short,
+        long,
+        conflicts_with_all = ["subtree", "xpath"],
+        help = "File with subtree filter XML applied to both datastores, read from stdin when '-'"
+    )]
+    file: Option<PathBuf>,
+}
+
+impl DiffArgs {
+    /// Reads subtree filter file once before connecting to hosts
+    pub(crate) fn read_subtree(&mut self) -> io::Result<()> {
+        if let Some(file) = &self.file {
+            self.subtree = Some(super::read_input(Some(file))?);
+        }
+        Ok(())
+    }
+
+    fn filter(&self) -> Option<Filter> {
+        match (&self.subtree, &self.xpath) {
+            (Some(subtree), _) => Some(Filter::subtree(subtree)),
+            (_, Some(xpath)) => Some(Filter::xpath(xpath)),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) fn run(address: &str, args: &DiffArgs, connection: &mut Connection) -> Result<()> {
+    let running = normalize(&get_config(connection, Datastore::Running, args.filter())?)?;
+    let candidate = normalize(&get_config(
+        connection,
+        Datastore::Candidate,
+        args.filter(),
+    )?)?;
+    if running == candidate {
+        log::info!(target: address, "No changes in candidate datastore");
+        return Ok(());
+    }
+    let diff = unified_diff(
+        &format!("{} running", address),
+        &format!("{} candidate", address),
+        &running,
+        &candidate,
+        io::stdout().is_terminal(),
+    );
+    print!("{}", diff);
+    Ok(())
+}
+
+pub(crate) fn get_config(
+    connection: &mut Connection,
+    datastore: Datastore,
+    filter: Option<Filter>,
+) -> Result<String> {
+    let rpc = Rpc::new(RpcContent::GetConfig {
+        source: Source { datastore },
+        filter,
+        with_defaults: None,
+    });
+    Ok(connection.execute(rpc)?.into_raw())
+}
+
+/// Re-indents contents of the reply element so formatting differences do not show in diff
+pub(crate) fn normalize(reply: &str) -> std::result::Result<String, quick_xml::Error> {
+    let mut reader = Reader::from_str(reply);
+    reader.config_mut().trim_text(true);
+    let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+    let mut depth = 0usize;
+    loop {
+        match reader.read_event()? {
+            Event::Eof => break,
+            Event::Decl(_) | Event::Comment(_) | Event::PI(_) | Event::DocType(_) => {}
+            Event::Start(start) => {
+                if depth > 0 {
+                    writer.write_event(Event::Start(start))?;
+                }
+                depth += 1;
+            }
+            Event::End(end) => {
+                depth = depth.saturating_sub(1);
+                if depth > 0 {
+                    writer.write_event(Event::End(end))?;
+                }
+            }
+            event if depth > 0 => writer.write_event(event)?,
+            _ => {}
+        }
+    }
+    let mut normalized = String::from_utf8_lossy(&writer.into_inner()).into_owned();
+    normalized.push('\n');
+    Ok(normalized)
+}
+
+pub(crate) fn unified_diff(
+    old_header: &str,
+    new_header: &str,
+    old: &str,
+    new: &str,
+    color: bool,
+) -> String {
+    let diff = TextDiff::from_lines(old, new)
+        .unified_diff()
+        .header(old_header, new_header)
+        .to_string();
+    if !color {
+        return diff;
+    }
+    diff.lines()
+        .map(|line| {
+            let color = match line.chars().next() {
+                Some('-') => RED,
+                Some('+') => GREEN,
+                Some('@') => CYAN,
+                _ => return format!("{}\n", line),
+            };
+            format!("{}{}{}\n", color, line, RESET)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_ignores_formatting() {
+        let compact = r#"<rpc-reply message-id="1"><data><system><hostname>router</hostname></system></data></rpc-reply>"#;
+        let indented = r#"<?xml version="1.0"?>
+<rpc-reply message-id="2">
+    <data>
+        <system>
+            <hostname>router</hostname>
+        </system>
+    </data>
+</rpc-reply>"#;
+        let expected =
+            "<data>\n  <system>\n    <hostname>router</hostname>\n  </system>\n</data>\n";
+        assert_eq!(normalize(compact).unwrap(), expected);
+        assert_eq!(normalize(indented).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_unified_diff() {
+        let running = "<data>\n  <hostname>old</hostname>\n</data>\n";
+        let candidate = "<data>\n  <hostname>new</hostname>\n</data>\n";
+        let expected = "--- host running
++++ host candidate
+@@ -1,3 +1,3 @@
+ <data>
+-  <hostname>old</hostname>
++  <hostname>new</hostname>
+ </data>
+";
+        assert_eq!(
+            unified_diff("host running", "host candidate", running, candidate, false),
+            expected
+        );
+    }
+}
@@ -0,0 +1,106 @@
+use super::diff::{get_config, normalize, unified_diff};
+use clap::Args;
+use netconf_rust::error::Result;
+use netconf_rust::message::{Datastore, Filter};
+use netconf_rust::Connection;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+#[derive(Debug, Args, Clone, Default)]
+pub(crate) struct DiffHostsArgs {
+    #[arg(short, long, default_value = "running")]
+    source: String,
+    #[arg(long, help = "Subtree filter XML applied on both hosts")]
+    subtree: Option<String>,
+    #[arg(
+        long,
+        conflicts_with = "subtree",
+        help = "XPath filter applied on both hosts"
+    )]
+    xpath: Option<String>,
+    #[arg(
+        short,
+        long,
+        conflicts_with_all = ["subtree", "xpath"],
+        help = "File with subtree filter XML applied on both hosts, read from stdin when '-'"
+    )]
+    file: Option<PathBuf>,
+}
+
+impl DiffHostsArgs {
+    /// Reads subtree filter file once before connecting to hosts
+    pub(crate) fn read_subtree(&mut self) -> io::Result<()> {
+        if let Some(file) = &self.file {
+            self.subtree = Some(super::read_input(Some(file))?);
+        }
+        Ok(())
+    }
+
+    fn filter(&self) -> Option<Filter> {
+        match (&self.subtree, &self.xpath) {
+            (Some(subtree), _) => Some(Filter::subtree(subtree)),
+            (_, Some(xpath)) => Some(Filter::xpath(xpath)),
+            _ => None,
+        }
+    }
+}
+
+/// Fetches normalized configuration of one host, compared by [`print_diff`] once
+/// both hosts are done
+pub(crate) fn run(args: &DiffHostsArgs, connection: &mut Connection) -> Result<String> {
+    let datastore = Datastore::from_str(&args.source)?;
+    Ok(normalize(&get_config(
+        connection,
+        datastore,
+        args.filter(),
+    )?)?)
+}
+
+pub(crate) fn print_diff(
+    args: &DiffHostsArgs,
+    (a, config_a): (&str, &str),
+    (b, config_b): (&str, &str),
+) {
+    if config_a == config_b {
+        log::info!("No differences in {} between {} and {}", args.source, a, b);
+        return;
+    }
+    let diff = unified_diff(
+        &format!("{} {}", a, args.source),
+        &format!("{} {}", b, args.source),
+        config_a,
+        config_b,
+        io::stdout().is_terminal(),
+    );
+    print!("{}", diff);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use std::{env, fs};
+
+    #[derive(Debug, Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: DiffHostsArgs,
+    }
+
+    #[test]
+    fn test_read_subtree() {
+        let file = env::temp_dir().join(format!("netconf-diff-hosts-{}.xml", std::process::id()));
+        fs::write(&file, "<system/>").unwrap();
+        let path = file.to_str().unwrap();
+        let mut args = Cli::try_parse_from(["diff-hosts", "-f", path])
+            .unwrap()
+            .args;
+        args.read_subtree().unwrap();
+        fs::remove_file(&file).unwrap();
+        assert_eq!(args.subtree.as_deref(), Some("<system/>"));
+        assert!(args.filter().is_some());
+
+        assert!(Cli::try_parse_from(["diff-hosts", "-f", path, "--xpath", "/system"]).is_err());
+    }
+}
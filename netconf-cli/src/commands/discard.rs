@@ -0,0 +1,8 @@
+use netconf_rust::error::Result;
+use netconf_rust::Connection;
+
+pub(crate) fn run(address: &str, connection: &mut Connection) -> Result<()> {
+    connection.discard_changes()?;
+    log::info!(target: address, "Discarded candidate changes");
+    Ok(())
+}
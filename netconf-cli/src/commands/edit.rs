@@ -0,0 +1,192 @@
+use crate::ssh::split_host_port;
+use clap::{Args, ValueEnum};
+use minijinja::{Environment, UndefinedBehavior};
+use netconf_rust::error::Result;
+use netconf_rust::message::{
+    Datastore, DefaultOperation, ErrorOption, RawXml, Rpc, RpcContent, Target,
+};
+use netconf_rust::Connection;
+use serde_json::{Map, Value};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+#[derive(Debug, Args, Clone, Default)]
+pub(crate) struct EditArgs {
+    #[arg(
+        short,
+        long,
+        help = "File with contents of the <config> element, read from stdin when omitted or '-'"
+    )]
+    file: Option<PathBuf>,
+    #[arg(
+        long,
+        conflicts_with = "file",
+        help = "Minijinja template of the payload, rendered separately for each host"
+    )]
+    template: Option<PathBuf>,
+    #[arg(
+        long,
+        requires = "template",
+        help = "YAML file with template variables, per host values under 'hosts' key"
+    )]
+    vars: Option<PathBuf>,
+    #[arg(long, default_value = "running")]
+    target: String,
+    #[arg(long, value_enum)]
+    default_operation: Option<DefaultOperationArg>,
+    #[arg(long, value_enum)]
+    error_option: Option<ErrorOptionArg>,
+
+    /// Payload read once before connecting to hosts
+    #[arg(skip)]
+    pub(crate) payload: String,
+    #[arg(skip)]
+    variables: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum DefaultOperationArg {
+    Merge,
+    Replace,
+    None,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ErrorOptionArg {
+    #[value(name = "stop-on-error")]
+    Stop,
+    #[value(name = "continue-on-error")]
+    Continue,
+    #[value(name = "rollback-on-error")]
+    Rollback,
+}
+
+impl EditArgs {
+    /// Reads payload, or template and variables when rendered per host
+    pub(crate) fn read_payload(&mut self) -> io::Result<()> {
+        let Some(template) = self.template.as_deref() else {
+            self.payload = super::read_input(self.file.as_deref())?;
+            return Ok(());
+        };
+        self.payload = fs::read_to_string(template)?;
+        environment()
+            .template_from_str(&self.payload)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        if let Some(vars) = self.vars.as_deref() {
+            let vars = fs::read_to_string(vars)?;
+            self.variables = match serde_yaml::from_str(&vars) {
+                Ok(Value::Object(variables)) => variables,
+                Ok(Value::Null) => Map::new(),
+                Ok(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "variables must be a mapping",
+                    ))
+                }
+                Err(err) => return Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+            };
+        }
+        Ok(())
+    }
+
+    /// Payload of host, template is rendered with common variables overridden by host ones.
+    /// Host variables are looked up by `address` as given, before ssh config resolves it.
+    fn render(&self, address: &str) -> std::result::Result<String, minijinja::Error> {
+        if self.template.is_none() {
+            return Ok(self.payload.clone());
+        }
+        let mut context = self.variables.clone();
+        let hosts = context.remove("hosts");
+        let name = split_host_port(address).map_or(address, |(name, _)| name);
+        let host_variables = hosts
+            .as_ref()
+            .and_then(|hosts| hosts.get(address).or_else(|| hosts.get(name)));
+        if let Some(Value::Object(host_variables)) = host_variables {
+            context.extend(host_variables.clone());
+        }
+        context.insert("host".to_string(), Value::String(name.to_string()));
+        environment().render_str(&self.payload, context)
+    }
+}
+
+/// Template environment failing on undefined variables instead of rendering them empty
+fn environment() -> Environment<'static> {
+    let mut environment = Environment::new();
+    environment.set_undefined_behavior(UndefinedBehavior::Strict);
+    environment
+}
+
+/// Edits configuration of host connected at `address`, `alias` is the host as given
+/// in inventory or on command line
+pub(crate) fn run(
+    address: &str,
+    alias: &str,
+    args: &EditArgs,
+    connection: &mut Connection,
+) -> Result<()> {
+    let payload = args
+        .render(alias)
+        .map_err(|err| io::Error::other(format!("could not render template: {}", err)))?;
+    let edit_config = Rpc::new(RpcContent::EditConfig {
+        target: Target {
+            datastore: Datastore::from_str(&args.target)?,
+        },
+        default_operation: args.default_operation.map(|op| match op {
+            DefaultOperationArg::Merge => DefaultOperation::Merge,
+            DefaultOperationArg::Replace => DefaultOperation::Replace,
+            DefaultOperationArg::None => DefaultOperation::None,
+        }),
+        test_option: None,
+        error_option: args.error_option.map(|op| match op {
+            ErrorOptionArg::Stop => ErrorOption::StopOnError,
+            ErrorOptionArg::Continue => ErrorOption::ContinueOnError,
+            ErrorOptionArg::Rollback => ErrorOption::RollbackOnError,
+        }),
+        config: RawXml(payload.trim().to_string()),
+    });
+    let resp = connection.execute(edit_config)?;
+    log::info!(target: address, "Edit-config rpc success");
+    log::trace!(target: address, "Response:\n{}", resp.pretty());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_host_variables() {
+        let variables = json!({
+            "domain": "example.com",
+            "hosts": {
+                "10.0.0.1": { "hostname": "core1" },
+                "10.0.0.2:830": { "hostname": "core2", "domain": "lab.example.com" },
+                "2001:db8::1": { "hostname": "core3" }
+            }
+        });
+        let args = EditArgs {
+            template: Some(PathBuf::from("system.xml.j2")),
+            payload: "<hostname>{{ hostname }}.{{ domain }}</hostname><!-- {{ host }} -->"
+                .to_string(),
+            variables: variables.as_object().unwrap().clone(),
+            ..Default::default()
+        };
+        assert_eq!(
+            args.render("10.0.0.1:830").unwrap(),
+            "<hostname>core1.example.com</hostname><!-- 10.0.0.1 -->"
+        );
+        assert_eq!(
+            args.render("10.0.0.2:830").unwrap(),
+            "<hostname>core2.lab.example.com</hostname><!-- 10.0.0.2 -->"
+        );
+        for address in ["2001:db8::1", "[2001:db8::1]:830"] {
+            assert_eq!(
+                args.render(address).unwrap(),
+                "<hostname>core3.example.com</hostname><!-- 2001:db8::1 -->"
+            );
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use clap::{ArgGroup, Args};
+use netconf_rust::error::Result;
+use netconf_rust::monitoring::Session;
+use netconf_rust::Connection;
+use std::time::Duration;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+#[derive(Debug, Args, Clone, Default)]
+#[command(group(ArgGroup::new("sessions").required(true)))]
+pub(crate) struct KillSessionArgs {
+    #[arg(long, group = "sessions", help = "Session to kill")]
+    session_id: Option<u64>,
+    #[arg(
+        long,
+        group = "sessions",
+        value_parser = humantime::parse_duration,
+        help = "Kills all other sessions logged in longer than duration, eg. 12h, read from netconf-state"
+    )]
+    stale: Option<Duration>,
+}
+
+pub(crate) fn run(
+    address: &str,
+    args: &KillSessionArgs,
+    connection: &mut Connection,
+) -> Result<()> {
+    let session_ids = match (args.session_id, args.stale) {
+        (Some(session_id), _) => vec![session_id],
+        (_, Some(max_age)) => {
+            let sessions = connection.get_sessions()?;
+            let stale = stale_sessions(
+                &sessions,
+                connection.session_id(),
+                max_age,
+                OffsetDateTime::now_utc(),
+            );
+            if stale.is_empty() {
+                log::info!(target: address, "No sessions older than {}", humantime::format_duration(max_age));
+            }
+            stale
+        }
+        _ => Vec::new(),
+    };
+    // Kills the remaining sessions also after a failure, returning the last error
+    let mut result = Ok(());
+    for session_id in session_ids {
+        match connection.kill_session(session_id) {
+            Ok(()) => log::info!(target: address, "Killed session {}", session_id),
+            Err(err) => {
+                log::error!(target: address, "Kill-session {} error: {}", session_id, err);
+                result = Err(err);
+            }
+        }
+    }
+    result
+}
+
+/// Sessions other than own logged in longer than `max_age` before `now`,
+/// sessions with unparseable login time are skipped
+fn stale_sessions(
+    sessions: &[Session],
+    own_session_id: u64,
+    max_age: Duration,
+    now: OffsetDateTime,
+) -> Vec<u64> {
+    sessions
+        .iter()
+        .filter(|session| session.session_id != own_session_id)
+        .filter(|session| {
+            OffsetDateTime::parse(&session.login_time, &Rfc3339)
+                .is_ok_and(|login_time| now - login_time > max_age)
+        })
+        .map(|session| session.session_id)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(session_id: u64, login_time: &str) -> Session {
+        Session {
+            session_id,
+            transport: "ncm:netconf-ssh".to_string(),
+            username: "admin".to_string(),
+            source_host: None,
+            login_time: login_time.to_string(),
+            in_rpcs: 0,
+            in_bad_rpcs: 0,
+            out_rpc_errors: 0,
+            out_notifications: 0,
+        }
+    }
+
+    #[test]
+    fn test_stale_sessions() {
+        let sessions = [
+            session(1, "2024-05-01T08:00:00Z"),
+            session(2, "2024-05-01T11:30:00Z"),
+            session(3, "2024-05-01T08:00:00Z"),
+            session(4, "yesterday"),
+        ];
+        let now = OffsetDateTime::parse("2024-05-01T12:00:00Z", &Rfc3339).unwrap();
+        let stale = stale_sessions(&sessions, 3, Duration::from_secs(3600), now);
+        assert_eq!(stale, vec![1]);
+    }
+}
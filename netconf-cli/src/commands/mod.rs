@@ -0,0 +1,59 @@
+use std::fs;
+use std::io;
+use std::io::{IsTerminal, Read, Write};
+use std::path::Path;
+
+pub(crate) mod backup;
+pub(crate) mod capabilities;
+pub(crate) mod commit;
+pub(crate) mod copy;
+pub(crate) mod diff;
+pub(crate) mod diff_hosts;
+pub(crate) mod discard;
+pub(crate) mod edit;
+pub(crate) mod kill_session;
+pub(crate) mod notification;
+pub(crate) mod rpc;
+pub(crate) mod snapshot;
+pub(crate) mod watch;
+
+/// Asks yes/no question on stdout, or on stderr in porcelain mode, anything but yes is `false`
+pub(crate) fn confirm(question: &str) -> io::Result<bool> {
+    // Stdin may carry the payload, answer would be read from it
+    if !io::stdin().is_terminal() {
+        return Err(io::Error::other(
+            "stdin is not a terminal, confirm with --yes",
+        ));
+    }
+    if crate::output::porcelain() {
+        eprint!("{} [y/N] ", question);
+    } else {
+        print!("{} [y/N] ", question);
+        io::stdout().flush()?;
+    }
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// Reads file, or stdin when path is not given or is `-`
+pub(crate) fn read_input(path: Option<&Path>) -> io::Result<String> {
+    match path {
+        Some(path) if path.as_os_str() != "-" => fs::read_to_string(path),
+        _ => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input)?;
+            Ok(input)
+        }
+    }
+}
+
+/// File name of host address, eg. `[2001:db8::1]:830` becomes `_2001_db8__1__830`
+fn file_name(host: &str) -> String {
+    host.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' => c,
+            _ => '_',
+        })
+        .collect()
+}
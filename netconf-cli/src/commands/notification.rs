@@ -0,0 +1,289 @@
+use crate::output;
+use crate::retry::{self, Retry};
+use clap::Args;
+use netconf_rust::error::{Error, Result};
+use netconf_rust::message::prettify;
+use netconf_rust::Connection;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+#[derive(Debug, Args, Clone, Default)]
+pub(crate) struct NotificationArgs {
+    #[arg(long, help = "Event stream, default NETCONF stream when omitted")]
+    stream: Option<String>,
+    #[arg(
+        long,
+        value_parser = parse_date_time,
+        help = "Replays notifications logged since RFC 3339 date-time"
+    )]
+    start_time: Option<OffsetDateTime>,
+    #[arg(
+        long,
+        value_parser = parse_date_time,
+        help = "Ends subscription at RFC 3339 date-time"
+    )]
+    stop_time: Option<OffsetDateTime>,
+    #[arg(
+        long,
+        conflicts_with = "stop_time",
+        value_parser = humantime::parse_duration,
+        help = "Ends subscription after duration, eg. 10m or 1h30m"
+    )]
+    duration: Option<Duration>,
+    #[arg(
+        long,
+        help = "Writes each notification to a timestamped file in directory instead of logging"
+    )]
+    out_dir: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Reconnects with backoff when session fails, replaying notifications since the last received one"
+    )]
+    reconnect: bool,
+    #[arg(
+        long,
+        requires = "reconnect",
+        value_parser = humantime::parse_duration,
+        help = "Reconnects when nothing is received within duration, eg. 30m [default: 5m]"
+    )]
+    idle_timeout: Option<Duration>,
+}
+
+impl NotificationArgs {
+    /// Stop time of subscription, duration is counted from now
+    fn stop_time(&self) -> Option<OffsetDateTime> {
+        match self.duration {
+            Some(duration) => Some(OffsetDateTime::now_utc() + duration),
+            None => self.stop_time,
+        }
+    }
+
+    /// Read timeout of the session with `--reconnect`, dropped sessions are not
+    /// noticed otherwise while waiting for notifications
+    fn idle_timeout(&self) -> Option<Duration> {
+        self.reconnect
+            .then(|| self.idle_timeout.unwrap_or(IDLE_TIMEOUT))
+    }
+}
+
+fn parse_date_time(value: &str) -> std::result::Result<OffsetDateTime, time::error::Parse> {
+    OffsetDateTime::parse(value, &Rfc3339)
+}
+
+/// File name of notification from host address and event time, safe on all platforms
+fn file_name(address: &str, event_time: &str, sequence: usize) -> String {
+    let sanitize = |value: &str| value.replace([':', '[', ']', '/'], "-");
+    format!(
+        "{}_{}_{}.xml",
+        sanitize(address),
+        sanitize(event_time),
+        sequence
+    )
+}
+
+/// Delay before first reconnect attempt, doubled after each failed attempt
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+const IDLE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Why listening on one session ended
+enum Ended {
+    Completed,
+    Failed(Error),
+}
+
+/// Subscribes and handles notifications. With `--reconnect`, a failed session is replaced
+/// by one opened with `reconnect`, subscribing again from the event time of the last
+/// received notification, skipping replayed notifications already handled.
+pub(crate) fn run(
+    address: &str,
+    args: &NotificationArgs,
+    connection: &mut Connection,
+    mut reconnect: impl FnMut() -> Result<Connection>,
+) -> Result<()> {
+    if let Some(dir) = &args.out_dir {
+        fs::create_dir_all(dir)?;
+    }
+    let stop_time = args.stop_time();
+    let mut last_event_time = None;
+    let mut sequence = 0;
+    loop {
+        let start_time = last_event_time.or(args.start_time);
+        let ended = listen(
+            address,
+            args,
+            connection,
+            start_time,
+            stop_time,
+            &mut last_event_time,
+            &mut sequence,
+        )?;
+        let err = match ended {
+            Ended::Completed => return Ok(()),
+            Ended::Failed(err) => err,
+        };
+        if !args.reconnect {
+            return Err(err);
+        }
+        match (&err, args.idle_timeout()) {
+            (Error::Timeout, Some(idle_timeout)) => log::warn!(
+                target: address,
+                "Nothing received in {}, reconnecting",
+                humantime::format_duration(idle_timeout)
+            ),
+            _ => log::error!(target: address, "Session failed: {}", err),
+        }
+        if stop_time.is_some_and(|stop_time| stop_time <= OffsetDateTime::now_utc()) {
+            return Ok(());
+        }
+        // Gives up when the subscription would have ended before the next attempt
+        let retryable = |err: &Error, delay: Duration| {
+            retry::is_retryable(err)
+                && stop_time.is_none_or(|stop_time| OffsetDateTime::now_utc() + delay < stop_time)
+        };
+        *connection =
+            Retry::new(u32::MAX, RECONNECT_DELAY).run_if(address, retryable, &mut reconnect)?;
+        log::info!(target: address, "Reconnected");
+    }
+}
+
+/// Listens until subscription completes or session fails, updating event time of the last
+/// handled notification. Replayed notifications up to that time are skipped, since
+/// replay includes the notification at start time.
+fn listen(
+    address: &str,
+    args: &NotificationArgs,
+    connection: &mut Connection,
+    start_time: Option<OffsetDateTime>,
+    stop_time: Option<OffsetDateTime>,
+    last_event_time: &mut Option<OffsetDateTime>,
+    sequence: &mut usize,
+) -> Result<Ended> {
+    let stream = args.stream.as_deref();
+    let mut subscribed = connection.create_subscription_between(stream, start_time, stop_time);
+    // Start time set after reconnect fails on streams without replay support
+    if let (Err(Error::Netconf(err)), Some(_), None) = (&subscribed, start_time, args.start_time) {
+        log::warn!(target: address, "Could not replay missed notifications: {}", err);
+        subscribed = connection.create_subscription_between(stream, None, stop_time);
+    }
+    match subscribed {
+        // Refused subscription is not retried on new session
+        Err(err @ Error::Netconf(_)) => return Err(err),
+        Err(err) => return Ok(Ended::Failed(err)),
+        Ok(()) => {}
+    }
+    log::info!(target: address, "Subscribed to notifications");
+    // Notifications may be arbitrarily far apart, so without reconnect there is no timeout
+    connection.set_timeout(args.idle_timeout());
+
+    let handled = *last_event_time;
+    for notification in connection.notifications() {
+        let notification = match notification {
+            Ok(notification) => notification,
+            Err(err) => return Ok(Ended::Failed(err)),
+        };
+        if notification.is_complete() {
+            log::info!(target: address, "Subscription completed");
+            return Ok(Ended::Completed);
+        }
+        if let Ok(event_time) = parse_date_time(notification.event_time()) {
+            if handled.is_some_and(|handled| event_time <= handled) {
+                log::debug!(target: address, "Skipped replayed notification of {}", notification.event_time());
+                continue;
+            }
+            *last_event_time = Some(event_time);
+        }
+        *sequence += 1;
+        let sequence = *sequence - 1;
+        match &args.out_dir {
+            Some(dir) => {
+                let path = dir.join(file_name(address, notification.event_time(), sequence));
+                fs::write(&path, notification.raw().trim())?;
+                log::debug!(target: address, "Wrote notification to {}", path.display());
+            }
+            None => {
+                let notification = prettify(notification.raw());
+                output::print_text(address, log::Level::Info, "Notification", notification);
+            }
+        }
+    }
+    Ok(Ended::Failed(Error::SessionClosed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use netconf_rust::transport::mock::MockTransport;
+    use std::env;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(
+            file_name("[2001:db8::1]:830", "2024-05-01T10:00:00.12Z", 3),
+            "-2001-db8--1--830_2024-05-01T10-00-00.12Z_3.xml"
+        );
+    }
+
+    /// Session receiving notifications of `event_times` after subscribing, then timing
+    /// out, or completing the subscription when `complete`
+    fn session(event_times: &[&str], complete: bool) -> (Connection, Arc<Mutex<Vec<String>>>) {
+        let mut transport = MockTransport::scripted([("<create-subscription", "<ok/>")]);
+        let notification = |body: String| {
+            format!(
+                r#"<notification xmlns="urn:ietf:params:xml:ns:netconf:notification:1.0">{}</notification>"#,
+                body
+            )
+        };
+        for event_time in event_times {
+            transport.push_message(notification(format!(
+                r#"<eventTime>{}</eventTime><event xmlns="urn:example:event"/>"#,
+                event_time
+            )));
+        }
+        if complete {
+            transport.push_message(notification(
+                "<eventTime>2024-05-01T10:05:00Z</eventTime><notificationComplete/>".to_string(),
+            ));
+        }
+        let sent = transport.sent();
+        (Connection::new(transport).unwrap(), sent)
+    }
+
+    #[test]
+    fn test_reconnect_skips_replayed() {
+        let dir = env::temp_dir().join(format!("netconf-notifications-{}", std::process::id()));
+        let args = NotificationArgs {
+            out_dir: Some(dir.clone()),
+            reconnect: true,
+            ..Default::default()
+        };
+        let (mut connection, _) = session(&["2024-05-01T10:00:00Z", "2024-05-01T10:01:00Z"], false);
+        let (reconnected, sent) = session(&["2024-05-01T10:01:00Z", "2024-05-01T10:02:00Z"], true);
+        let mut reconnected = Some(reconnected);
+        run("r1", &args, &mut connection, || {
+            Ok(reconnected.take().unwrap())
+        })
+        .unwrap();
+
+        let mut files: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            files,
+            [
+                "r1_2024-05-01T10-00-00Z_0.xml",
+                "r1_2024-05-01T10-01-00Z_1.xml",
+                "r1_2024-05-01T10-02-00Z_2.xml"
+            ]
+        );
+        let sent = sent.lock().unwrap();
+        assert!(sent[1].contains("<startTime>2024-05-01T10:01:00Z</startTime>"));
+    }
+}
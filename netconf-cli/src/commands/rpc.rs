@@ -0,0 +1,43 @@
+use crate::output::{self, OutputFormat};
+use clap::Args;
+use netconf_rust::error::Result;
+use netconf_rust::message::{RawXml, Rpc, RpcContent};
+use netconf_rust::Connection;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Debug, Args, Clone, Default)]
+pub(crate) struct RpcArgs {
+    #[arg(
+        short,
+        long,
+        help = "File with the operation element, read from stdin when omitted or '-'"
+    )]
+    file: Option<PathBuf>,
+
+    /// Operation read once before connecting to hosts
+    #[arg(skip)]
+    pub(crate) operation: String,
+}
+
+impl RpcArgs {
+    pub(crate) fn read_operation(&mut self) -> io::Result<()> {
+        self.operation = super::read_input(self.file.as_deref())?;
+        Ok(())
+    }
+}
+
+pub(crate) fn run(
+    address: &str,
+    args: &RpcArgs,
+    format: OutputFormat,
+    connection: &mut Connection,
+) -> Result<()> {
+    let rpc = Rpc::new(RpcContent::Raw(RawXml(args.operation.trim().to_string())));
+    let resp = connection.execute(rpc)?;
+    match format {
+        OutputFormat::Text => output::print_text(address, log::Level::Info, "Reply", resp.pretty()),
+        OutputFormat::Json => output::print_json(address, &resp),
+    }
+    Ok(())
+}
@@ -0,0 +1,253 @@
+use super::diff::{get_config, normalize, unified_diff};
+use super::file_name;
+use clap::{Args, Subcommand};
+use dirs::data_dir;
+use netconf_rust::error::Result;
+use netconf_rust::message::{Datastore, Filter};
+use netconf_rust::Connection;
+use serde_derive::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+#[derive(Debug, Args, Clone)]
+pub(crate) struct SnapshotArgs {
+    #[command(subcommand)]
+    command: SnapshotCommand,
+    #[arg(
+        long,
+        env = "NETCONF_SNAPSHOT_DIR",
+        help = "Directory of snapshots [default: netconf/snapshots in user data directory]"
+    )]
+    dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Subcommand, Clone)]
+enum SnapshotCommand {
+    #[command(about = "Stores configuration of each host as named snapshot")]
+    Save(SnapshotTarget),
+    #[command(about = "Lists stored snapshots")]
+    List,
+    #[command(about = "Diff of live configuration against named snapshot")]
+    Diff(SnapshotTarget),
+}
+
+#[derive(Debug, Args, Clone)]
+struct SnapshotTarget {
+    #[arg(value_parser = parse_name, help = "Snapshot name, eg. baseline")]
+    name: String,
+    #[arg(short, long, default_value = "running")]
+    source: String,
+    #[arg(long, help = "Subtree filter XML")]
+    subtree: Option<String>,
+    #[arg(long, conflicts_with = "subtree", help = "XPath filter")]
+    xpath: Option<String>,
+    #[arg(
+        short,
+        long,
+        conflicts_with_all = ["subtree", "xpath"],
+        help = "File with subtree filter XML, read from stdin when '-'"
+    )]
+    file: Option<PathBuf>,
+}
+
+impl SnapshotTarget {
+    fn read_subtree(&mut self) -> io::Result<()> {
+        if let Some(file) = &self.file {
+            self.subtree = Some(super::read_input(Some(file))?);
+        }
+        Ok(())
+    }
+
+    fn filter(&self) -> Option<Filter> {
+        match (&self.subtree, &self.xpath) {
+            (Some(subtree), _) => Some(Filter::subtree(subtree)),
+            (_, Some(xpath)) => Some(Filter::xpath(xpath)),
+            _ => None,
+        }
+    }
+
+    /// Normalized configuration of `source`, fails if datastore is unknown, get-config
+    /// fails or the reply is not well-formed XML
+    fn fetch(&self, connection: &mut Connection) -> Result<String> {
+        let datastore = Datastore::from_str(&self.source)?;
+        Ok(normalize(&get_config(
+            connection,
+            datastore,
+            self.filter(),
+        )?)?)
+    }
+}
+
+/// Stored next to the configuration of each host
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Metadata {
+    host: String,
+    datastore: String,
+    /// RFC 3339 time of saving
+    timestamp: String,
+}
+
+impl SnapshotArgs {
+    /// Listing is local, other commands connect to hosts
+    pub(crate) fn needs_hosts(&self) -> bool {
+        !matches!(self.command, SnapshotCommand::List)
+    }
+
+    /// Reads subtree filter file once before connecting to hosts
+    pub(crate) fn read_subtree(&mut self) -> io::Result<()> {
+        match &mut self.command {
+            SnapshotCommand::Save(target) | SnapshotCommand::Diff(target) => target.read_subtree(),
+            SnapshotCommand::List => Ok(()),
+        }
+    }
+
+    fn dir(&self) -> io::Result<PathBuf> {
+        match &self.dir {
+            Some(dir) => Ok(dir.clone()),
+            None => data_dir()
+                .map(|dir| dir.join("netconf").join("snapshots"))
+                .ok_or_else(|| io::Error::other("no user data directory, use --dir")),
+        }
+    }
+
+    /// Prints snapshots with their hosts, datastores and timestamps
+    pub(crate) fn list(&self) -> io::Result<()> {
+        for (name, metadata) in snapshots(&self.dir()?)? {
+            println!(
+                "{}\t{}\t{}\t{}",
+                name, metadata.host, metadata.datastore, metadata.timestamp
+            );
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn run(address: &str, args: &SnapshotArgs, connection: &mut Connection) -> Result<()> {
+    let dir = args.dir()?;
+    match &args.command {
+        SnapshotCommand::Save(target) => {
+            let config = target.fetch(connection)?;
+            let metadata = Metadata {
+                host: address.to_string(),
+                datastore: target.source.clone(),
+                timestamp: OffsetDateTime::now_utc().format(&Rfc3339)?,
+            };
+            save(&dir.join(&target.name), &metadata, &config)?;
+            log::info!(target: address, "Saved snapshot {}", target.name);
+        }
+        SnapshotCommand::Diff(target) => {
+            let (metadata, baseline) = load(&dir.join(&target.name), address)?;
+            let config = target.fetch(connection)?;
+            if config == baseline {
+                log::info!(target: address, "No changes since snapshot {}", target.name);
+                return Ok(());
+            }
+            let diff = unified_diff(
+                &format!("{} {} {}", address, metadata.datastore, target.name),
+                &format!("{} {}", address, target.source),
+                &baseline,
+                &config,
+                io::stdout().is_terminal(),
+            );
+            print!("{}", diff);
+        }
+        SnapshotCommand::List => {}
+    }
+    Ok(())
+}
+
+fn save(snapshot: &Path, metadata: &Metadata, config: &str) -> io::Result<()> {
+    fs::create_dir_all(snapshot)?;
+    let name = file_name(&metadata.host);
+    fs::write(snapshot.join(format!("{}.xml", name)), config)?;
+    let metadata = serde_json::to_string_pretty(metadata)?;
+    fs::write(snapshot.join(format!("{}.json", name)), metadata)
+}
+
+fn load(snapshot: &Path, host: &str) -> io::Result<(Metadata, String)> {
+    let name = file_name(host);
+    let metadata = fs::read_to_string(snapshot.join(format!("{}.json", name)))?;
+    let config = fs::read_to_string(snapshot.join(format!("{}.xml", name)))?;
+    Ok((serde_json::from_str(&metadata)?, config))
+}
+
+/// Snapshot name is a directory below snapshot directory, so it must not contain a path
+fn parse_name(value: &str) -> std::result::Result<String, String> {
+    if matches!(value, "" | "." | "..") || value.contains(['/', '\\']) {
+        return Err("must be a file name without path separators".to_string());
+    }
+    Ok(value.to_string())
+}
+
+/// Metadata of every host in every snapshot, sorted by snapshot name and host
+fn snapshots(dir: &Path) -> io::Result<Vec<(String, Metadata)>> {
+    let mut snapshots = Vec::new();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(snapshots),
+        Err(err) => return Err(err),
+    };
+    for entry in entries {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if !path.is_dir() {
+            continue;
+        }
+        for file in fs::read_dir(&path)? {
+            let file = file?.path();
+            if file
+                .extension()
+                .is_some_and(|extension| extension == "json")
+            {
+                let metadata = serde_json::from_str(&fs::read_to_string(&file)?)?;
+                snapshots.push((name.to_string(), metadata));
+            }
+        }
+    }
+    snapshots.sort_by(|(a, a_metadata), (b, b_metadata)| {
+        (a, &a_metadata.host).cmp(&(b, &b_metadata.host))
+    });
+    Ok(snapshots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = env::temp_dir().join(format!("netconf-snapshots-{}", std::process::id()));
+        let metadata = Metadata {
+            host: "[2001:db8::1]:830".to_string(),
+            datastore: "running".to_string(),
+            timestamp: "2023-11-14T22:13:20Z".to_string(),
+        };
+        save(&dir.join("baseline"), &metadata, "<data/>\n").unwrap();
+        assert!(dir.join("baseline/_2001_db8__1__830.xml").is_file());
+
+        let (loaded, config) = load(&dir.join("baseline"), "[2001:db8::1]:830").unwrap();
+        assert_eq!(loaded, metadata);
+        assert_eq!(config, "<data/>\n");
+        let snapshots = snapshots(&dir).unwrap();
+        assert_eq!(snapshots, vec![("baseline".to_string(), metadata)]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_name() {
+        assert_eq!(
+            parse_name("baseline-2024.05"),
+            Ok("baseline-2024.05".to_string())
+        );
+        for name in ["", ".", "..", "../etc", "a/b", "a\\b", "/tmp"] {
+            assert!(parse_name(name).is_err(), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use super::diff::{normalize, unified_diff};
+use clap::Args;
+use netconf_rust::error::Result;
+use netconf_rust::message::{Datastore, Filter, Rpc, RpcContent, Source};
+use netconf_rust::Connection;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+#[derive(Debug, Args, Clone)]
+pub(crate) struct WatchArgs {
+    #[arg(
+        long,
+        default_value = "30s",
+        value_parser = humantime::parse_duration,
+        help = "Time between requests, eg. 30s or 5m"
+    )]
+    interval: Duration,
+    #[arg(
+        short,
+        long,
+        help = "Runs get-config from datastore instead of get of state and configuration"
+    )]
+    source: Option<String>,
+    #[arg(long, help = "Subtree filter XML")]
+    subtree: Option<String>,
+    #[arg(long, conflicts_with = "subtree", help = "XPath filter")]
+    xpath: Option<String>,
+    #[arg(
+        short,
+        long,
+        conflicts_with_all = ["subtree", "xpath"],
+        help = "File with subtree filter XML, read from stdin when '-'"
+    )]
+    file: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Stops after number of requests, runs until interrupted by default"
+    )]
+    count: Option<u32>,
+}
+
+impl WatchArgs {
+    /// Reads subtree filter file once before connecting to hosts
+    pub(crate) fn read_subtree(&mut self) -> io::Result<()> {
+        if let Some(file) = &self.file {
+            self.subtree = Some(super::read_input(Some(file))?);
+        }
+        Ok(())
+    }
+
+    fn rpc(&self) -> Result<Rpc> {
+        let filter = match (&self.subtree, &self.xpath) {
+            (Some(subtree), _) => Some(Filter::subtree(subtree)),
+            (_, Some(xpath)) => Some(Filter::xpath(xpath)),
+            _ => None,
+        };
+        let content = match &self.source {
+            Some(source) => RpcContent::GetConfig {
+                source: Source {
+                    datastore: Datastore::from_str(source)?,
+                },
+                filter,
+                with_defaults: None,
+            },
+            None => RpcContent::Get {
+                filter,
+                with_defaults: None,
+            },
+        };
+        Ok(Rpc::new(content))
+    }
+}
+
+/// Repeats the request every interval, printing diff of each reply against the previous one
+pub(crate) fn run(address: &str, args: &WatchArgs, connection: &mut Connection) -> Result<()> {
+    let mut previous: Option<(String, String)> = None;
+    let mut requests = 0;
+    while args.count.is_none_or(|count| requests < count) {
+        if requests > 0 {
+            thread::sleep(args.interval);
+        }
+        requests += 1;
+        let current = normalize(connection.execute(args.rpc()?)?.raw())?;
+        let timestamp = OffsetDateTime::now_utc().format(&Rfc3339)?;
+        match &previous {
+            None => {
+                log::info!(target: address, "Watching every {}", humantime::format_duration(args.interval))
+            }
+            Some((_, before)) if *before == current => {}
+            Some((before_timestamp, before)) => {
+                let diff = unified_diff(
+                    &format!("{} {}", address, before_timestamp),
+                    &format!("{} {}", address, timestamp),
+                    before,
+                    &current,
+                    io::stdout().is_terminal(),
+                );
+                print!("{}", diff);
+            }
+        }
+        previous = Some((timestamp, current));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use netconf_rust::transport::mock::MockTransport;
+
+    #[derive(Debug, Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: WatchArgs,
+    }
+
+    fn parse(args: &[&str]) -> clap::error::Result<WatchArgs> {
+        Cli::try_parse_from(std::iter::once("watch").chain(args.iter().copied()))
+            .map(|cli| cli.args)
+    }
+
+    #[test]
+    fn test_parse_watch_args() {
+        let args = parse(&[
+            "--source",
+            "running",
+            "--subtree",
+            "<system/>",
+            "--interval",
+            "5m",
+        ])
+        .unwrap();
+        assert_eq!(args.interval, Duration::from_secs(300));
+        let rpc = args.rpc().unwrap().to_string();
+        assert!(rpc.contains("<get-config>"));
+        assert!(rpc.contains("<system/>"));
+
+        let rpc = parse(&["--xpath", "/system"])
+            .unwrap()
+            .rpc()
+            .unwrap()
+            .to_string();
+        assert!(rpc.contains("<get>"));
+        assert!(rpc.contains(r#"select="/system""#));
+
+        assert!(parse(&["--subtree", "<system/>", "--xpath", "/system"]).is_err());
+        assert!(parse(&["--source", "runing"]).unwrap().rpc().is_err());
+
+        let file = std::env::temp_dir().join(format!("netconf-watch-{}.xml", std::process::id()));
+        std::fs::write(&file, "<interfaces/>").unwrap();
+        let mut args = parse(&["-f", file.to_str().unwrap()]).unwrap();
+        args.read_subtree().unwrap();
+        std::fs::remove_file(&file).unwrap();
+        assert!(args.rpc().unwrap().to_string().contains("<interfaces/>"));
+        assert!(parse(&["-f", "-", "--xpath", "/system"]).is_err());
+    }
+
+    #[test]
+    fn test_run() {
+        let transport = MockTransport::scripted([
+            ("<get/>", "<data><a>1</a></data>"),
+            ("<get/>", "<data><a>2</a></data>"),
+            ("<close-session/>", "<ok/>"),
+        ]);
+        let sent = transport.sent();
+        let mut connection = Connection::new(transport).unwrap();
+        let args = parse(&["--count", "2", "--interval", "0s"]).unwrap();
+        run("r1", &args, &mut connection).unwrap();
+        assert_eq!(sent.lock().unwrap().len(), 3);
+
+        let args = parse(&["--source", "runing", "--count", "1"]).unwrap();
+        assert!(run("r1", &args, &mut connection).is_err());
+        assert_eq!(sent.lock().unwrap().len(), 3);
+    }
+}
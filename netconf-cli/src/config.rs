@@ -0,0 +1,134 @@
+use crate::output::OutputFormat;
+use crate::WithDefaultsArg;
+use dirs::home_dir;
+use serde_derive::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Defaults read from `~/.config/netconf/config.toml`, or file in `NETCONF_CONFIG`.
+/// Command line flags and environment variables take precedence.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub(crate) struct CliConfig {
+    pub(crate) username: Option<String>,
+    pub(crate) with_defaults: Option<WithDefaultsArg>,
+    pub(crate) parallel: Option<u16>,
+    pub(crate) format: Option<OutputFormat>,
+    pub(crate) groups: BTreeMap<String, HostGroup>,
+}
+
+/// Overrides for hosts matching any of `hosts` patterns, `*` matches any characters
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub(crate) struct HostGroup {
+    pub(crate) hosts: Vec<String>,
+    pub(crate) username: Option<String>,
+    pub(crate) port: Option<u16>,
+    pub(crate) timeout: Option<u64>,
+    /// Jump hosts in `ProxyJump` format, eg. `admin@bastion:22`
+    pub(crate) jump_host: Option<String>,
+    /// Password of jump hosts not accepting keys, host passwords are never sent to them
+    pub(crate) jump_password: Option<String>,
+}
+
+impl CliConfig {
+    /// Reads config file, missing file results in empty config
+    pub(crate) fn new() -> io::Result<CliConfig> {
+        let path = match env::var_os("NETCONF_CONFIG") {
+            Some(path) => PathBuf::from(path),
+            None => match home_dir() {
+                Some(home) => home.join(".config").join("netconf").join("config.toml"),
+                None => return Ok(CliConfig::default()),
+            },
+        };
+        match fs::read_to_string(&path) {
+            Ok(content) => toml::from_str(&content).map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: {}", path.display(), err),
+                )
+            }),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(CliConfig::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// First group, in name order, with pattern matching host
+    pub(crate) fn group(&self, host: &str) -> Option<&HostGroup> {
+        self.groups.values().find(|group| {
+            group
+                .hosts
+                .iter()
+                .any(|pattern| matches_pattern(pattern, host))
+        })
+    }
+}
+
+fn matches_pattern(pattern: &str, host: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == host,
+        Some((prefix, rest)) => {
+            let Some(host) = host.strip_prefix(prefix) else {
+                return false;
+            };
+            (0..=host.len())
+                .filter(|&i| host.is_char_boundary(i))
+                .any(|i| matches_pattern(rest, &host[i..]))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config: CliConfig = toml::from_str(
+            r#"
+username = "admin"
+with-defaults = "report-all"
+parallel = 32
+format = "json"
+
+[groups.core]
+hosts = ["core-*", "10.0.0.1"]
+username = "netops"
+port = 22
+jump-host = "admin@bastion-1,bastion-2:2222"
+jump-password = "bastion"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.username.as_deref(), Some("admin"));
+        assert!(matches!(
+            config.with_defaults,
+            Some(WithDefaultsArg::ReportAll)
+        ));
+        assert_eq!(config.parallel, Some(32));
+        assert!(matches!(config.format, Some(OutputFormat::Json)));
+
+        let group = config.group("core-r1.example.com").unwrap();
+        assert_eq!(group.username.as_deref(), Some("netops"));
+        assert_eq!(group.port, Some(22));
+        assert_eq!(
+            group.jump_host.as_deref(),
+            Some("admin@bastion-1,bastion-2:2222")
+        );
+        assert_eq!(group.jump_password.as_deref(), Some("bastion"));
+        assert!(config.group("10.0.0.1").is_some());
+        assert!(config.group("edge-r1").is_none());
+    }
+
+    #[test]
+    fn test_matches_pattern() {
+        assert!(matches_pattern("*", "router"));
+        assert!(matches_pattern("core-*.lab", "core-r1.lab"));
+        assert!(!matches_pattern("core-*.lab", "core-r1.prod"));
+        assert!(matches_pattern("10.0.*.1", "10.0.12.1"));
+        assert!(!matches_pattern("router", "router2"));
+    }
+}
@@ -0,0 +1,312 @@
+//! Values selected from replies with simple XPath location paths, printed as table or CSV
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::str::FromStr;
+
+/// Column of `--extract [NAME=]PATH`. Path is absolute from the contents of `<data>`,
+/// e.g. `/interfaces/interface[name='eth0']/mtu`, `//` selects descendants at any depth.
+/// Prefixes are ignored and predicates only compare child element text.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Extract {
+    pub(crate) name: String,
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Step {
+    /// Matches at any depth below the previous step
+    descendant: bool,
+    /// Local name, `*` matches any element
+    name: String,
+    /// Child element name and its text
+    predicate: Option<(String, String)>,
+}
+
+impl FromStr for Extract {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (name, path) = match value.split_once('=') {
+            Some((name, path)) if !name.contains(['/', '[']) => (name.to_string(), path),
+            _ => (value.to_string(), value),
+        };
+        let Some(mut rest) = path.strip_prefix('/') else {
+            return Err("expected absolute path starting with /".to_string());
+        };
+        let mut steps = Vec::new();
+        while !rest.is_empty() {
+            let descendant = match rest.strip_prefix('/') {
+                Some(stripped) => {
+                    rest = stripped;
+                    true
+                }
+                None => false,
+            };
+            let end = step_end(rest);
+            steps.push(parse_step(&rest[..end], descendant)?);
+            rest = rest[end..].strip_prefix('/').unwrap_or(&rest[end..]);
+        }
+        if steps.is_empty() {
+            return Err("empty path".to_string());
+        }
+        Ok(Extract { name, steps })
+    }
+}
+
+/// Index of `/` ending the first step, ignoring ones inside predicates
+fn step_end(path: &str) -> usize {
+    let mut quote = None;
+    let mut in_predicate = false;
+    for (index, c) in path.char_indices() {
+        match (c, quote) {
+            ('\'' | '"', None) if in_predicate => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            ('[', None) => in_predicate = true,
+            (']', None) => in_predicate = false,
+            ('/', None) if !in_predicate => return index,
+            _ => {}
+        }
+    }
+    path.len()
+}
+
+fn parse_step(step: &str, descendant: bool) -> Result<Step, String> {
+    let (name, predicate) = match step.split_once('[') {
+        Some((name, predicate)) => {
+            let predicate = predicate
+                .strip_suffix(']')
+                .and_then(|predicate| predicate.split_once('='))
+                .map(|(child, value)| {
+                    let value = value.trim();
+                    let unquoted = value
+                        .strip_prefix('\'')
+                        .and_then(|v| v.strip_suffix('\''))
+                        .or_else(|| value.strip_prefix('"').and_then(|v| v.strip_suffix('"')));
+                    (local_name(child.trim()), unquoted)
+                });
+            match predicate {
+                Some((child, Some(value))) => (name, Some((child, value.to_string()))),
+                _ => return Err(format!("unsupported predicate in {}", step)),
+            }
+        }
+        None => (step, None),
+    };
+    if name.is_empty() {
+        return Err(format!("missing element name in {}", step));
+    }
+    Ok(Step {
+        descendant,
+        name: local_name(name),
+        predicate,
+    })
+}
+
+fn local_name(name: &str) -> String {
+    name.rsplit(':').next().unwrap_or(name).to_string()
+}
+
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    text: String,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn child_text(&self, name: &str) -> Option<&str> {
+        self.children
+            .iter()
+            .find(|child| child.name == name)
+            .map(|child| child.text.as_str())
+    }
+
+    fn matches(&self, step: &Step) -> bool {
+        (step.name == "*" || step.name == self.name)
+            && step
+                .predicate
+                .as_ref()
+                .is_none_or(|(child, value)| self.child_text(child) == Some(value.as_str()))
+    }
+
+    fn descendants<'a>(&'a self, found: &mut Vec<&'a Element>) {
+        for child in &self.children {
+            found.push(child);
+            child.descendants(found);
+        }
+    }
+}
+
+/// Parses reply to element tree, returning the `<data>` element or the reply root
+fn parse(reply: &str) -> Result<Element, quick_xml::Error> {
+    let mut reader = Reader::from_str(reply);
+    reader.config_mut().trim_text(true);
+    let mut stack = vec![Element::default()];
+    loop {
+        match reader.read_event()? {
+            Event::Start(start) => stack.push(Element {
+                name: String::from_utf8_lossy(start.local_name().as_ref()).into_owned(),
+                ..Default::default()
+            }),
+            Event::Empty(empty) => {
+                let element = Element {
+                    name: String::from_utf8_lossy(empty.local_name().as_ref()).into_owned(),
+                    ..Default::default()
+                };
+                stack.last_mut().unwrap().children.push(element);
+            }
+            Event::Text(text) => stack.last_mut().unwrap().text.push_str(&text.unescape()?),
+            Event::CData(data) => stack
+                .last_mut()
+                .unwrap()
+                .text
+                .push_str(&String::from_utf8_lossy(&data)),
+            Event::End(_) if stack.len() > 1 => {
+                let element = stack.pop().unwrap();
+                stack.last_mut().unwrap().children.push(element);
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    let mut document = stack.swap_remove(0);
+    let mut root = match document.children.pop() {
+        Some(root) => root,
+        None => return Ok(document),
+    };
+    Ok(match root.children.iter().position(|c| c.name == "data") {
+        Some(index) => root.children.swap_remove(index),
+        None => root,
+    })
+}
+
+/// Values of each extract, multiple matches are separated by `;`
+pub(crate) fn values(reply: &str, extracts: &[Extract]) -> Result<Vec<String>, quick_xml::Error> {
+    let data = parse(reply)?;
+    Ok(extracts
+        .iter()
+        .map(|extract| {
+            let mut context = vec![&data];
+            for step in &extract.steps {
+                let mut candidates = Vec::new();
+                for element in context {
+                    if step.descendant {
+                        element.descendants(&mut candidates);
+                    } else {
+                        candidates.extend(element.children.iter());
+                    }
+                }
+                context = candidates.into_iter().filter(|e| e.matches(step)).collect();
+            }
+            context
+                .iter()
+                .map(|element| element.text.as_str())
+                .collect::<Vec<_>>()
+                .join(";")
+        })
+        .collect())
+}
+
+/// Table with columns aligned by padding with spaces
+pub(crate) fn table(header: &[String], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = header.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (width, value) in widths.iter_mut().zip(row) {
+            *width = (*width).max(value.chars().count());
+        }
+    }
+    std::iter::once(header)
+        .chain(rows.iter().map(Vec::as_slice))
+        .map(|row| {
+            let line: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .map(|(value, width)| format!("{:width$}", value, width = width))
+                .collect();
+            format!("{}\n", line.join("  ").trim_end())
+        })
+        .collect()
+}
+
+/// CSV with values quoted when needed, see [RFC4180](https://tools.ietf.org/html/rfc4180)
+pub(crate) fn csv(header: &[String], rows: &[Vec<String>]) -> String {
+    let field = |value: &String| {
+        if value.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.clone()
+        }
+    };
+    std::iter::once(header)
+        .chain(rows.iter().map(Vec::as_slice))
+        .map(|row| {
+            format!(
+                "{}\r\n",
+                row.iter().map(field).collect::<Vec<_>>().join(",")
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPLY: &str = r#"<rpc-reply message-id="1" xmlns="urn:ietf:params:xml:ns:netconf:base:1.0">
+  <data>
+    <system xmlns="urn:example:system"><hostname>r1</hostname><version>1.2</version></system>
+    <interfaces xmlns="urn:example:if">
+      <interface><name>eth0</name><mtu>1500</mtu></interface>
+      <interface><name>eth1</name><mtu>9000</mtu></interface>
+    </interfaces>
+  </data>
+</rpc-reply>"#;
+
+    #[test]
+    fn test_parse_extract() {
+        let extract: Extract = "mtu=/if:interfaces/interface[if:name='a/b']/mtu"
+            .parse()
+            .unwrap();
+        assert_eq!(extract.name, "mtu");
+        assert_eq!(extract.steps.len(), 3);
+        assert_eq!(
+            extract.steps[1].predicate,
+            Some(("name".to_string(), "a/b".to_string()))
+        );
+        assert!("interfaces".parse::<Extract>().is_err());
+        assert!("/a[b]".parse::<Extract>().is_err());
+    }
+
+    #[test]
+    fn test_values() {
+        let extracts: Vec<Extract> = [
+            "/system/hostname",
+            "/interfaces/interface[name=\"eth1\"]/mtu",
+            "//name",
+            "/missing",
+        ]
+        .iter()
+        .map(|path| path.parse().unwrap())
+        .collect();
+        assert_eq!(
+            values(REPLY, &extracts).unwrap(),
+            vec!["r1", "9000", "eth0;eth1", ""]
+        );
+    }
+
+    #[test]
+    fn test_table_and_csv() {
+        let header = vec!["host".to_string(), "hostname".to_string()];
+        let rows = vec![
+            vec!["10.0.0.1:830".to_string(), "r1".to_string()],
+            vec!["h2".to_string(), "a, \"b\"".to_string()],
+        ];
+        assert_eq!(
+            table(&header, &rows),
+            "host          hostname\n10.0.0.1:830  r1\nh2            a, \"b\"\n"
+        );
+        assert_eq!(
+            csv(&header, &rows),
+            "host,hostname\r\n10.0.0.1:830,r1\r\nh2,\"a, \"\"b\"\"\"\r\n"
+        );
+    }
+}
@@ -0,0 +1,66 @@
+use keyring::Entry;
+
+const SERVICE: &str = "netconf";
+
+fn entry(user: &str, host: &str) -> keyring::Result<Entry> {
+    Entry::new(SERVICE, &format!("{}@{}", user, host))
+}
+
+/// Password of user on host stored in platform keyring
+pub(crate) fn get_password(user: &str, host: &str) -> Option<String> {
+    match entry(user, host) {
+        Ok(entry) => read_password(&entry, host),
+        Err(err) => {
+            log::warn!(target: host, "Could not read password from keyring: {}", err);
+            None
+        }
+    }
+}
+
+/// Stores password of user on host to platform keyring, keeping a previously stored one
+pub(crate) fn add_password(user: &str, host: &str, password: &str) {
+    match entry(user, host) {
+        Ok(entry) => add_to_entry(&entry, host, password),
+        Err(err) => log::warn!(target: host, "Could not store password to keyring: {}", err),
+    }
+}
+
+fn read_password(entry: &Entry, host: &str) -> Option<String> {
+    match entry.get_password() {
+        Ok(password) => Some(password),
+        Err(keyring::Error::NoEntry) => None,
+        Err(err) => {
+            log::warn!(target: host, "Could not read password from keyring: {}", err);
+            None
+        }
+    }
+}
+
+fn add_to_entry(entry: &Entry, host: &str, password: &str) {
+    if read_password(entry, host).is_none() {
+        store_password(entry, host, password);
+    }
+}
+
+fn store_password(entry: &Entry, host: &str, password: &str) {
+    if let Err(err) = entry.set_password(password) {
+        log::warn!(target: host, "Could not store password to keyring: {}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_password_roundtrip() {
+        keyring::set_default_credential_builder(keyring::mock::default_credential_builder());
+        // Mock credentials are not persisted, so the same entry is used for both
+        let entry = entry("admin", "router1").unwrap();
+        assert_eq!(read_password(&entry, "router1"), None);
+        store_password(&entry, "router1", "secret");
+        assert_eq!(read_password(&entry, "router1").as_deref(), Some("secret"));
+        add_to_entry(&entry, "router1", "prompted");
+        assert_eq!(read_password(&entry, "router1").as_deref(), Some("secret"));
+    }
+}
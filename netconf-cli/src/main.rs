@@ -1,13 +1,48 @@
-use clap::{Args, Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use commands::backup::BackupArgs;
+use commands::capabilities::CapabilitiesArgs;
+use commands::commit::CommitArgs;
+use commands::copy::CopyArgs;
+use commands::diff::DiffArgs;
+use commands::diff_hosts::DiffHostsArgs;
+use commands::edit::EditArgs;
+use commands::kill_session::KillSessionArgs;
+use commands::notification::NotificationArgs;
+use commands::rpc::RpcArgs;
+use commands::snapshot::SnapshotArgs;
+use commands::watch::WatchArgs;
+use config::CliConfig;
 use env_logger::{Builder, Env, Target};
+use extract::Extract;
 use netconf_rust::error::Result;
+use netconf_rust::message::{Datastore, Filter, Reply, Rpc, RpcContent, Source, WithDefaultsValue};
+use netconf_rust::profile::DeviceProfile;
+use netconf_rust::transport::Transport;
 use netconf_rust::Connection;
-use ssh::Host;
-use ssh2_config::HostParams;
+use output::OutputFormat;
+use progress::Progress;
+use retry::Retry;
+use semaphore::Semaphore;
+use serde_derive::Deserialize;
+use ssh::{Credentials, Host, Jump};
+use ssh2_config::{DefaultAlgorithms, HostParams};
 use std::env;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
 use std::thread;
-use std::time::Instant;
+use std::time::{Duration, Instant};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
 
+mod commands;
+mod config;
+mod extract;
+mod keychain;
+mod output;
+mod progress;
+mod retry;
+mod semaphore;
 mod ssh;
 
 const ABOUT: &str = "Netconf cli tool written in Rust\nUse NETCONF_LOG to set log filter and level";
@@ -26,9 +61,22 @@ struct Cli {
         global = true,
         value_delimiter = ',',
         env = "NETCONF_HOST",
-        help = "Host(s) to connect. Value can include port, eg. 172.30.15.1:22. Default port is 830"
+        help = "Host(s) to connect. Value can include port, eg. 172.30.15.1:22 or [2001:db8::1]:22. Default port is 830"
     )]
     host: Vec<String>,
+    #[arg(
+        long,
+        global = true,
+        help = "Port for hosts without one, ssh config Port is used when omitted"
+    )]
+    port: Option<u16>,
+    #[arg(
+        long,
+        global = true,
+        value_parser = parse_profile,
+        help = "Device profile for all hosts instead of the one detected from capabilities: junos, sros or iosxr"
+    )]
+    profile: Option<String>,
     #[arg(
         short,
         long,
@@ -46,6 +94,98 @@ struct Cli {
         hide_env_values = true
     )]
     password: Option<String>,
+    #[arg(
+        long,
+        global = true,
+        help = "Asks password interactively, even if it is given with --password or environment"
+    )]
+    ask_pass: bool,
+    #[arg(
+        short,
+        long,
+        global = true,
+        help = "Skips confirmation of changes on multiple hosts and of overwriting startup"
+    )]
+    yes: bool,
+    #[arg(
+        long,
+        global = true,
+        help = "Reads passwords from platform keyring and stores asked ones there, keyed by user and host"
+    )]
+    use_keyring: bool,
+    #[arg(
+        short,
+        long,
+        global = true,
+        env = "NETCONF_IDENTITY_FILE",
+        help = "Private key for all hosts, used instead of ssh agent. Passphrase is asked if key is encrypted"
+    )]
+    identity_file: Option<PathBuf>,
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        help = "Output format for replies, json prints one object per host to stdout [default: text]"
+    )]
+    format: Option<OutputFormat>,
+    #[arg(
+        long,
+        global = true,
+        value_parser = clap::value_parser!(u16).range(1..),
+        help = "Maximum number of hosts with concurrent sessions [default: 16]"
+    )]
+    parallel: Option<u16>,
+    #[arg(
+        long,
+        global = true,
+        value_name = "SECONDS",
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Timeout for connecting and for each rpc, overrides ssh config ConnectTimeout [default: 10]"
+    )]
+    timeout: Option<u64>,
+    #[arg(
+        long,
+        global = true,
+        default_value_t = 0,
+        help = "Retries of failed connection or session setup per host, and of rpcs rejected with lock-denied, resource-denied or in-use"
+    )]
+    retries: u32,
+    #[arg(
+        long,
+        global = true,
+        value_name = "SECONDS",
+        default_value = "1",
+        value_parser = parse_delay,
+        help = "Delay before first retry, doubled after each retry"
+    )]
+    retry_delay: Duration,
+    #[arg(
+        long,
+        global = true,
+        value_name = "FILE",
+        help = "Appends every rpc and reply with timestamp and host to the file"
+    )]
+    audit_log: Option<PathBuf>,
+    #[arg(
+        long,
+        global = true,
+        value_name = "[USER@]HOST[:PORT]",
+        help = "Jump host(s) for all hosts, comma separated like ssh ProxyJump, overrides config file and ssh config"
+    )]
+    jump_host: Option<String>,
+    #[arg(
+        short,
+        long,
+        global = true,
+        help = "Hides progress line of completed and failed hosts, shown on terminal for multiple hosts"
+    )]
+    quiet: bool,
+    #[arg(
+        long,
+        global = true,
+        help = "Writes only replies to stdout and logging to stderr, for use in pipelines"
+    )]
+    porcelain: bool,
 
     #[command(subcommand)]
     command: Commands,
@@ -57,39 +197,299 @@ enum Commands {
     Get(GetConfigArgs),
     #[command(about = "Get-config rpc from specific datastore")]
     GetConfig(GetConfigArgs),
-    #[command(about = "Edit-config rpc")]
-    EditConfig(EditConfigArgs),
+    #[command(
+        about = "Edit-config rpc with payload from file or stdin",
+        alias = "edit-config"
+    )]
+    Edit(EditArgs),
+    #[command(
+        about = "Copy-config rpc between datastores or urls",
+        alias = "copy-config"
+    )]
+    Copy(CopyArgs),
+    #[command(about = "Arbitrary rpc with operation from file or stdin")]
+    Rpc(RpcArgs),
+    #[command(about = "Commit candidate datastore, optionally as confirmed commit")]
+    Commit(CommitArgs),
+    #[command(
+        about = "Discard pending changes in candidate datastore",
+        alias = "discard-changes"
+    )]
+    Discard,
+    #[command(about = "Diff of candidate datastore against running")]
+    Diff(DiffArgs),
+    #[command(about = "Diff of the same configuration on two hosts")]
+    DiffHosts(DiffHostsArgs),
+    #[command(
+        about = "Subscribe to event notifications until interrupted or stop time",
+        alias = "notifications"
+    )]
+    Notification(NotificationArgs),
+    #[command(about = "Capabilities advertised in server hello")]
+    Capabilities(CapabilitiesArgs),
+    #[command(about = "Stores configuration snapshots and diffs hosts against them")]
+    Snapshot(SnapshotArgs),
+    #[command(about = "Commits running configuration of each host to git repository")]
+    Backup(BackupArgs),
+    #[command(about = "Repeats get or get-config and prints changes between replies")]
+    Watch(WatchArgs),
+    #[command(about = "Kill-session rpc terminating other sessions, eg. ones holding stuck locks")]
+    KillSession(KillSessionArgs),
+}
+
+impl Commands {
+    fn name(&self) -> &'static str {
+        match self {
+            Commands::Get(_) => "get",
+            Commands::GetConfig(_) => "get-config",
+            Commands::Edit(_) => "edit-config",
+            Commands::Copy(_) => "copy-config",
+            Commands::Rpc(_) => "rpc",
+            Commands::Commit(_) => "commit",
+            Commands::Discard => "discard-changes",
+            Commands::Diff(_) => "diff",
+            Commands::DiffHosts(_) => "diff-hosts",
+            Commands::Notification(_) => "notification",
+            Commands::Capabilities(_) => "capabilities",
+            Commands::Snapshot(_) => "snapshot",
+            Commands::Backup(_) => "backup",
+            Commands::Watch(_) => "watch",
+            Commands::KillSession(_) => "kill-session",
+        }
+    }
+
+    /// Operation changing device configuration
+    fn destructive_operation(&self) -> Option<&'static str> {
+        match self {
+            Commands::Edit(_) => Some("edit-config"),
+            Commands::Copy(_) => Some("copy-config"),
+            Commands::Commit(_) => Some("commit"),
+            Commands::KillSession(_) => Some("kill-session"),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Args, Clone, Default)]
 struct GetConfigArgs {
     #[arg(short, long, default_value = "running")]
     source: String,
+    #[arg(long, value_enum, help = "Reporting mode of default values")]
+    with_defaults: Option<WithDefaultsArg>,
+    #[arg(
+        long,
+        help = "XPath filter, eg. /if:interfaces/if:interface[if:name='eth0']"
+    )]
+    xpath: Option<String>,
+    #[arg(
+        short,
+        long,
+        conflicts_with = "xpath",
+        help = "File with subtree filter XML, read from stdin when '-'"
+    )]
+    file: Option<PathBuf>,
+    #[arg(
+        long,
+        requires = "xpath",
+        value_name = "PREFIX=URI",
+        value_parser = parse_namespace,
+        help = "Namespace of prefix used in XPath filter, can be repeated"
+    )]
+    ns: Vec<(String, String)>,
+    #[arg(
+        long,
+        value_name = "[NAME=]PATH",
+        help = "Prints value selected by path, eg. /system/hostname, as table column instead of the reply, can be repeated"
+    )]
+    extract: Vec<Extract>,
+    #[arg(long, requires = "extract", help = "Prints extracted values as CSV")]
+    csv: bool,
+
+    /// Subtree filter read once before connecting to hosts
+    #[arg(skip)]
+    subtree: Option<String>,
 }
 
-#[derive(Debug, Args, Clone, Default)]
-struct EditConfigArgs {
-    #[arg(short, long, default_value = "running")]
-    source: String,
+#[derive(Debug, Clone, Copy, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum WithDefaultsArg {
+    ReportAll,
+    ReportAllTagged,
+    Trim,
+    Explicit,
+}
+
+impl From<WithDefaultsArg> for WithDefaultsValue {
+    fn from(value: WithDefaultsArg) -> Self {
+        match value {
+            WithDefaultsArg::ReportAll => WithDefaultsValue::ReportAll,
+            WithDefaultsArg::ReportAllTagged => WithDefaultsValue::ReportAllTagged,
+            WithDefaultsArg::Trim => WithDefaultsValue::Trim,
+            WithDefaultsArg::Explicit => WithDefaultsValue::Explicit,
+        }
+    }
+}
+
+impl GetConfigArgs {
+    fn read_subtree(&mut self) -> io::Result<()> {
+        if let Some(file) = &self.file {
+            self.subtree = Some(commands::read_input(Some(file))?);
+        }
+        Ok(())
+    }
+
+    /// Filter of the request, prefixes not given with `--ns` are resolved by `profile`
+    fn filter(&self, profile: Option<&dyn DeviceProfile>) -> Option<Filter> {
+        if let Some(subtree) = &self.subtree {
+            return Some(Filter::subtree(subtree));
+        }
+        let filter = Filter::xpath(self.xpath.as_deref()?);
+        let filter = self.ns.iter().fold(filter, |filter, (prefix, uri)| {
+            filter.namespace(prefix, uri)
+        });
+        Some(match profile {
+            Some(profile) => filter.resolve_prefixes(|prefix| profile.namespace(prefix)),
+            None => filter,
+        })
+    }
 }
 
-fn init_logging() {
+fn parse_profile(value: &str) -> std::result::Result<String, String> {
+    match netconf_rust::profile::by_name(value) {
+        Some(_) => Ok(value.to_string()),
+        None => Err("expected junos, sros or iosxr".to_string()),
+    }
+}
+
+fn parse_delay(value: &str) -> std::result::Result<Duration, String> {
+    value
+        .parse()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .ok_or_else(|| "expected non-negative number of seconds".to_string())
+}
+
+fn parse_namespace(value: &str) -> std::result::Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((prefix, uri)) if !prefix.is_empty() && !uri.is_empty() => {
+            Ok((prefix.to_string(), uri.to_string()))
+        }
+        _ => Err("expected PREFIX=URI".to_string()),
+    }
+}
+
+/// Asks confirmation of copy to startup, and of destructive operations on multiple hosts
+fn confirm_command(cli: &Cli) -> io::Result<bool> {
+    if let Commands::Copy(args) = &cli.command {
+        if let Some(confirmed) = args.confirm()? {
+            return Ok(confirmed);
+        }
+    }
+    match cli.command.destructive_operation() {
+        Some(operation) if cli.host.len() > 1 => {
+            let question = format!("Apply {} to {} hosts?", operation, cli.host.len());
+            commands::confirm(&question)
+        }
+        _ => Ok(true),
+    }
+}
+
+fn init_logging(porcelain: bool) {
     let env = Env::default().filter_or("NETCONF_LOG", "info");
     let mut builder = Builder::new();
-    builder.target(Target::Stdout);
+    builder.target(if porcelain {
+        Target::Stderr
+    } else {
+        Target::Stdout
+    });
     builder.parse_env(env);
     builder.init();
 }
 
 fn main() {
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
     if cli.debug {
         env::set_var("NETCONF_LOG", "debug");
     }
     if cli.trace {
         env::set_var("NETCONF_LOG", "trace");
     }
-    init_logging();
+    init_logging(cli.porcelain);
+    output::set_porcelain(cli.porcelain);
+
+    let cli_config = match CliConfig::new() {
+        Ok(cli_config) => cli_config,
+        Err(err) => {
+            log::error!("Could not read config file: {}", err);
+            return;
+        }
+    };
+    if let Commands::Get(args) | Commands::GetConfig(args) = &mut cli.command {
+        args.with_defaults = args.with_defaults.or(cli_config.with_defaults);
+        if let Err(err) = args.read_subtree() {
+            log::error!("Could not read subtree filter: {}", err);
+            return;
+        }
+    }
+
+    let read_subtree = match &mut cli.command {
+        Commands::Diff(args) => args.read_subtree(),
+        Commands::DiffHosts(args) => args.read_subtree(),
+        Commands::Snapshot(args) => args.read_subtree(),
+        Commands::Watch(args) => args.read_subtree(),
+        _ => Ok(()),
+    };
+    if let Err(err) = read_subtree {
+        log::error!("Could not read subtree filter: {}", err);
+        return;
+    }
+
+    if let Commands::Edit(args) = &mut cli.command {
+        if let Err(err) = args.read_payload() {
+            log::error!("Could not read edit-config payload: {}", err);
+            return;
+        }
+    }
+
+    if let Commands::Rpc(args) = &mut cli.command {
+        if let Err(err) = args.read_operation() {
+            log::error!("Could not read rpc operation: {}", err);
+            return;
+        }
+    }
+    if let Commands::Snapshot(args) = &cli.command {
+        if !args.needs_hosts() {
+            if let Err(err) = args.list() {
+                log::error!("Could not list snapshots: {}", err);
+            }
+            return;
+        }
+    }
+    if matches!(cli.command, Commands::DiffHosts(_)) && cli.host.len() != 2 {
+        log::error!("diff-hosts requires exactly two hosts");
+        return;
+    }
+    if !cli.yes {
+        match confirm_command(&cli) {
+            Ok(true) => {}
+            Ok(false) => return,
+            Err(err) => {
+                log::error!("Could not read confirmation: {}", err);
+                return;
+            }
+        }
+    }
+
+    let mut credentials = Credentials {
+        username: cli.username.clone(),
+        password: cli.password.clone(),
+        identity_file: cli.identity_file.clone(),
+        passphrase: None,
+    };
+    if let Err(err) = credentials.read_passphrase() {
+        log::error!("Could not read identity file: {}", err);
+        return;
+    }
 
     let config = ssh::read_config();
     let mut hosts = Vec::new();
@@ -97,90 +497,414 @@ fn main() {
         let command = match &cli.command {
             Commands::GetConfig(args) => Commands::GetConfig(args.clone()),
             Commands::Get(args) => Commands::Get(args.clone()),
-            Commands::EditConfig(args) => Commands::EditConfig(args.clone()),
+            Commands::Edit(args) => Commands::Edit(args.clone()),
+            Commands::Copy(args) => Commands::Copy(args.clone()),
+            Commands::Rpc(args) => Commands::Rpc(args.clone()),
+            Commands::Commit(args) => Commands::Commit(args.clone()),
+            Commands::Discard => Commands::Discard,
+            Commands::Diff(args) => Commands::Diff(args.clone()),
+            Commands::DiffHosts(args) => Commands::DiffHosts(args.clone()),
+            Commands::Notification(args) => Commands::Notification(args.clone()),
+            Commands::Capabilities(args) => Commands::Capabilities(args.clone()),
+            Commands::Snapshot(args) => Commands::Snapshot(args.clone()),
+            Commands::Backup(args) => Commands::Backup(args.clone()),
+            Commands::Watch(args) => Commands::Watch(args.clone()),
+            Commands::KillSession(args) => Commands::KillSession(args.clone()),
         };
-        hosts.push(Host::new(
+        match Host::new(
             address,
-            cli.username.clone(),
-            cli.password.clone(),
+            credentials.clone(),
+            cli.port,
+            cli.timeout.map(Duration::from_secs),
             command,
-        ));
+        ) {
+            Ok(mut host) => {
+                host.set_jump_host(cli.jump_host.as_deref());
+                host.set_profile(
+                    cli.profile
+                        .as_deref()
+                        .and_then(netconf_rust::profile::by_name),
+                );
+                if let Some(group) = cli_config.group(host.name()) {
+                    host.set_jump_host(group.jump_host.as_deref());
+                    host.set_jump_password(group.jump_password.as_deref());
+                    host.set_defaults(
+                        group.username.as_deref(),
+                        group.port,
+                        group.timeout.map(Duration::from_secs),
+                    );
+                }
+                host.set_defaults(cli_config.username.as_deref(), None, None);
+                hosts.push(host);
+            }
+            Err(err) => log::error!("{}", err),
+        }
+    }
+
+    let params_of = |host: &Host| match &config {
+        Some(p) => p.query(host.name()),
+        None => HostParams::new(&DefaultAlgorithms::default()),
+    };
+    if cli.use_keyring && !cli.ask_pass {
+        for host in hosts.iter_mut() {
+            let params = params_of(host);
+            if !host.needs_password(&params) {
+                continue;
+            }
+            let password = host
+                .username(&params)
+                .and_then(|user| keychain::get_password(&user, host.name()));
+            if let Some(password) = password {
+                host.set_password(password);
+            }
+        }
+    }
+    if cli.ask_pass
+        || hosts
+            .iter()
+            .any(|host| host.needs_password(&params_of(host)))
+    {
+        match rpassword::prompt_password("Password: ") {
+            Ok(password) => {
+                for host in hosts.iter_mut() {
+                    let params = params_of(host);
+                    if host.set_prompted_password(&password, cli.ask_pass, &params)
+                        && cli.use_keyring
+                    {
+                        if let Some(user) = host.username(&params) {
+                            keychain::add_password(&user, host.name(), &password);
+                        }
+                    }
+                }
+            }
+            Err(err) => {
+                log::error!("Could not read password: {}", err);
+                return;
+            }
+        }
     }
 
+    let started = OffsetDateTime::now_utc()
+        .format(&Rfc3339)
+        .unwrap_or_default();
+    let format = cli.format.or(cli_config.format).unwrap_or_default();
+    let parallel = cli.parallel.or(cli_config.parallel).unwrap_or(16).max(1);
+    let semaphore = Semaphore::new(parallel as usize);
+    let retry = Retry::new(cli.retries, cli.retry_delay);
+    let progress = Progress::new(cli.command.name(), hosts.len(), cli.quiet);
     let mut handles = vec![];
     for mut host in hosts.into_iter() {
-        let params = match &config {
-            Some(p) => p.query(host.address()),
-            None => HostParams::default(),
-        };
+        let mut params = params_of(&host);
+        host.apply_jump_host(&mut params);
+        let jumps = ssh::proxy_jumps(config.as_ref(), &params);
 
-        let start_time = Instant::now();
-        let task = thread::spawn(move || match host.connect(&params) {
-            Ok(session) => {
-                let ssh =
-                    netconf_rust::transport::ssh::SSHTransport::dial_session(session).unwrap();
-                log::info!(target: &host.address(), "Connected to host");
-                let mut connection = Connection::new(ssh).unwrap();
-                log::debug!(
-                    target: &host.address(),
-                    "Started Netconf session with session-id: {}",
-                    connection.session_id()
-                );
+        let audit_log = cli.audit_log.clone();
 
-                match &host.command {
-                    Commands::GetConfig(args) => {
-                        run_get_config(&host.address(), args, &mut connection).unwrap();
+        let permit = semaphore.acquire();
+        let mut host_progress = progress.host();
+        let start_time = Instant::now();
+        let task = thread::spawn(move || {
+            let _permit = permit;
+            let address = host.address();
+            let mut output: Option<HostOutput> = None;
+            let connect = |host: &mut Host| {
+                let mut connection = open_session(host, &params, &jumps)?;
+                if let Some(policy) = retry.policy() {
+                    connection.set_retry_policy(policy);
+                }
+                if let Some(path) = &audit_log {
+                    if let Err(err) = connection.set_audit_log(path, &address) {
+                        log::error!(target: &address, "Could not open audit log {}: {}", path.display(), err);
                     }
-                    Commands::Get(args) => {
-                        run_get(&host.address(), args, &mut connection).unwrap();
+                }
+                Ok(connection)
+            };
+            match retry.run_if(
+                &address,
+                |err, _| retry::is_retryable(err),
+                || connect(&mut host),
+            ) {
+                Ok(mut connection) => {
+                    let resolved = host.address();
+                    let result = match &host.command {
+                        Commands::GetConfig(args) => {
+                            run_get_config(&resolved, args, format, &mut connection).map(|values| {
+                                output = values.map(HostOutput::Values);
+                            })
+                        }
+                        Commands::Get(args) => run_get(&resolved, args, format, &mut connection)
+                            .map(|values| {
+                                output = values.map(HostOutput::Values);
+                            }),
+                        Commands::Edit(args) => {
+                            commands::edit::run(&resolved, &address, args, &mut connection)
+                        }
+                        Commands::Copy(args) => {
+                            commands::copy::run(&resolved, args, &mut connection)
+                        }
+                        Commands::Rpc(args) => {
+                            commands::rpc::run(&resolved, args, format, &mut connection)
+                        }
+                        Commands::Commit(args) => {
+                            commands::commit::run(&resolved, args, &mut connection)
+                        }
+                        Commands::Discard => commands::discard::run(&resolved, &mut connection),
+                        Commands::Diff(args) => {
+                            commands::diff::run(&resolved, args, &mut connection)
+                        }
+                        Commands::DiffHosts(args) => {
+                            commands::diff_hosts::run(args, &mut connection).map(|config| {
+                                output = Some(HostOutput::Config(config));
+                            })
+                        }
+                        Commands::Notification(args) => {
+                            let args = args.clone();
+                            commands::notification::run(&address, &args, &mut connection, || {
+                                connect(&mut host)
+                            })
+                        }
+                        Commands::Capabilities(args) => {
+                            commands::capabilities::run(&resolved, args, format, &mut connection)
+                        }
+                        Commands::Watch(args) => {
+                            commands::watch::run(&address, args, &mut connection)
+                        }
+                        Commands::KillSession(args) => {
+                            commands::kill_session::run(&address, args, &mut connection)
+                        }
+                        Commands::Backup(args) => {
+                            commands::backup::run(&address, args, &mut connection).map(|file| {
+                                output = Some(HostOutput::File(file));
+                            })
+                        }
+                        Commands::Snapshot(args) => {
+                            commands::snapshot::run(&address, args, &mut connection)
+                        }
+                    };
+                    if let Err(err) = result {
+                        log::error!(target: &resolved, "{} failed: {}", host.command.name(), err);
+                        host_progress.fail();
                     }
-                    Commands::EditConfig(_args) => {
-                        log::warn!("Edit-config not implemented yet");
+                    if let Err(err) = connection.shutdown() {
+                        log::error!(target: &host.address(), "Failed to close session: {}", err);
                     }
-                };
-                log::info!(target: &host.address(), "Operation took: {:.3}s", start_time.elapsed().as_secs_f32());
-            }
-            Err(err) => {
-                log::error!(target: &host.address(), "Could not connect to host, error: {err}");
+                    log::info!(target: &host.address(), "Operation took: {:.3}s", start_time.elapsed().as_secs_f32());
+                }
+                Err(err) => {
+                    log::error!(target: &host.address(), "Could not connect to host, error: {err}");
+                    host_progress.fail();
+                }
             }
+            output.map(|output| (address, output))
         });
         handles.push(task);
     }
 
+    let mut outputs = Vec::new();
     for i in handles {
         match i.join() {
-            Ok(_) => {}
+            Ok(output) => outputs.extend(output),
             Err(err) => {
                 log::error!("Task error: {:?}", err);
             }
         };
     }
+    progress.finish();
+    match (&cli.command, outputs.as_slice()) {
+        (
+            Commands::DiffHosts(args),
+            [(a, HostOutput::Config(config_a)), (b, HostOutput::Config(config_b))],
+        ) => {
+            commands::diff_hosts::print_diff(args, (a, config_a), (b, config_b));
+        }
+        (Commands::Backup(args), outputs) => {
+            for (address, output) in outputs {
+                if let HostOutput::File(file) = output {
+                    if let Err(err) = commands::backup::commit(args, address, file, &started) {
+                        log::error!(target: address, "Could not commit backup: {}", err);
+                    }
+                }
+            }
+        }
+        (Commands::Get(args) | Commands::GetConfig(args), outputs) if !outputs.is_empty() => {
+            print_extracted(args, outputs);
+        }
+        _ => {}
+    }
 }
 
-fn run_get(address: &str, args: &GetConfigArgs, connection: &mut Connection) -> Result<()> {
-    match connection.get_config(&args.source) {
-        Ok(resp) => {
-            log::info!("Get rpc success");
-            log::trace!(target: address, "Response:\n{}", resp.trim());
-        }
-        Err(err) => {
-            log::error!(target: address, "Get error: {}", err);
+/// Result of a host collected for printing once all hosts are done
+enum HostOutput {
+    /// Normalized configuration for diff-hosts
+    Config(String),
+    /// Backup file to commit
+    File(String),
+    /// Extracted values
+    Values(Vec<String>),
+}
+
+/// Prints extracted values of all hosts as table, or CSV
+fn print_extracted(args: &GetConfigArgs, outputs: &[(String, HostOutput)]) {
+    let header: Vec<String> = std::iter::once("host".to_string())
+        .chain(args.extract.iter().map(|extract| extract.name.clone()))
+        .collect();
+    let rows: Vec<Vec<String>> = outputs
+        .iter()
+        .filter_map(|(address, output)| match output {
+            HostOutput::Values(values) => Some(
+                std::iter::once(address.clone())
+                    .chain(values.iter().cloned())
+                    .collect(),
+            ),
+            _ => None,
+        })
+        .collect();
+    if args.csv {
+        print!("{}", extract::csv(&header, &rows));
+    } else {
+        print!("{}", extract::table(&header, &rows));
+    }
+}
+
+/// Connects and authenticates to host and starts Netconf session
+fn open_session(host: &mut Host, params: &HostParams, jumps: &[Jump]) -> Result<Connection> {
+    let session = host.connect(params, jumps)?;
+    let mut ssh = netconf_rust::transport::ssh::SSHTransport::dial_session(session)?;
+    if let Some(interval) = ssh::keepalive_interval(params) {
+        log::debug!(target: &host.address(), "Setting keepalive interval: {} seconds", interval.as_secs());
+        ssh.set_keepalive(interval);
+    }
+    log::info!(target: &host.address(), "Connected to host");
+    ssh.set_timeout(Some(host.timeout()));
+    let connection = match host.profile() {
+        Some(profile) => {
+            Connection::new_with_profile_and_hello_timeout(ssh, profile, host.timeout())?
         }
+        None => Connection::new_with_hello_timeout(ssh, host.timeout())?,
     };
-    connection.close_session().unwrap();
-    Ok(())
+    log::debug!(
+        target: &host.address(),
+        "Started Netconf session with session-id: {}",
+        connection.session_id()
+    );
+    Ok(connection)
 }
 
-fn run_get_config(address: &str, args: &GetConfigArgs, connection: &mut Connection) -> Result<()> {
-    match connection.get_config(&args.source) {
-        Ok(resp) => {
-            log::info!("Get-config rpc success");
-            log::trace!(target: address, "Response:\n{}", resp.trim());
+fn run_get(
+    address: &str,
+    args: &GetConfigArgs,
+    format: OutputFormat,
+    connection: &mut Connection,
+) -> Result<Option<Vec<String>>> {
+    let get = Rpc::new(RpcContent::Get {
+        filter: args.filter(connection.profile()),
+        with_defaults: args
+            .with_defaults
+            .map(|value| WithDefaultsValue::from(value).into()),
+    });
+    let resp = connection.execute(get)?;
+    log::info!(target: address, "Get rpc success");
+    handle_response(address, args, &resp, format)
+}
+
+fn run_get_config(
+    address: &str,
+    args: &GetConfigArgs,
+    format: OutputFormat,
+    connection: &mut Connection,
+) -> Result<Option<Vec<String>>> {
+    let get_config = Rpc::new(RpcContent::GetConfig {
+        source: Source {
+            datastore: Datastore::from_str(&args.source)?,
+        },
+        filter: args.filter(connection.profile()),
+        with_defaults: args
+            .with_defaults
+            .map(|value| WithDefaultsValue::from(value).into()),
+    });
+    let resp = connection.execute(get_config)?;
+    log::info!(target: address, "Get-config rpc success");
+    handle_response(address, args, &resp, format)
+}
+
+/// Prints the reply, or returns values to extract for printing with other hosts.
+/// In JSON format extracted values are printed as object per host.
+fn handle_response(
+    address: &str,
+    args: &GetConfigArgs,
+    resp: &Reply,
+    format: OutputFormat,
+) -> Result<Option<Vec<String>>> {
+    if args.extract.is_empty() {
+        print_response(address, resp, format);
+        return Ok(None);
+    }
+    let values = extract::values(resp.raw(), &args.extract)?;
+    match format {
+        OutputFormat::Text => Ok(Some(values)),
+        OutputFormat::Json => {
+            let values = args
+                .extract
+                .iter()
+                .zip(values)
+                .map(|(extract, value)| (extract.name.clone(), serde_json::Value::String(value)))
+                .collect();
+            output::print_host_json(address, "values", serde_json::Value::Object(values));
+            Ok(None)
         }
-        Err(err) => {
-            log::error!(target: address, "Get-config error: {}", err);
+    }
+}
+
+fn print_response(address: &str, resp: &Reply, format: OutputFormat) {
+    match format {
+        OutputFormat::Text => {
+            output::print_text(address, log::Level::Trace, "Response", resp.pretty())
         }
-    };
-    connection.close_session().unwrap();
-    Ok(())
+        OutputFormat::Json => output::print_json(address, resp),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_short_f_is_file() {
+        for command in Cli::command().get_subcommands() {
+            for arg in command.get_arguments() {
+                if arg.get_short() == Some('f') {
+                    assert_eq!(arg.get_long(), Some("file"), "{}", command.get_name());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_filter_file() {
+        for command in ["get", "get-config", "diff", "diff-hosts", "watch"] {
+            let cli = Cli::try_parse_from(["netconf", "--host", "r1", command, "-f", "-"]);
+            assert!(cli.is_ok(), "{}", command);
+        }
+        for command in ["save", "diff"] {
+            let args = [
+                "netconf", "--host", "r1", "snapshot", command, "base", "-f", "-",
+            ];
+            assert!(Cli::try_parse_from(args).is_ok(), "{}", command);
+        }
+    }
+
+    #[test]
+    fn test_parse_delay() {
+        assert_eq!(parse_delay("0.5"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_delay("0"), Ok(Duration::ZERO));
+        for delay in ["-1", "inf", "NaN", "1s"] {
+            assert!(parse_delay(delay).is_err(), "{}", delay);
+        }
+    }
 }
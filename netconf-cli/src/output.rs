@@ -0,0 +1,50 @@
+use clap::ValueEnum;
+use netconf_rust::message::{to_json, Reply};
+use serde_derive::Deserialize;
+use serde_json::{Map, Value};
+use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Replies in text format are printed to stdout instead of logged
+static PORCELAIN: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+pub(crate) fn set_porcelain(porcelain: bool) {
+    PORCELAIN.store(porcelain, Ordering::Relaxed);
+}
+
+pub(crate) fn porcelain() -> bool {
+    PORCELAIN.load(Ordering::Relaxed)
+}
+
+/// Prints text reply to stdout in porcelain mode, otherwise logs it with label at level
+pub(crate) fn print_text(address: &str, level: log::Level, label: &str, text: impl Display) {
+    if porcelain() {
+        println!("{}", text);
+    } else {
+        log::log!(target: address, level, "{}:\n{}", label, text);
+    }
+}
+
+/// Prints reply converted to JSON object tagged with host address to stdout
+pub(crate) fn print_json(address: &str, reply: &Reply) {
+    match to_json(reply.raw()) {
+        Ok(value) => print_host_json(address, "reply", value),
+        Err(err) => log::error!(target: address, "Could not convert reply to JSON: {}", err),
+    }
+}
+
+/// Prints `{"host": address, key: value}` object to stdout
+pub(crate) fn print_host_json(address: &str, key: &str, value: Value) {
+    let mut output = Map::new();
+    output.insert("host".to_string(), Value::String(address.to_string()));
+    output.insert(key.to_string(), value);
+    println!("{:#}", Value::Object(output));
+}
@@ -0,0 +1,94 @@
+use std::io::{self, IsTerminal, Write};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Completed and failed hosts of a multi-host run, redrawn on one stderr line
+#[derive(Debug)]
+pub(crate) struct Progress {
+    operation: &'static str,
+    total: usize,
+    enabled: bool,
+    /// Completed and failed hosts
+    state: Mutex<(usize, usize)>,
+}
+
+/// Reports host as completed when dropped, and as failed also when its thread panicked
+#[derive(Debug)]
+pub(crate) struct HostProgress {
+    progress: Arc<Progress>,
+    failed: bool,
+}
+
+impl Progress {
+    /// Progress is shown only for multiple hosts and when stderr is a terminal
+    pub(crate) fn new(operation: &'static str, total: usize, quiet: bool) -> Arc<Progress> {
+        Arc::new(Progress {
+            operation,
+            total,
+            enabled: !quiet && total > 1 && io::stderr().is_terminal(),
+            state: Mutex::new((0, 0)),
+        })
+    }
+
+    pub(crate) fn host(self: &Arc<Self>) -> HostProgress {
+        HostProgress {
+            progress: self.clone(),
+            failed: false,
+        }
+    }
+
+    fn host_done(&self, failed: bool) {
+        let mut state = self.state.lock().unwrap();
+        state.0 += 1;
+        if failed {
+            state.1 += 1;
+        }
+        if self.enabled {
+            let mut stderr = io::stderr().lock();
+            let _ = write!(stderr, "\r\x1b[K{}", self.line(*state));
+            let _ = stderr.flush();
+        }
+    }
+
+    /// Ends progress line, after which other output does not overwrite it
+    pub(crate) fn finish(&self) {
+        if self.enabled {
+            eprintln!();
+        }
+    }
+
+    fn line(&self, (completed, failed): (usize, usize)) -> String {
+        format!(
+            "[{}/{}] {}, {} failed",
+            completed, self.total, self.operation, failed
+        )
+    }
+}
+
+impl HostProgress {
+    pub(crate) fn fail(&mut self) {
+        self.failed = true;
+    }
+}
+
+impl Drop for HostProgress {
+    fn drop(&mut self) {
+        self.progress.host_done(self.failed || thread::panicking());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_progress() {
+        let progress = Progress::new("get-config", 3, true);
+        drop(progress.host());
+        let mut host = progress.host();
+        host.fail();
+        drop(host);
+        let state = *progress.state.lock().unwrap();
+        assert_eq!(progress.line(state), "[2/3] get-config, 1 failed");
+    }
+}
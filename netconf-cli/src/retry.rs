@@ -0,0 +1,165 @@
+use netconf_rust::error::Error;
+use netconf_rust::retry::RetryPolicy;
+use std::fmt::Display;
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+const MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// Retries failed operation with exponentially growing delay
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Retry {
+    retries: u32,
+    delay: Duration,
+}
+
+impl Retry {
+    pub(crate) fn new(retries: u32, delay: Duration) -> Retry {
+        Retry { retries, delay }
+    }
+
+    /// Delay before retry `attempt`, doubled after each attempt up to one minute
+    fn delay(&self, attempt: u32) -> Duration {
+        self.delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(MAX_DELAY)
+    }
+
+    /// Policy retrying rpcs rejected with transient errors, e.g. `lock-denied`, as many
+    /// times and with the same delays, `None` without retries
+    pub(crate) fn policy(&self) -> Option<RetryPolicy> {
+        (self.retries > 0).then(|| {
+            RetryPolicy::new(self.retries.saturating_add(1))
+                .delay(self.delay)
+                .max_delay(MAX_DELAY)
+        })
+    }
+
+    /// Runs `operation` until it succeeds, retries are exhausted or `retryable` returns
+    /// false for the error and the delay before the next attempt, returning the last error
+    pub(crate) fn run_if<T, E: Display>(
+        &self,
+        address: &str,
+        retryable: impl Fn(&E, Duration) -> bool,
+        mut operation: impl FnMut() -> Result<T, E>,
+    ) -> Result<T, E> {
+        let mut attempt = 0;
+        loop {
+            match operation() {
+                Ok(value) => return Ok(value),
+                Err(err) if attempt < self.retries && retryable(&err, self.delay(attempt)) => {
+                    let delay = self.delay(attempt);
+                    attempt += 1;
+                    log::warn!(
+                        target: address,
+                        "Attempt {} failed: {}, retrying in {:.1}s",
+                        attempt,
+                        err,
+                        delay.as_secs_f32()
+                    );
+                    thread::sleep(delay);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+/// Whether connecting or session setup failing with `err` may succeed on another attempt,
+/// false for errors of credentials and capabilities of the host, so that repeated
+/// authentication failures do not lock accounts
+pub(crate) fn is_retryable(err: &Error) -> bool {
+    match err {
+        Error::AuthenticationFailed(_) | Error::HelloFailed(_) | Error::CapabilityMissing(_) => {
+            false
+        }
+        Error::Io(err) => err.kind() != io::ErrorKind::PermissionDenied,
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry() {
+        let retry = Retry::new(2, Duration::from_millis(1));
+        let mut attempts = 0;
+        let result: Result<u32, String> = retry.run_if(
+            "host",
+            |_, _| true,
+            || {
+                attempts += 1;
+                match attempts {
+                    3 => Ok(attempts),
+                    _ => Err("refused".to_string()),
+                }
+            },
+        );
+        assert_eq!(result, Ok(3));
+
+        let mut attempts = 0;
+        let result: Result<(), String> = retry.run_if(
+            "host",
+            |_, _| true,
+            || {
+                attempts += 1;
+                Err(format!("refused {}", attempts))
+            },
+        );
+        assert_eq!(result, Err("refused 3".to_string()));
+    }
+
+    #[test]
+    fn test_is_retryable() {
+        assert!(is_retryable(&Error::SessionClosed));
+        assert!(is_retryable(&Error::ConnectTimeout));
+        assert!(!is_retryable(&Error::AuthenticationFailed(
+            "denied".to_string()
+        )));
+        let denied = io::Error::new(io::ErrorKind::PermissionDenied, "no suitable public key");
+        assert!(!is_retryable(&Error::Io(denied)));
+        assert!(Retry::new(0, Duration::from_secs(1)).policy().is_none());
+        assert!(Retry::new(2, Duration::from_secs(1)).policy().is_some());
+    }
+
+    #[test]
+    fn test_retry_if() {
+        let retry = Retry::new(u32::MAX, Duration::from_millis(1));
+        let mut attempts = 0;
+        let result: Result<(), String> = retry.run_if(
+            "host",
+            |err, _| err != "denied",
+            || {
+                attempts += 1;
+                match attempts {
+                    3 => Err("denied".to_string()),
+                    _ => Err("refused".to_string()),
+                }
+            },
+        );
+        assert_eq!(result, Err("denied".to_string()));
+
+        let mut attempts = 0;
+        let result: Result<(), String> = retry.run_if(
+            "host",
+            |_, delay| delay < Duration::from_millis(4),
+            || {
+                attempts += 1;
+                Err(format!("refused {}", attempts))
+            },
+        );
+        assert_eq!(result, Err("refused 3".to_string()));
+    }
+
+    #[test]
+    fn test_delay_backoff() {
+        let retry = Retry::new(10, Duration::from_secs(1));
+        assert_eq!(retry.delay(0), Duration::from_secs(1));
+        assert_eq!(retry.delay(3), Duration::from_secs(8));
+        assert_eq!(retry.delay(8), MAX_DELAY);
+        assert_eq!(retry.delay(40), MAX_DELAY);
+    }
+}
@@ -0,0 +1,74 @@
+use std::sync::{Arc, Condvar, Mutex};
+
+/// Counting semaphore limiting concurrent host sessions
+#[derive(Debug, Clone)]
+pub(crate) struct Semaphore {
+    inner: Arc<(Mutex<usize>, Condvar)>,
+}
+
+/// Releases permit back to semaphore when dropped
+#[derive(Debug)]
+pub(crate) struct Permit {
+    inner: Arc<(Mutex<usize>, Condvar)>,
+}
+
+impl Semaphore {
+    pub(crate) fn new(permits: usize) -> Semaphore {
+        Semaphore {
+            inner: Arc::new((Mutex::new(permits), Condvar::new())),
+        }
+    }
+
+    /// Blocks until permit is available
+    pub(crate) fn acquire(&self) -> Permit {
+        let (lock, available) = &*self.inner;
+        let mut permits = lock.lock().unwrap();
+        while *permits == 0 {
+            permits = available.wait(permits).unwrap();
+        }
+        *permits -= 1;
+        Permit {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let (lock, available) = &*self.inner;
+        *lock.lock().unwrap() += 1;
+        available.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_semaphore_limits_concurrency() {
+        let semaphore = Semaphore::new(2);
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let mut handles = vec![];
+        for _ in 0..8 {
+            let permit = semaphore.acquire();
+            let running = running.clone();
+            let peak = peak.clone();
+            handles.push(thread::spawn(move || {
+                let _permit = permit;
+                let current = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(current, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(10));
+                running.fetch_sub(1, Ordering::SeqCst);
+            }));
+        }
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert!(peak.load(Ordering::SeqCst) <= 2);
+    }
+}
@@ -0,0 +1,85 @@
+//! Audit trail of rpcs and replies, written regardless of log level, e.g. for
+//! compliance records of configuration changes.
+
+use crate::error::Result;
+use crate::interceptor::RpcInterceptor;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+/// Interceptor appending every sent rpc and received message to a writer, each entry
+/// prefixed with direction, UTC timestamp and host
+pub struct AuditLog<W> {
+    to: W,
+    host: String,
+}
+
+impl AuditLog<File> {
+    /// Opens `path` for appending, creating it if missing
+    pub fn open(path: impl AsRef<Path>, host: &str) -> Result<AuditLog<File>> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(AuditLog::new(file, host))
+    }
+}
+
+impl<W: Write + Send> AuditLog<W> {
+    pub fn new(to: W, host: &str) -> AuditLog<W> {
+        AuditLog {
+            to,
+            host: host.to_string(),
+        }
+    }
+
+    fn write(&mut self, arrow: &str, message: &str) {
+        let timestamp = OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .unwrap_or_default();
+        let entry = format!(
+            "{} {} {}\n{}\n",
+            arrow,
+            timestamp,
+            self.host,
+            message.trim()
+        );
+        // Whole entry in one write, so that entries of sessions sharing a file are not mixed
+        let result = self
+            .to
+            .write_all(entry.as_bytes())
+            .and_then(|_| self.to.flush());
+        if let Err(err) = result {
+            error!("Failed to write audit log: {}", err);
+        }
+    }
+}
+
+impl<W: Write + Send> RpcInterceptor for AuditLog<W> {
+    fn on_send(&mut self, rpc: &mut String) {
+        self.write(">>>", rpc);
+    }
+
+    fn on_receive(&mut self, message: &mut String) {
+        self.write("<<<", message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audit_log() {
+        let mut log = AuditLog::new(Vec::new(), "r1");
+        log.on_send(&mut "<rpc><commit/></rpc>".to_string());
+        log.on_receive(&mut "<rpc-reply><ok/></rpc-reply>\n".to_string());
+
+        let written = String::from_utf8(log.to).unwrap();
+        let lines: Vec<&str> = written.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with(">>> ") && lines[0].ends_with("Z r1"));
+        assert_eq!(lines[1], "<rpc><commit/></rpc>");
+        assert!(lines[2].starts_with("<<< "));
+        assert_eq!(lines[3], "<rpc-reply><ok/></rpc-reply>");
+    }
+}
@@ -0,0 +1,213 @@
+use crate::error::{Error, Result};
+use crate::Connection;
+
+/// Applies change to candidate datastore of every connection and commits it only if
+/// candidate validated on all devices, otherwise the changes are discarded everywhere.
+///
+/// All devices must support `:candidate` and `:validate`, which is checked before any
+/// rpc is sent. Candidate is locked on all devices before `edit` is called with the
+/// index of the connection. Failure is returned as [`Error::DeviceFailed`] with the
+/// index of the first failed device. If commit itself fails on some device, devices
+/// committed before it keep the change and the rest are discarded.
+pub fn commit_all<F>(connections: &mut [Connection], mut edit: F) -> Result<()>
+where
+    F: FnMut(usize, &mut Connection) -> Result<()>,
+{
+    check_capabilities(connections)?;
+    let mut locked = 0;
+    let mut result = prepare(connections, &mut locked, &mut edit);
+    let mut committed = 0;
+    if result.is_ok() {
+        for (index, connection) in connections.iter_mut().enumerate() {
+            if let Err(err) = connection.commit() {
+                error!(
+                    "Commit failed on device {}, {} devices committed",
+                    index, index
+                );
+                result = Err(device_failed(index, err));
+                break;
+            }
+            committed += 1;
+        }
+    }
+    if result.is_err() {
+        for connection in connections[committed..locked].iter_mut() {
+            if let Err(err) = connection.discard_changes() {
+                error!("Failed to discard changes: {}", err);
+            }
+        }
+    }
+
+    for connection in connections[..locked].iter_mut() {
+        if let Err(err) = connection.unlock("candidate") {
+            error!("Failed to unlock candidate: {}", err);
+        }
+    }
+    result
+}
+
+fn check_capabilities(connections: &[Connection]) -> Result<()> {
+    for (index, connection) in connections.iter().enumerate() {
+        let missing = if !connection.supports_candidate() {
+            "urn:ietf:params:netconf:capability:candidate:1.0"
+        } else if !connection.supports_validate() {
+            "urn:ietf:params:netconf:capability:validate:1.1"
+        } else {
+            continue;
+        };
+        return Err(device_failed(
+            index,
+            Error::CapabilityMissing(missing.to_string()),
+        ));
+    }
+    Ok(())
+}
+
+fn prepare<F>(connections: &mut [Connection], locked: &mut usize, edit: &mut F) -> Result<()>
+where
+    F: FnMut(usize, &mut Connection) -> Result<()>,
+{
+    for (index, connection) in connections.iter_mut().enumerate() {
+        connection
+            .lock("candidate")
+            .map_err(|err| device_failed(index, err))?;
+        *locked += 1;
+    }
+    for (index, connection) in connections.iter_mut().enumerate() {
+        edit(index, connection).map_err(|err| device_failed(index, err))?;
+    }
+    for (index, connection) in connections.iter_mut().enumerate() {
+        connection
+            .validate("candidate")
+            .map_err(|err| device_failed(index, err))?;
+    }
+    Ok(())
+}
+
+fn device_failed(index: usize, err: Error) -> Error {
+    Error::DeviceFailed {
+        index,
+        source: Box::new(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::mock::MockTransport;
+    use pretty_assertions::assert_eq;
+    use std::sync::{Arc, Mutex};
+
+    const ERROR: &str = "<rpc-error><error-type>app</error-type><error-tag>invalid-value</error-tag><error-severity>error</error-severity></rpc-error>";
+    const OPERATIONS: [&str; 6] = [
+        "lock",
+        "edit-config",
+        "validate",
+        "commit",
+        "discard-changes",
+        "unlock",
+    ];
+
+    type Sent = Arc<Mutex<Vec<String>>>;
+
+    /// Device replying ok to operations except the one failing with rpc-error
+    fn device(operations: &[&str], failing: Option<&str>) -> (Connection, Sent) {
+        let script = operations.iter().map(|op| {
+            let reply = if Some(*op) == failing { ERROR } else { "<ok/>" };
+            (format!("<{}", op), reply)
+        });
+        let mut transport = MockTransport::scripted(script);
+        transport.set_capabilities([
+            "urn:ietf:params:netconf:capability:candidate:1.0",
+            "urn:ietf:params:netconf:capability:validate:1.1",
+        ]);
+        let sent = transport.sent();
+        (Connection::new(transport).unwrap(), sent)
+    }
+
+    fn sent_operations(sent: &Sent) -> Vec<&'static str> {
+        sent.lock()
+            .unwrap()
+            .iter()
+            .skip(1)
+            .filter_map(|rpc| {
+                OPERATIONS
+                    .into_iter()
+                    .find(|op| rpc.contains(&format!("<{}", op)))
+            })
+            .collect()
+    }
+
+    fn edit(_: usize, connection: &mut Connection) -> Result<()> {
+        connection.edit_config("candidate", "<system/>")
+    }
+
+    #[test]
+    fn test_commit_all_discards_on_failure() {
+        let discarded = [
+            "lock",
+            "edit-config",
+            "validate",
+            "discard-changes",
+            "unlock",
+        ];
+        let (first, first_sent) = device(&discarded, None);
+        let (second, second_sent) = device(&discarded, Some("validate"));
+        let mut connections = vec![first, second];
+        match commit_all(&mut connections, edit) {
+            Err(Error::DeviceFailed { index, source }) => {
+                assert_eq!(index, 1);
+                assert!(matches!(*source, Error::Netconf(_)));
+            }
+            other => panic!("expected device failure, got {:?}", other),
+        }
+        assert_eq!(sent_operations(&first_sent), discarded);
+        assert_eq!(sent_operations(&second_sent), discarded);
+    }
+
+    #[test]
+    fn test_commit_all_discards_uncommitted() {
+        let committed = ["lock", "edit-config", "validate", "commit", "unlock"];
+        let failed = [
+            "lock",
+            "edit-config",
+            "validate",
+            "commit",
+            "discard-changes",
+            "unlock",
+        ];
+        let discarded = [
+            "lock",
+            "edit-config",
+            "validate",
+            "discard-changes",
+            "unlock",
+        ];
+        let (first, first_sent) = device(&committed, None);
+        let (second, second_sent) = device(&failed, Some("commit"));
+        let (third, third_sent) = device(&discarded, None);
+        let mut connections = vec![first, second, third];
+        assert!(matches!(
+            commit_all(&mut connections, edit),
+            Err(Error::DeviceFailed { index: 1, .. })
+        ));
+        assert_eq!(sent_operations(&first_sent), committed);
+        assert_eq!(sent_operations(&second_sent), failed);
+        assert_eq!(sent_operations(&third_sent), discarded);
+    }
+
+    #[test]
+    fn test_commit_all_checks_capabilities() {
+        let (first, first_sent) = device(&[], None);
+        let transport = MockTransport::scripted::<_, &str, &str>([]);
+        let mut connections = vec![first, Connection::new(transport).unwrap()];
+        match commit_all(&mut connections, edit) {
+            Err(Error::DeviceFailed { index, source }) => {
+                assert_eq!(index, 1);
+                assert!(matches!(*source, Error::CapabilityMissing(_)));
+            }
+            other => panic!("expected device failure, got {:?}", other),
+        }
+        assert!(sent_operations(&first_sent).is_empty());
+    }
+}
@@ -1,4 +1,6 @@
-use crate::message;
+use crate::{framer, message};
+use std::io;
+use std::sync::Arc;
 use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -7,10 +9,22 @@ pub type Result<T> = std::result::Result<T, Error>;
 pub enum Error {
     #[error(transparent)]
     Io(#[from] std::io::Error),
+    #[cfg(feature = "ssh2")]
     #[error(transparent)]
     Ssh(#[from] ssh2::Error),
+    #[cfg(feature = "russh")]
+    #[error(transparent)]
+    Russh(#[from] russh::Error),
+    #[error("authentication failed for user {0}")]
+    AuthenticationFailed(String),
     #[error(transparent)]
     SerializingFailure(#[from] quick_xml::DeError),
+    #[error("could not serialize operation: {0}")]
+    Serialize(#[from] quick_xml::SeError),
+    #[error(transparent)]
+    Xml(quick_xml::Error),
+    #[error("invalid date-time: {0}")]
+    DateTime(#[from] time::error::Format),
     #[error("remote procedure call failed:\n{0}")]
     Netconf(#[from] message::RpcReply),
     #[error("unknown datastore {}, (expected {:?})", unknown, expected)]
@@ -24,4 +38,44 @@ pub enum Error {
         actual
     )]
     MalformedChunk { expected: char, actual: char },
+    #[error("malformed chunk, skipped {skipped} bytes to the end of message")]
+    FrameResynchronized { skipped: usize },
+    #[error("message exceeds maximum size of {limit} bytes")]
+    MessageTooLarge { limit: usize },
+    #[error("chunk size {size} exceeds maximum of {limit} bytes")]
+    ChunkTooLarge { size: usize, limit: usize },
+    #[error("message contains invalid UTF-8 at byte {offset}")]
+    InvalidUtf8 { offset: usize },
+    #[error("timed out connecting to server")]
+    ConnectTimeout,
+    /// Rpc did not receive reply within [`Connection::set_timeout`](crate::Connection::set_timeout)
+    #[error("timed out waiting for reply")]
+    Timeout,
+    #[error("timed out waiting for server hello")]
+    HelloTimeout,
+    #[error("hello exchange failed: {0}")]
+    HelloFailed(String),
+    /// Server closed the session or it was closed with close-session
+    #[error("session closed")]
+    SessionClosed,
+    #[cfg(feature = "yang")]
+    /// Edit-config payload has an element not defined in modules of the name check
+    #[error("config check failed: {0}")]
+    UnknownElement(String),
+    #[error("server does not support capability {0}")]
+    CapabilityMissing(String),
+    #[error("device {index} failed: {source}")]
+    DeviceFailed { index: usize, source: Box<Error> },
+}
+
+impl From<quick_xml::Error> for Error {
+    fn from(err: quick_xml::Error) -> Self {
+        match err {
+            quick_xml::Error::Io(err) => match Arc::try_unwrap(err) {
+                Ok(err) => framer::into_error(err),
+                Err(err) => Error::Io(io::Error::new(err.kind(), err.to_string())),
+            },
+            err => Error::Xml(err),
+        }
+    }
 }
@@ -0,0 +1,255 @@
+//! Base event notifications of the NETCONF stream, see [RFC6470](https://tools.ietf.org/html/rfc6470)
+use crate::error::Result;
+use quick_xml::de::from_str;
+use serde::de::IgnoredAny;
+use serde_derive::Deserialize;
+
+/// Event of ietf-netconf-notifications module
+#[derive(Debug)]
+pub enum NetconfEvent {
+    ConfigChange(ConfigChange),
+    CapabilityChange(CapabilityChange),
+    SessionStart(SessionStart),
+    SessionEnd(SessionEnd),
+    ConfirmedCommit(ConfirmedCommit),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct BaseNotification {
+    netconf_config_change: Option<ConfigChange>,
+    netconf_capability_change: Option<CapabilityChange>,
+    netconf_session_start: Option<SessionStart>,
+    netconf_session_end: Option<SessionEnd>,
+    netconf_confirmed_commit: Option<ConfirmedCommit>,
+}
+
+impl NetconfEvent {
+    /// Parses event from raw notification, `None` if it is not a base notification
+    pub fn from_notification(xml: &str) -> Result<Option<NetconfEvent>> {
+        let notification: BaseNotification = from_str(xml)?;
+        let event = if let Some(event) = notification.netconf_config_change {
+            NetconfEvent::ConfigChange(event)
+        } else if let Some(event) = notification.netconf_capability_change {
+            NetconfEvent::CapabilityChange(event)
+        } else if let Some(event) = notification.netconf_session_start {
+            NetconfEvent::SessionStart(event)
+        } else if let Some(event) = notification.netconf_session_end {
+            NetconfEvent::SessionEnd(event)
+        } else if let Some(event) = notification.netconf_confirmed_commit {
+            NetconfEvent::ConfirmedCommit(event)
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(event))
+    }
+}
+
+/// Originator of a change, the server itself or a management session
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ChangedBy {
+    server: Option<IgnoredAny>,
+    pub username: Option<String>,
+    pub session_id: Option<u64>,
+    pub source_host: Option<String>,
+}
+
+impl ChangedBy {
+    /// Change was caused by the server, e.g. internal process
+    pub fn is_server(&self) -> bool {
+        self.server.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EditOperation {
+    Merge,
+    Replace,
+    Create,
+    Delete,
+    Remove,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Edit {
+    /// Instance-identifier of the changed node
+    pub target: Option<String>,
+    pub operation: Option<EditOperation>,
+}
+
+/// `netconf-config-change`, configuration of running or startup datastore changed
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ConfigChange {
+    pub changed_by: Option<ChangedBy>,
+    /// Changed datastore, `running` when omitted
+    #[serde(default = "running")]
+    pub datastore: String,
+    #[serde(default)]
+    pub edit: Vec<Edit>,
+}
+
+fn running() -> String {
+    "running".to_string()
+}
+
+/// `netconf-capability-change`, capabilities of the server changed
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CapabilityChange {
+    pub changed_by: Option<ChangedBy>,
+    #[serde(default)]
+    pub added_capability: Vec<String>,
+    #[serde(default)]
+    pub deleted_capability: Vec<String>,
+    #[serde(default)]
+    pub modified_capability: Vec<String>,
+}
+
+/// `netconf-session-start`, management session started
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SessionStart {
+    pub username: String,
+    pub session_id: u64,
+    pub source_host: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TerminationReason {
+    Closed,
+    Killed,
+    Dropped,
+    Timeout,
+    BadHello,
+    Other,
+}
+
+/// `netconf-session-end`, management session ended
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SessionEnd {
+    pub username: String,
+    pub session_id: u64,
+    pub source_host: Option<String>,
+    /// Session that killed this one with kill-session
+    pub killed_by: Option<u64>,
+    pub termination_reason: TerminationReason,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConfirmEvent {
+    Start,
+    Cancel,
+    Timeout,
+    Extend,
+    Complete,
+}
+
+/// `netconf-confirmed-commit`, state of confirmed commit changed
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ConfirmedCommit {
+    /// Session of the commit, not present on timeout
+    pub username: Option<String>,
+    pub session_id: Option<u64>,
+    pub source_host: Option<String>,
+    pub confirm_event: ConfirmEvent,
+    /// Seconds until rollback, present on start and extend
+    pub timeout: Option<u32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn parse(event: &str) -> NetconfEvent {
+        let xml = format!(
+            r#"<notification xmlns="urn:ietf:params:xml:ns:netconf:notification:1.0"><eventTime>2024-05-01T10:00:00Z</eventTime>{}</notification>"#,
+            event
+        );
+        NetconfEvent::from_notification(&xml).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_config_change() {
+        let event = parse(
+            r#"<netconf-config-change xmlns="urn:ietf:params:xml:ns:yang:ietf-netconf-notifications">
+  <changed-by><username>admin</username><session-id>12</session-id><source-host>192.0.2.1</source-host></changed-by>
+  <datastore>running</datastore>
+  <edit><target xmlns:if="urn:ietf:params:xml:ns:yang:ietf-interfaces">/if:interfaces/if:interface[if:name='eth0']</target><operation>merge</operation></edit>
+  <edit><target>/system</target><operation>delete</operation></edit>
+</netconf-config-change>"#,
+        );
+        let NetconfEvent::ConfigChange(change) = event else {
+            panic!("unexpected event {:?}", event);
+        };
+        let changed_by = change.changed_by.unwrap();
+        assert!(!changed_by.is_server());
+        assert_eq!(changed_by.username.as_deref(), Some("admin"));
+        assert_eq!(changed_by.session_id, Some(12));
+        assert_eq!(change.datastore, "running");
+        assert_eq!(change.edit.len(), 2);
+        assert_eq!(change.edit[1].operation, Some(EditOperation::Delete));
+    }
+
+    #[test]
+    fn test_capability_change() {
+        let event = parse(
+            r#"<netconf-capability-change xmlns="urn:ietf:params:xml:ns:yang:ietf-netconf-notifications">
+  <changed-by><server/></changed-by>
+  <added-capability>urn:ietf:params:netconf:capability:candidate:1.0</added-capability>
+  <added-capability>urn:ietf:params:netconf:capability:startup:1.0</added-capability>
+</netconf-capability-change>"#,
+        );
+        let NetconfEvent::CapabilityChange(change) = event else {
+            panic!("unexpected event {:?}", event);
+        };
+        assert!(change.changed_by.unwrap().is_server());
+        assert_eq!(change.added_capability.len(), 2);
+        assert!(change.deleted_capability.is_empty());
+    }
+
+    #[test]
+    fn test_session_events() {
+        let event = parse(
+            r#"<netconf-session-start xmlns="urn:ietf:params:xml:ns:yang:ietf-netconf-notifications"><username>admin</username><session-id>3</session-id><source-host>192.0.2.1</source-host></netconf-session-start>"#,
+        );
+        assert!(matches!(
+            event,
+            NetconfEvent::SessionStart(SessionStart { session_id: 3, .. })
+        ));
+
+        let event = parse(
+            r#"<netconf-session-end xmlns="urn:ietf:params:xml:ns:yang:ietf-netconf-notifications"><username>admin</username><session-id>3</session-id><killed-by>4</killed-by><termination-reason>killed</termination-reason></netconf-session-end>"#,
+        );
+        let NetconfEvent::SessionEnd(end) = event else {
+            panic!("unexpected event {:?}", event);
+        };
+        assert_eq!(end.killed_by, Some(4));
+        assert_eq!(end.termination_reason, TerminationReason::Killed);
+    }
+
+    #[test]
+    fn test_confirmed_commit() {
+        let event = parse(
+            r#"<netconf-confirmed-commit xmlns="urn:ietf:params:xml:ns:yang:ietf-netconf-notifications"><username>admin</username><session-id>5</session-id><confirm-event>start</confirm-event><timeout>600</timeout></netconf-confirmed-commit>"#,
+        );
+        let NetconfEvent::ConfirmedCommit(commit) = event else {
+            panic!("unexpected event {:?}", event);
+        };
+        assert_eq!(commit.confirm_event, ConfirmEvent::Start);
+        assert_eq!(commit.timeout, Some(600));
+    }
+
+    #[test]
+    fn test_other_notification() {
+        let xml = r#"<notification xmlns="urn:ietf:params:xml:ns:netconf:notification:1.0"><eventTime>2024-05-01T10:00:00Z</eventTime><link-down xmlns="urn:example:events"/></notification>"#;
+        assert!(NetconfEvent::from_notification(xml).unwrap().is_none());
+    }
+}
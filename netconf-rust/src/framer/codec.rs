@@ -0,0 +1,316 @@
+use super::{put_chunked, NETCONF_1_0_TERMINATOR, NETCONF_1_1_TERMINATOR};
+use crate::error::{Error, Result};
+use crate::transport::MessageLimits;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use memmem::{Searcher, TwoWaySearcher};
+use tokio_util::codec::{Decoder, Encoder};
+
+/// NETCONF framing as [`Decoder`] and [`Encoder`], for use with `tokio_util::codec::Framed`
+/// over any async stream. Decodes to message bodies and encodes messages given as `&str`.
+///
+/// Starts with end-of-message framing, [`NetconfCodec::upgrade`] switches to chunked framing
+/// after both peers have announced base 1.1 capability.
+/// See [RFC6242](https://tools.ietf.org/html/rfc6242#section-4)
+#[derive(Debug, Default)]
+pub struct NetconfCodec {
+    upgraded: bool,
+    limits: MessageLimits,
+    max_outbound_chunk_size: Option<usize>,
+    lossy_utf8: bool,
+    message: Vec<u8>,
+}
+
+impl NetconfCodec {
+    pub fn new() -> NetconfCodec {
+        NetconfCodec::default()
+    }
+
+    pub fn upgrade(&mut self) {
+        self.upgraded = true;
+    }
+
+    pub fn set_limits(&mut self, limits: MessageLimits) {
+        self.limits = limits;
+    }
+
+    /// Replaces invalid UTF-8 in decoded messages instead of failing with
+    /// [`Error::InvalidUtf8`]
+    pub fn set_lossy_utf8(&mut self, lossy: bool) {
+        self.lossy_utf8 = lossy;
+    }
+
+    /// Splits encoded messages to chunks of at most `size` bytes, for servers limiting
+    /// chunk size. By default each message is encoded as one chunk.
+    pub fn set_max_outbound_chunk_size(&mut self, size: Option<usize>) {
+        self.max_outbound_chunk_size = size;
+    }
+
+    /// Decodes next message body without UTF-8 validation or trimming, copying
+    /// nothing for end-of-message framing and only the chunk payloads for chunked framing
+    fn decode_bytes(&mut self, src: &mut BytesMut) -> Result<Option<Bytes>> {
+        if self.upgraded {
+            self.decode_chunked(src)
+        } else {
+            self.decode_eom(src)
+        }
+    }
+
+    fn decode_eom(&mut self, src: &mut BytesMut) -> Result<Option<Bytes>> {
+        let searcher = TwoWaySearcher::new(NETCONF_1_0_TERMINATOR.as_bytes());
+        match searcher.search_in(src) {
+            Some(end) => {
+                self.check_message_size(end)?;
+                let message = src.split_to(end).freeze();
+                src.advance(NETCONF_1_0_TERMINATOR.len());
+                Ok(Some(message))
+            }
+            None => {
+                self.check_message_size(src.len().saturating_sub(NETCONF_1_0_TERMINATOR.len()))?;
+                Ok(None)
+            }
+        }
+    }
+
+    fn decode_chunked(&mut self, src: &mut BytesMut) -> Result<Option<Bytes>> {
+        loop {
+            let Some((header_len, chunk_size)) = parse_header(src)? else {
+                return Ok(None);
+            };
+            if chunk_size == 0 {
+                src.advance(header_len);
+                return Ok(Some(Bytes::from(std::mem::take(&mut self.message))));
+            }
+
+            if let Some(limit) = self.limits.max_chunk_size {
+                if chunk_size > limit {
+                    return Err(Error::ChunkTooLarge {
+                        size: chunk_size,
+                        limit,
+                    });
+                }
+            }
+            self.check_message_size(self.message.len() + chunk_size)?;
+            if src.len() < header_len + chunk_size {
+                src.reserve(header_len + chunk_size - src.len());
+                return Ok(None);
+            }
+            src.advance(header_len);
+            self.message.extend_from_slice(&src.split_to(chunk_size));
+        }
+    }
+
+    fn check_message_size(&self, size: usize) -> Result<()> {
+        match self.limits.max_message_size {
+            Some(limit) if size > limit => Err(Error::MessageTooLarge { limit }),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Decoder for NetconfCodec {
+    type Item = String;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<String>> {
+        self.decode_bytes(src)?
+            .map(|message| into_message(&message, self.lossy_utf8))
+            .transpose()
+    }
+}
+
+/// Like [`NetconfCodec`], but decodes to message bodies as [`Bytes`], leaving UTF-8
+/// validation and trimming of surrounding whitespace to the caller. Avoids copying
+/// large replies, e.g. when they are written to a file or parsed from bytes.
+#[derive(Debug, Default)]
+pub struct NetconfBytesCodec {
+    inner: NetconfCodec,
+}
+
+impl NetconfBytesCodec {
+    pub fn new() -> NetconfBytesCodec {
+        NetconfBytesCodec::default()
+    }
+
+    pub fn upgrade(&mut self) {
+        self.inner.upgrade();
+    }
+
+    pub fn set_limits(&mut self, limits: MessageLimits) {
+        self.inner.set_limits(limits);
+    }
+
+    pub fn set_max_outbound_chunk_size(&mut self, size: Option<usize>) {
+        self.inner.set_max_outbound_chunk_size(size);
+    }
+}
+
+impl Decoder for NetconfBytesCodec {
+    type Item = Bytes;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Bytes>> {
+        self.inner.decode_bytes(src)
+    }
+}
+
+impl Encoder<&str> for NetconfBytesCodec {
+    type Error = Error;
+
+    fn encode(&mut self, message: &str, dst: &mut BytesMut) -> Result<()> {
+        self.inner.encode(message, dst)
+    }
+}
+
+impl Encoder<&str> for NetconfCodec {
+    type Error = Error;
+
+    fn encode(&mut self, message: &str, dst: &mut BytesMut) -> Result<()> {
+        if self.upgraded {
+            dst.reserve(message.len() + NETCONF_1_1_TERMINATOR.len() + 16);
+            put_chunked(dst, message.as_bytes(), self.max_outbound_chunk_size);
+        } else {
+            dst.reserve(message.len() + NETCONF_1_0_TERMINATOR.len());
+            dst.put(message.as_bytes());
+            dst.put(NETCONF_1_0_TERMINATOR.as_bytes());
+        }
+        Ok(())
+    }
+}
+
+/// Parses chunk header at the start of `src`, returning its length and chunk size,
+/// which is 0 for end-of-chunks. `None` if the header is not complete yet.
+fn parse_header(src: &[u8]) -> Result<Option<(usize, usize)>> {
+    for (index, expected) in [b'\n', b'#'].into_iter().enumerate() {
+        match src.get(index) {
+            Some(&actual) if actual != expected => {
+                return Err(Error::MalformedChunk {
+                    expected: expected.into(),
+                    actual: actual.into(),
+                })
+            }
+            Some(_) => {}
+            None => return Ok(None),
+        }
+    }
+    if src.get(2) == Some(&b'#') {
+        return match src.get(3) {
+            Some(b'\n') => Ok(Some((4, 0))),
+            Some(&actual) => Err(Error::MalformedChunk {
+                expected: '\n',
+                actual: actual.into(),
+            }),
+            None => Ok(None),
+        };
+    }
+
+    let mut chunk_size: u32 = 0;
+    for (index, &byte) in src.iter().enumerate().skip(2) {
+        if byte == b'\n' && index > 2 {
+            return Ok(Some((index + 1, chunk_size as usize)));
+        }
+        if !byte.is_ascii_digit() {
+            return Err(Error::MalformedChunk {
+                expected: '0',
+                actual: byte.into(),
+            });
+        }
+        chunk_size = chunk_size
+            .checked_mul(10)
+            .and_then(|size| size.checked_add(u32::from(byte - b'0')))
+            .ok_or(Error::MalformedChunk {
+                expected: '\n',
+                actual: byte.into(),
+            })?;
+    }
+    Ok(None)
+}
+
+fn into_message(bytes: &[u8], lossy: bool) -> Result<String> {
+    match std::str::from_utf8(bytes) {
+        Ok(message) => Ok(message.trim().to_string()),
+        Err(_) if lossy => Ok(String::from_utf8_lossy(bytes).trim().to_string()),
+        Err(err) => Err(Error::InvalidUtf8 {
+            offset: err.valid_up_to(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_decode_eom_in_parts() {
+        let mut codec = NetconfCodec::new();
+        let mut buffer = BytesMut::from("<hello/>]]>");
+        assert_eq!(codec.decode(&mut buffer).unwrap(), None);
+        buffer.extend_from_slice(b"]]><rpc-reply/>]]>]]>");
+        assert_eq!(codec.decode(&mut buffer).unwrap().unwrap(), "<hello/>");
+        assert_eq!(codec.decode(&mut buffer).unwrap().unwrap(), "<rpc-reply/>");
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_decode_chunked_in_parts() {
+        let mut codec = NetconfCodec::new();
+        codec.upgrade();
+        let input = b"\n#4\n<rpc\n#17\n-reply message-id\n#5\n=\"1\"/\n#1\n>\n##\n";
+        let mut buffer = BytesMut::new();
+        let mut messages = Vec::new();
+        for byte in input {
+            buffer.put_u8(*byte);
+            if let Some(message) = codec.decode(&mut buffer).unwrap() {
+                messages.push(message);
+            }
+        }
+        assert_eq!(messages, vec![r#"<rpc-reply message-id="1"/>"#]);
+    }
+
+    #[test]
+    fn test_decode_bytes() {
+        let mut codec = NetconfBytesCodec::new();
+        let mut buffer = BytesMut::from("\n<hello/>\n]]>]]>");
+        assert_eq!(codec.decode(&mut buffer).unwrap().unwrap(), "\n<hello/>\n");
+
+        codec.upgrade();
+        let mut buffer = BytesMut::from("\n#4\n<rpc\n#8\n-reply/>\n##\n");
+        assert_eq!(codec.decode(&mut buffer).unwrap().unwrap(), "<rpc-reply/>");
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_decode_malformed_chunk() {
+        let mut codec = NetconfCodec::new();
+        codec.upgrade();
+        let mut buffer = BytesMut::from("\n#x\n");
+        assert!(matches!(
+            codec.decode(&mut buffer),
+            Err(Error::MalformedChunk { .. })
+        ));
+    }
+
+    #[test]
+    fn test_encode_roundtrip() {
+        for upgraded in [false, true] {
+            let mut codec = NetconfCodec::new();
+            if upgraded {
+                codec.upgrade();
+            }
+            let mut buffer = BytesMut::new();
+            codec.encode("<rpc/>", &mut buffer).unwrap();
+            assert_eq!(codec.decode(&mut buffer).unwrap().unwrap(), "<rpc/>");
+        }
+    }
+
+    #[test]
+    fn test_encode_max_chunk_size() {
+        let mut codec = NetconfCodec::new();
+        codec.upgrade();
+        codec.set_max_outbound_chunk_size(Some(4));
+        let mut buffer = BytesMut::new();
+        codec.encode("<get-config/>", &mut buffer).unwrap();
+        assert_eq!(&buffer[..], b"\n#4\n<get\n#4\n-con\n#4\nfig/\n#1\n>\n##\n");
+        assert_eq!(codec.decode(&mut buffer).unwrap().unwrap(), "<get-config/>");
+    }
+}
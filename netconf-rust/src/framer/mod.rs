@@ -1,22 +1,46 @@
 use crate::error::{Error, Result};
+use crate::transport::MessageLimits;
+use crate::wire::{Direction, WireObserver};
+use bytes::{Buf, BufMut, BytesMut};
 use memmem::{Searcher, TwoWaySearcher};
+use std::io;
 use std::io::{Read, Write};
+use std::time::SystemTime;
+
+#[cfg(feature = "codec")]
+pub(crate) mod codec;
 
 const NETCONF_1_0_TERMINATOR: &str = "]]>]]>";
 const NETCONF_1_1_TERMINATOR: &str = "##";
+const READ_SIZE: usize = 8 * 1024;
 
 /// Trait for NETCONF message framing
 /// See [RFC6242](https://tools.ietf.org/html/rfc6242#section-4.1)
 pub(crate) struct Framer {
-    read_buffer: Vec<u8>,
+    read_buffer: BytesMut,
+    /// Frames not yet written when autoflush is off
+    write_buffer: Vec<u8>,
+    autoflush: bool,
+    max_outbound_chunk_size: Option<usize>,
+    lossy_utf8: bool,
     upgraded: bool,
+    recovery: bool,
+    limits: MessageLimits,
+    observer: Option<Box<dyn WireObserver>>,
 }
 
 impl Framer {
     pub(crate) fn new() -> Framer {
         Framer {
-            read_buffer: Vec::new(),
+            read_buffer: BytesMut::new(),
+            write_buffer: Vec::new(),
+            autoflush: true,
+            max_outbound_chunk_size: None,
+            lossy_utf8: false,
             upgraded: false,
+            recovery: false,
+            limits: MessageLimits::default(),
+            observer: None,
         }
     }
 
@@ -24,56 +48,105 @@ impl Framer {
         self.upgraded = true;
     }
 
-    pub(crate) fn read_xml<R>(&mut self, mut from: R) -> Result<String>
+    pub(crate) fn set_recovery(&mut self, recovery: bool) {
+        self.recovery = recovery;
+    }
+
+    pub(crate) fn set_observer(&mut self, observer: Box<dyn WireObserver>) {
+        self.observer = Some(observer);
+    }
+
+    pub(crate) fn set_limits(&mut self, limits: MessageLimits) {
+        self.limits = limits;
+    }
+
+    /// Splits sent messages to chunks of at most `size` bytes, by default
+    /// each message is sent as one chunk
+    pub(crate) fn set_max_outbound_chunk_size(&mut self, size: Option<usize>) {
+        self.max_outbound_chunk_size = size;
+    }
+
+    /// Replaces invalid UTF-8 in received messages instead of failing with
+    /// [`Error::InvalidUtf8`]
+    pub(crate) fn set_lossy_utf8(&mut self, lossy: bool) {
+        self.lossy_utf8 = lossy;
+    }
+
+    /// When off, frames are buffered by [`Framer::write_xml`] until [`Framer::flush`]
+    pub(crate) fn set_autoflush(&mut self, autoflush: bool) {
+        self.autoflush = autoflush;
+    }
+
+    pub(crate) fn read_xml<R>(&mut self, from: R) -> Result<String>
     where
         R: Read,
     {
-        if self.upgraded {
-            loop {
-                let chunk_size: u32 = self.read_header(&mut from)?;
-                if chunk_size == 0 {
-                    break;
-                }
-                let mut buffer = vec![0u8; chunk_size as usize];
-                from.read(&mut buffer)?;
-                self.read_buffer.extend(&buffer);
-            }
-            let response = String::from_utf8_lossy(&self.read_buffer).to_string();
-            self.read_buffer.drain(..);
-            Ok(response)
-        } else {
-            let mut buffer = [0u8; 128];
-            let search = TwoWaySearcher::new(NETCONF_1_0_TERMINATOR.as_bytes());
-            while search.search_in(&self.read_buffer).is_none() {
-                let bytes = from.read(&mut buffer)?;
-                self.read_buffer.extend(&buffer[..bytes]);
-            }
-            let pos = search.search_in(&self.read_buffer).unwrap();
-            let resp = String::from_utf8_lossy(&self.read_buffer[..pos]).to_string();
-            self.read_buffer.drain(0..(pos + 6));
-            Ok(resp.trim().to_string())
+        let mut message = Vec::new();
+        self.message_reader(from)
+            .read_to_end(&mut message)
+            .map_err(into_error)?;
+        into_trimmed_string(message, self.lossy_utf8)
+    }
+
+    /// Returns reader yielding the body of the next message as it arrives,
+    /// without buffering the whole message in memory.
+    pub(crate) fn message_reader<R>(&mut self, from: R) -> MessageReader<'_, R>
+    where
+        R: Read,
+    {
+        MessageReader {
+            framer: self,
+            from,
+            chunk_remaining: 0,
+            received: 0,
+            done: false,
         }
     }
 
-    pub(crate) fn write_xml<T>(&mut self, rpc: &str, mut to: T) -> Result<()>
+    pub(crate) fn write_xml<T>(&mut self, rpc: &str, to: T) -> Result<()>
     where
         T: Write,
     {
+        // Whole frame in one write, separate writes for header and trailer would be
+        // sent as small packets, since SSH channels don't support vectored writes
         if self.upgraded {
-            write!(
-                to,
-                "\n#{}\n{}\n{}\n",
-                rpc.len(),
-                rpc,
-                NETCONF_1_1_TERMINATOR
-            )?;
+            put_chunked(
+                &mut self.write_buffer,
+                rpc.as_bytes(),
+                self.max_outbound_chunk_size,
+            );
         } else {
-            write!(to, "{}{}", rpc, NETCONF_1_0_TERMINATOR)?;
+            self.write_buffer
+                .reserve(rpc.len() + NETCONF_1_0_TERMINATOR.len());
+            self.write_buffer.extend_from_slice(rpc.as_bytes());
+            self.write_buffer
+                .extend_from_slice(NETCONF_1_0_TERMINATOR.as_bytes());
+        }
+        if self.autoflush {
+            self.flush(to)?;
         }
         Ok(())
     }
 
-    fn read_header<R>(&mut self, mut from: R) -> Result<u32>
+    /// Writes buffered frames at once
+    pub(crate) fn flush<T>(&mut self, mut to: T) -> Result<()>
+    where
+        T: Write,
+    {
+        if self.write_buffer.is_empty() {
+            return Ok(());
+        }
+        let result = to.write_all(&self.write_buffer).and_then(|_| to.flush());
+        if result.is_ok() {
+            if let Some(observer) = &mut self.observer {
+                observer.on_frame(Direction::Sent, SystemTime::now(), &self.write_buffer);
+            }
+        }
+        self.write_buffer.clear();
+        Ok(result?)
+    }
+
+    fn read_header<R>(mut from: R) -> Result<u32>
     where
         R: Read,
     {
@@ -105,14 +178,226 @@ impl Framer {
             if last_read == b'\n' {
                 return Ok(chunk_size);
             }
-            if last_read < b'0' || last_read > b'9' {
+            if !last_read.is_ascii_digit() {
                 return Err(Error::MalformedChunk {
                     expected: '0',
                     actual: last_read.into(),
                 });
             }
-            chunk_size = chunk_size * 10 + u32::from(last_read - b'0');
+            chunk_size = chunk_size
+                .checked_mul(10)
+                .and_then(|size| size.checked_add(u32::from(last_read - b'0')))
+                .ok_or(Error::MalformedChunk {
+                    expected: '\n',
+                    actual: last_read.into(),
+                })?;
+        }
+    }
+}
+
+/// Reader over a single framed NETCONF message, returns EOF at the end of message.
+pub(crate) struct MessageReader<'a, R> {
+    framer: &'a mut Framer,
+    from: R,
+    chunk_remaining: usize,
+    received: usize,
+    done: bool,
+}
+
+impl<R: Read> MessageReader<'_, R> {
+    fn read_chunked(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if self.chunk_remaining == 0 {
+            let chunk_size = match Framer::read_header(self.source()) {
+                Err(Error::MalformedChunk { expected, actual }) if self.framer.recovery => {
+                    warn!("Malformed chunk, expected '{}' got '{}'", expected, actual);
+                    return Err(self.resynchronize());
+                }
+                result => result?,
+            };
+            if chunk_size == 0 {
+                self.done = true;
+                return Ok(0);
+            }
+            let chunk_size = chunk_size as usize;
+            if let Some(limit) = self.framer.limits.max_chunk_size {
+                if chunk_size > limit {
+                    return Err(Error::ChunkTooLarge {
+                        size: chunk_size,
+                        limit,
+                    });
+                }
+            }
+            self.check_message_size(self.received + chunk_size)?;
+            self.received += chunk_size;
+            self.chunk_remaining = chunk_size;
+        }
+
+        let len = buf.len().min(self.chunk_remaining);
+        let bytes = self.source().read(&mut buf[..len])?;
+        if bytes == 0 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        self.chunk_remaining -= bytes;
+        Ok(bytes)
+    }
+
+    fn read_eom(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let search = TwoWaySearcher::new(NETCONF_1_0_TERMINATOR.as_bytes());
+        let read_buffer = &mut self.framer.read_buffer;
+        loop {
+            // Everything before the terminator, or before a possible partial
+            // terminator at the end of the buffer, is safe to hand out
+            let (available, found) = match search.search_in(read_buffer) {
+                Some(pos) => (pos, true),
+                None => (
+                    read_buffer
+                        .len()
+                        .saturating_sub(NETCONF_1_0_TERMINATOR.len() - 1),
+                    false,
+                ),
+            };
+            if available > 0 {
+                let len = buf.len().min(available);
+                buf[..len].copy_from_slice(&read_buffer[..len]);
+                read_buffer.advance(len);
+                self.received += len;
+                return Ok(len);
+            }
+            if found {
+                read_buffer.advance(NETCONF_1_0_TERMINATOR.len());
+                self.done = true;
+                return Ok(0);
+            }
+
+            let mut buffer = [0u8; READ_SIZE];
+            let bytes = self.from.read(&mut buffer)?;
+            if bytes == 0 {
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+            }
+            if let Some(observer) = &mut self.framer.observer {
+                observer.on_frame(Direction::Received, SystemTime::now(), &buffer[..bytes]);
+            }
+            read_buffer.extend_from_slice(&buffer[..bytes]);
+            if let Some(limit) = self.framer.limits.max_message_size {
+                if self.received + read_buffer.len() > limit + NETCONF_1_0_TERMINATOR.len() {
+                    return Err(Error::MessageTooLarge { limit });
+                }
+            }
+        }
+    }
+
+    /// Skips input until end-of-chunks marker, so the next message can be read
+    fn resynchronize(&mut self) -> Error {
+        let marker = format!("\n{}\n", NETCONF_1_1_TERMINATOR);
+        let mut window = Vec::with_capacity(marker.len());
+        let mut skipped = 0;
+        let mut source = self.source();
+        while window != marker.as_bytes() {
+            let mut byte = [0u8; 1];
+            if let Err(err) = source.read_exact(&mut byte) {
+                return err.into();
+            }
+            if window.len() == marker.len() {
+                window.remove(0);
+            }
+            window.push(byte[0]);
+            skipped += 1;
+        }
+        self.done = true;
+        Error::FrameResynchronized { skipped }
+    }
+
+    /// Source of chunked data, bytes buffered while reading 1.0 messages come first
+    fn source(&mut self) -> BufferedSource<'_, R> {
+        BufferedSource {
+            buffer: &mut self.framer.read_buffer,
+            from: &mut self.from,
+            observer: &mut self.framer.observer,
+        }
+    }
+
+    fn check_message_size(&self, size: usize) -> Result<()> {
+        match self.framer.limits.max_message_size {
+            Some(limit) if size > limit => Err(Error::MessageTooLarge { limit }),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<R: Read> Read for MessageReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.done || buf.is_empty() {
+            return Ok(0);
+        }
+        let result = if self.framer.upgraded {
+            self.read_chunked(buf)
+        } else {
+            self.read_eom(buf)
+        };
+        result.map_err(|err| match err {
+            Error::Io(err) => err,
+            err => io::Error::new(io::ErrorKind::InvalidData, err),
+        })
+    }
+}
+
+struct BufferedSource<'a, R> {
+    buffer: &'a mut BytesMut,
+    from: &'a mut R,
+    observer: &'a mut Option<Box<dyn WireObserver>>,
+}
+
+impl<R: Read> Read for BufferedSource<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.buffer.is_empty() {
+            let bytes = self.from.read(buf)?;
+            if let Some(observer) = self.observer.as_mut().filter(|_| bytes > 0) {
+                observer.on_frame(Direction::Received, SystemTime::now(), &buf[..bytes]);
+            }
+            return Ok(bytes);
         }
+        let len = buf.len().min(self.buffer.len());
+        buf[..len].copy_from_slice(&self.buffer[..len]);
+        self.buffer.advance(len);
+        Ok(len)
+    }
+}
+
+/// Appends `message` in chunks of at most `max_chunk_size` bytes followed by
+/// end-of-chunks, see [RFC6242](https://tools.ietf.org/html/rfc6242#section-4.2)
+pub(crate) fn put_chunked(dst: &mut impl BufMut, message: &[u8], max_chunk_size: Option<usize>) {
+    let chunk_size = max_chunk_size.unwrap_or(message.len()).max(1);
+    for chunk in message.chunks(chunk_size) {
+        dst.put_slice(format!("\n#{}\n", chunk.len()).as_bytes());
+        dst.put_slice(chunk);
+    }
+    dst.put_slice(format!("\n{}\n", NETCONF_1_1_TERMINATOR).as_bytes());
+}
+
+/// Converts message to string reusing its buffer. Invalid UTF-8 fails with
+/// [`Error::InvalidUtf8`], or is copied with replacements if `lossy`.
+fn into_trimmed_string(message: Vec<u8>, lossy: bool) -> Result<String> {
+    let mut message = match String::from_utf8(message) {
+        Ok(message) => message,
+        Err(err) if lossy => String::from_utf8_lossy(err.as_bytes()).into_owned(),
+        Err(err) => {
+            return Err(Error::InvalidUtf8 {
+                offset: err.utf8_error().valid_up_to(),
+            })
+        }
+    };
+    message.truncate(message.trim_end().len());
+    let start = message.len() - message.trim_start().len();
+    message.drain(..start);
+    Ok(message)
+}
+
+/// Unwraps framing errors passed through [`io::Error`] by [`MessageReader`]
+pub(crate) fn into_error(err: io::Error) -> Error {
+    if err.get_ref().is_some_and(|inner| inner.is::<Error>()) {
+        *err.into_inner().unwrap().downcast::<Error>().unwrap()
+    } else {
+        Error::Io(err)
     }
 }
 
@@ -120,7 +405,24 @@ impl Framer {
 mod tests {
     use super::*;
     use pretty_assertions::assert_eq;
-    use std::io::Cursor;
+    use std::io::{Cursor, IoSlice};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_invalid_utf8() {
+        let mut framer = Framer::new();
+        let received = b"<data>caf\xe9</data>]]>]]>".repeat(2);
+        let mut from = Cursor::new(received);
+        assert!(matches!(
+            framer.read_xml(&mut from),
+            Err(Error::InvalidUtf8 { offset: 9 })
+        ));
+        framer.set_lossy_utf8(true);
+        assert_eq!(
+            framer.read_xml(&mut from).unwrap(),
+            "<data>caf\u{fffd}</data>"
+        );
+    }
 
     #[test]
     fn test_chunked_framer() {
@@ -272,4 +574,165 @@ mod tests {
 "#;
         assert_eq!(resp, expected.trim());
     }
+
+    #[test]
+    fn test_message_size_limits() {
+        let mut framer = Framer::new();
+        framer.upgrade();
+        framer.set_limits(MessageLimits {
+            max_message_size: Some(16),
+            max_chunk_size: Some(10),
+        });
+
+        let chunked = "\n#10\n<rpc-reply\n#10\n></rpc-rep\n#3\nly>\n##\n";
+        match framer.read_xml(Cursor::new(chunked)) {
+            Err(Error::MessageTooLarge { limit }) => assert_eq!(limit, 16),
+            other => panic!("expected too large message, got {:?}", other),
+        }
+
+        let mut framer = Framer::new();
+        framer.upgrade();
+        framer.set_limits(MessageLimits {
+            max_message_size: None,
+            max_chunk_size: Some(10),
+        });
+        match framer.read_xml(Cursor::new("\n#23\n<rpc-reply></rpc-reply>\n##\n")) {
+            Err(Error::ChunkTooLarge { size, limit }) => assert_eq!((size, limit), (23, 10)),
+            other => panic!("expected too large chunk, got {:?}", other),
+        }
+
+        let mut framer = Framer::new();
+        framer.set_limits(MessageLimits {
+            max_message_size: Some(16),
+            max_chunk_size: None,
+        });
+        let eom = "<rpc-reply>".repeat(50) + "]]>]]>";
+        match framer.read_xml(Cursor::new(eom)) {
+            Err(Error::MessageTooLarge { limit }) => assert_eq!(limit, 16),
+            other => panic!("expected too large message, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_upgrade_with_buffered_data() {
+        let mut framer = Framer::new();
+        let mut input = Cursor::new("<hello/>]]>]]>\n#12\n<rpc-reply/>\n##\n");
+        assert_eq!(framer.read_xml(&mut input).unwrap(), "<hello/>");
+        framer.upgrade();
+        assert_eq!(framer.read_xml(&mut input).unwrap(), "<rpc-reply/>");
+    }
+
+    #[test]
+    fn test_resynchronize_after_malformed_chunk() {
+        let mut framer = Framer::new();
+        framer.upgrade();
+        framer.set_recovery(true);
+        let mut input = Cursor::new("\n#5\n<rpc-\n#x3\nly/>\n##\n\n#12\n<rpc-reply/>\n##\n");
+        match framer.read_xml(&mut input) {
+            Err(Error::FrameResynchronized { skipped }) => assert_eq!(skipped, 10),
+            other => panic!("expected resynchronization, got {:?}", other),
+        }
+        assert_eq!(framer.read_xml(&mut input).unwrap(), "<rpc-reply/>");
+    }
+
+    #[test]
+    fn test_wire_observer() {
+        type Frames = Arc<Mutex<Vec<(Direction, Vec<u8>)>>>;
+        struct Capture(Frames);
+
+        impl WireObserver for Capture {
+            fn on_frame(&mut self, direction: Direction, _timestamp: SystemTime, data: &[u8]) {
+                self.0.lock().unwrap().push((direction, data.to_vec()));
+            }
+        }
+
+        let frames = Frames::default();
+        let mut framer = Framer::new();
+        framer.upgrade();
+        framer.set_observer(Box::new(Capture(Arc::clone(&frames))));
+
+        framer.write_xml("<rpc/>", Vec::new()).unwrap();
+        let received = "\n#12\n<rpc-reply/>\n##\n";
+        framer.read_xml(Cursor::new(received)).unwrap();
+
+        let frames = frames.lock().unwrap();
+        assert_eq!(frames[0], (Direction::Sent, b"\n#6\n<rpc/>\n##\n".to_vec()));
+        let received_data: Vec<u8> = frames[1..]
+            .iter()
+            .inspect(|(direction, _)| assert_eq!(*direction, Direction::Received))
+            .flat_map(|(_, data)| data.clone())
+            .collect();
+        assert_eq!(received_data, received.as_bytes());
+    }
+
+    #[test]
+    fn test_max_outbound_chunk_size() {
+        let mut framer = Framer::new();
+        framer.upgrade();
+        framer.set_max_outbound_chunk_size(Some(4));
+        let mut written = Vec::new();
+        framer.write_xml("<rpc/>", &mut written).unwrap();
+        framer.write_xml("<ok/>", &mut written).unwrap();
+        assert_eq!(
+            String::from_utf8(written.clone()).unwrap(),
+            "\n#4\n<rpc\n#2\n/>\n##\n\n#4\n<ok/\n#1\n>\n##\n"
+        );
+
+        let mut from = Cursor::new(written);
+        assert_eq!(framer.read_xml(&mut from).unwrap(), "<rpc/>");
+        assert_eq!(framer.read_xml(&mut from).unwrap(), "<ok/>");
+    }
+
+    #[test]
+    fn test_frame_written_at_once() {
+        /// Accepts at most `limit` bytes per write, counting calls
+        #[derive(Default)]
+        struct CountingWriter {
+            data: Vec<u8>,
+            writes: usize,
+            limit: usize,
+        }
+
+        impl Write for CountingWriter {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.write_vectored(&[IoSlice::new(buf)])
+            }
+
+            fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+                self.writes += 1;
+                let buf: Vec<u8> = bufs.iter().flat_map(|b| b.to_vec()).collect();
+                let len = buf.len().min(self.limit);
+                self.data.extend_from_slice(&buf[..len]);
+                Ok(len)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut framer = Framer::new();
+        framer.upgrade();
+        for (limit, writes) in [(usize::MAX, 1), (5, 3)] {
+            let mut writer = CountingWriter {
+                limit,
+                ..Default::default()
+            };
+            framer.write_xml("<rpc/>", &mut writer).unwrap();
+            assert_eq!(writer.data, b"\n#6\n<rpc/>\n##\n");
+            assert_eq!(writer.writes, writes);
+        }
+
+        framer.set_autoflush(false);
+        let mut writer = CountingWriter {
+            limit: usize::MAX,
+            ..Default::default()
+        };
+        framer.write_xml("<rpc/>", &mut writer).unwrap();
+        framer.write_xml("<rpc/>", &mut writer).unwrap();
+        assert_eq!(writer.writes, 0);
+        framer.flush(&mut writer).unwrap();
+        assert_eq!(writer.data, b"\n#6\n<rpc/>\n##\n\n#6\n<rpc/>\n##\n");
+        assert_eq!(writer.writes, 1);
+    }
 }
@@ -0,0 +1,275 @@
+//! Sharing one session between threads. Rpcs executed through [`ConnectionHandle`]
+//! are pipelined: the connection thread sends every queued rpc before waiting for
+//! replies, which are dispatched to the callers by message-id.
+use crate::error::{Error, Result};
+use crate::message::{Reply, Rpc};
+use crate::{Connection, PendingRpc};
+use std::collections::VecDeque;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+
+enum Operation {
+    Call(Box<dyn FnOnce(&mut Connection) + Send>),
+    Execute(Box<Rpc>, Sender<Result<Reply>>),
+}
+
+/// Sent rpc whose reply is awaited
+struct Waiting {
+    pending: PendingRpc,
+    rpc: Box<Rpc>,
+    reply: Sender<Result<Reply>>,
+}
+
+/// Clonable handle to a connection owned by a background thread, for sharing one
+/// session between threads without a `Mutex`. Operations are started in the order
+/// they are received. Session is closed when the last handle is dropped.
+#[derive(Clone)]
+pub struct ConnectionHandle {
+    operations: Sender<Operation>,
+}
+
+impl ConnectionHandle {
+    pub fn new(connection: Connection) -> ConnectionHandle {
+        let (operations, received) = channel::<Operation>();
+        thread::spawn(move || drive(connection, received));
+        ConnectionHandle { operations }
+    }
+
+    /// Runs `operation` on the connection thread and returns its result,
+    /// fails with [`Error::SessionClosed`] if the thread has stopped
+    pub fn call<T, F>(&self, operation: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> T + Send + 'static,
+    {
+        let (result, received) = channel();
+        self.operations
+            .send(Operation::Call(Box::new(move |connection| {
+                let _ = result.send(operation(connection));
+            })))
+            .map_err(|_| Error::SessionClosed)?;
+        received.recv().map_err(|_| Error::SessionClosed)
+    }
+
+    /// Executes `rpc` pipelined with rpcs of other threads. Failed rpc is retried as
+    /// by [`Connection::execute`] when a retry policy is set.
+    pub fn execute(&self, rpc: Rpc) -> Result<Reply> {
+        let (reply, received) = channel();
+        self.operations
+            .send(Operation::Execute(Box::new(rpc), reply))
+            .map_err(|_| Error::SessionClosed)?;
+        received.recv().map_err(|_| Error::SessionClosed)?
+    }
+}
+
+/// Runs operations until all handles are dropped. Queued rpcs are sent before a reply
+/// is awaited, replies are awaited in the order the rpcs were sent, since servers
+/// reply in that order. Reading a reply blocks, so rpcs queued meanwhile are sent
+/// after it.
+fn drive(mut connection: Connection, operations: Receiver<Operation>) {
+    let mut waiting: VecDeque<Waiting> = VecDeque::new();
+    loop {
+        let operation = if waiting.is_empty() {
+            match operations.recv() {
+                Ok(operation) => Some(operation),
+                Err(_) => break,
+            }
+        } else {
+            operations.try_recv().ok()
+        };
+        match operation {
+            Some(Operation::Call(operation)) => operation(&mut connection),
+            Some(Operation::Execute(rpc, reply)) => match connection.send(&rpc) {
+                Ok(pending) => waiting.push_back(Waiting {
+                    pending,
+                    rpc,
+                    reply,
+                }),
+                Err(err) => {
+                    let _ = reply.send(Err(err));
+                }
+            },
+            None => {
+                if let Some(waiting) = waiting.pop_front() {
+                    let result = receive(&mut connection, waiting.pending, waiting.rpc);
+                    let _ = waiting.reply.send(result);
+                }
+            }
+        }
+    }
+    let session_id = connection.session_id();
+    if let Err(err) = connection.shutdown() {
+        warn!("Closing session {} failed: {}", session_id, err);
+    }
+}
+
+/// Waits reply of pending rpc, retrying it with the retry policy of the connection
+fn receive(connection: &mut Connection, pending: PendingRpc, rpc: Box<Rpc>) -> Result<Reply> {
+    // Replies of rpcs sent after a broken session are never received
+    if connection.broken {
+        connection.forget(pending.message_id());
+        return Err(Error::SessionClosed);
+    }
+    let result = connection.wait_reply(pending);
+    connection.retry_rpc(*rpc, result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{Datastore, RpcContent, Source};
+    use crate::metrics;
+    use crate::retry::RetryPolicy;
+    use crate::transport::mock::MockTransport;
+    use crate::transport::{MessageLimits, Transport};
+    use pretty_assertions::assert_eq;
+    use std::io::Read;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    fn assert_shareable<T: Clone + Send + Sync>() {}
+
+    #[test]
+    fn test_shared_handle() {
+        assert_shareable::<ConnectionHandle>();
+        let transport =
+            MockTransport::new(|rpc| rpc.contains("<get/>").then(|| "<data/>".to_string()));
+        let handle = ConnectionHandle::new(Connection::new(transport).unwrap());
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let handle = handle.clone();
+                thread::spawn(move || {
+                    handle
+                        .execute(Rpc::new(RpcContent::Get {
+                            filter: None,
+                            with_defaults: None,
+                        }))
+                        .map(|reply| reply.raw().contains("<data/>"))
+                })
+            })
+            .collect();
+        for thread in threads {
+            assert!(thread.join().unwrap().unwrap());
+        }
+        assert_eq!(
+            handle.call(|connection| connection.session_id()).unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_retried_rpc() {
+        let error = "<rpc-error><error-type>protocol</error-type><error-tag>in-use</error-tag><error-severity>error</error-severity></rpc-error>";
+        let transport = MockTransport::new(move |_| Some(error.to_string()));
+        let sent = transport.sent();
+        let mut connection = Connection::new(transport).unwrap();
+        connection.set_retry_policy(RetryPolicy::new(2).delay(Duration::from_millis(1)));
+        let handle = ConnectionHandle::new(connection);
+        let result = handle.execute(Rpc::new(RpcContent::Get {
+            filter: None,
+            with_defaults: None,
+        }));
+        assert!(matches!(result, Err(Error::Netconf(_))));
+        let sent = sent.lock().unwrap();
+        let gets: Vec<_> = sent.iter().filter(|rpc| rpc.contains("<get/>")).collect();
+        assert_eq!(gets.len(), 2);
+        assert_ne!(gets[0], gets[1], "retry has new message-id");
+    }
+
+    /// Records sends and receives of the wrapped transport
+    struct Recording {
+        inner: MockTransport,
+        events: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Transport for Recording {
+        fn send(&mut self, message: &str) -> Result<()> {
+            let name = metrics::operation_name(message);
+            self.events.lock().unwrap().push(format!("send {}", name));
+            self.inner.send(message)
+        }
+
+        fn receive(&mut self) -> Result<String> {
+            self.events.lock().unwrap().push("receive".to_string());
+            self.inner.receive()
+        }
+
+        fn receive_stream(&mut self) -> Result<Box<dyn Read + '_>> {
+            self.inner.receive_stream()
+        }
+
+        fn timeout(&self) -> Option<Duration> {
+            self.inner.timeout()
+        }
+
+        fn set_timeout(&mut self, timeout: Option<Duration>) {
+            self.inner.set_timeout(timeout)
+        }
+
+        fn set_message_limits(&mut self, limits: MessageLimits) {
+            self.inner.set_message_limits(limits)
+        }
+
+        fn close(&mut self) -> Result<()> {
+            self.inner.close()
+        }
+
+        fn upgrade(&mut self) {}
+    }
+
+    #[test]
+    fn test_pipelined_rpcs() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let transport = Recording {
+            inner: MockTransport::scripted([
+                ("<get/>", "<data/>"),
+                ("<get-config>", "<data/>"),
+                ("<close-session/>", "<ok/>"),
+            ]),
+            events: Arc::clone(&events),
+        };
+        let connection = Connection::new(transport).unwrap();
+        events.lock().unwrap().clear();
+
+        // Both rpcs are queued before the connection thread starts
+        let (operations, received) = channel();
+        let (get, get_reply) = channel();
+        let (get_config, get_config_reply) = channel();
+        let get_rpc = Rpc::new(RpcContent::Get {
+            filter: None,
+            with_defaults: None,
+        });
+        let get_config_rpc = Rpc::new(RpcContent::GetConfig {
+            source: Source {
+                datastore: Datastore::Running,
+            },
+            filter: None,
+            with_defaults: None,
+        });
+        let message_ids = (
+            get_rpc.message_id().to_string(),
+            get_config_rpc.message_id().to_string(),
+        );
+        operations
+            .send(Operation::Execute(Box::new(get_rpc), get))
+            .unwrap();
+        operations
+            .send(Operation::Execute(Box::new(get_config_rpc), get_config))
+            .unwrap();
+        drop(operations);
+        drive(connection, received);
+
+        assert_eq!(
+            get_reply.recv().unwrap().unwrap().message_id(),
+            message_ids.0
+        );
+        assert_eq!(
+            get_config_reply.recv().unwrap().unwrap().message_id(),
+            message_ids.1
+        );
+        assert_eq!(
+            events.lock().unwrap()[..4],
+            ["send get", "send get-config", "receive", "receive"]
+        );
+    }
+}
@@ -0,0 +1,14 @@
+/// Hook registered on [`Connection`](crate::Connection) observing and modifying
+/// messages exchanged with the server, e.g. for auditing or redacting secrets.
+///
+/// Interceptors are called in the order they were added. Message-id of an rpc
+/// must not be changed, since replies are matched to requests by it.
+pub trait RpcInterceptor: Send {
+    /// Called with serialized rpc before it is sent
+    fn on_send(&mut self, _rpc: &mut String) {}
+
+    /// Called with every received reply and notification before it is parsed.
+    /// Streamed replies, e.g. of [`Connection::get_config_stream`](crate::Connection::get_config_stream),
+    /// are held in memory and passed after they have been read, so changes to them have no effect.
+    fn on_receive(&mut self, _message: &mut String) {}
+}
@@ -0,0 +1,184 @@
+use crate::error::Result;
+use quick_xml::errors::IllFormedError;
+use quick_xml::events::Event;
+use quick_xml::name::ResolveResult;
+use quick_xml::NsReader;
+use serde_json::{json, Map, Value};
+
+const YANG_NAMESPACE_PREFIX: &str = "urn:ietf:params:xml:ns:yang:";
+
+struct Element {
+    name: String,
+    prefix: Option<String>,
+    namespace: Option<String>,
+    text: String,
+    children: Vec<Element>,
+}
+
+/// Converts contents of the root element, e.g. `<data>` of rpc-reply, to RFC 7951 style JSON.
+/// Repeated siblings become arrays, empty elements `[null]` and, without the schema,
+/// all leaf values are strings. Member names are prefixed with the module name when
+/// namespace changes and the module can be resolved from the namespace or XML prefix.
+pub fn to_json(xml: &str) -> Result<Value> {
+    let mut reader = NsReader::from_str(xml);
+    let mut stack: Vec<Element> = Vec::new();
+    loop {
+        let (namespace, event) = reader.read_resolved_event()?;
+        let namespace = match namespace {
+            ResolveResult::Bound(ns) => Some(String::from_utf8_lossy(ns.as_ref()).into_owned()),
+            _ => None,
+        };
+        match event {
+            Event::Start(ref start) | Event::Empty(ref start) => {
+                let is_empty = matches!(event, Event::Empty(_));
+                let name = start.name();
+                let element = Element {
+                    name: String::from_utf8_lossy(name.local_name().as_ref()).into_owned(),
+                    prefix: name
+                        .prefix()
+                        .map(|prefix| String::from_utf8_lossy(prefix.as_ref()).into_owned()),
+                    namespace,
+                    text: String::new(),
+                    children: Vec::new(),
+                };
+                if is_empty {
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(element),
+                        None => return Ok(element_to_json(&element)),
+                    }
+                } else {
+                    stack.push(element);
+                }
+            }
+            Event::Text(text) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&text.unescape()?);
+                }
+            }
+            Event::CData(text) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&String::from_utf8_lossy(&text));
+                }
+            }
+            Event::End(end) => {
+                let Some(element) = stack.pop() else {
+                    let name = String::from_utf8_lossy(end.name().as_ref()).into_owned();
+                    return Err(
+                        quick_xml::Error::IllFormed(IllFormedError::UnmatchedEndTag(name)).into(),
+                    );
+                };
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok(element_to_json(&element)),
+                }
+            }
+            Event::Eof => match stack.pop() {
+                Some(element) => {
+                    return Err(quick_xml::Error::IllFormed(IllFormedError::MissingEndTag(
+                        element.name,
+                    ))
+                    .into())
+                }
+                None => return Ok(Value::Null),
+            },
+            _ => {}
+        }
+    }
+}
+
+fn element_to_json(element: &Element) -> Value {
+    if element.children.is_empty() {
+        return match element.text.trim() {
+            "" => json!([null]),
+            text => Value::String(text.to_string()),
+        };
+    }
+    let mut groups: Vec<(String, Vec<Value>)> = Vec::new();
+    for child in element.children.iter() {
+        let name = member_name(child, element.namespace.as_deref());
+        let value = element_to_json(child);
+        match groups.iter_mut().find(|(existing, _)| *existing == name) {
+            Some((_, values)) => values.push(value),
+            None => groups.push((name, vec![value])),
+        }
+    }
+    let members = groups
+        .into_iter()
+        .map(|(name, mut values)| match values.len() {
+            1 => (name, values.remove(0)),
+            _ => (name, Value::Array(values)),
+        })
+        .collect::<Map<String, Value>>();
+    Value::Object(members)
+}
+
+/// Name qualified with module name when namespace differs from the parent
+fn member_name(element: &Element, parent_namespace: Option<&str>) -> String {
+    let Some(namespace) = element.namespace.as_deref() else {
+        return element.name.clone();
+    };
+    if Some(namespace) == parent_namespace {
+        return element.name.clone();
+    }
+    let module = namespace
+        .strip_prefix(YANG_NAMESPACE_PREFIX)
+        .or(element.prefix.as_deref());
+    match module {
+        Some(module) => format!("{}:{}", module, element.name),
+        None => element.name.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    #[test]
+    fn test_to_json() {
+        let xml = r#"<rpc-reply xmlns="urn:ietf:params:xml:ns:netconf:base:1.0" message-id="1">
+  <data>
+    <interfaces xmlns="urn:ietf:params:xml:ns:yang:ietf-interfaces">
+      <interface>
+        <name>eth0</name>
+        <enabled>true</enabled>
+      </interface>
+      <interface>
+        <name>eth1</name>
+        <description>uplink &amp; backup</description>
+      </interface>
+    </interfaces>
+    <system xmlns:ex="http://example.com/system" xmlns="http://example.com/system">
+      <ex:hostname>router</ex:hostname>
+    </system>
+  </data>
+</rpc-reply>"#;
+        let expected = json!({
+            "data": {
+                "ietf-interfaces:interfaces": {
+                    "interface": [
+                        { "name": "eth0", "enabled": "true" },
+                        { "name": "eth1", "description": "uplink & backup" }
+                    ]
+                },
+                "system": { "hostname": "router" }
+            }
+        });
+        assert_eq!(to_json(xml).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_empty_elements() {
+        let xml = r#"<rpc-reply xmlns="urn:ietf:params:xml:ns:netconf:base:1.0" message-id="1"><ok/></rpc-reply>"#;
+        assert_eq!(to_json(xml).unwrap(), json!({ "ok": [null] }));
+    }
+
+    #[test]
+    fn test_unbalanced() {
+        assert!(matches!(to_json("</data>"), Err(Error::Xml(_))));
+        assert!(matches!(to_json("<data><a>1</a>"), Err(Error::Xml(_))));
+        assert_eq!(to_json("").unwrap(), Value::Null);
+    }
+}
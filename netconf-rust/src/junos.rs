@@ -0,0 +1,285 @@
+//! Juniper Junos specific rpcs, enabled with the `junos` feature
+use crate::error::Result;
+use crate::message::{RawXml, Reply, Rpc, RpcContent};
+use crate::Connection;
+use quick_xml::escape::escape;
+
+/// Format of configuration loaded with [`Connection::load_configuration`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadFormat {
+    Xml,
+    /// Curly brace text format
+    Text,
+    /// `set` and `delete` commands, always loaded with action `set`
+    Set,
+}
+
+/// How loaded configuration is combined with the candidate
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LoadAction {
+    #[default]
+    Merge,
+    Override,
+    Replace,
+    Update,
+}
+
+impl LoadAction {
+    fn as_str(self) -> &'static str {
+        match self {
+            LoadAction::Merge => "merge",
+            LoadAction::Override => "override",
+            LoadAction::Replace => "replace",
+            LoadAction::Update => "update",
+        }
+    }
+}
+
+/// Options of `<commit-configuration>`
+#[derive(Debug, Default)]
+pub struct CommitConfiguration {
+    check: bool,
+    synchronize: bool,
+    log: Option<String>,
+}
+
+impl CommitConfiguration {
+    /// Only checks candidate syntax and semantics without committing
+    pub fn check(mut self) -> CommitConfiguration {
+        self.check = true;
+        self
+    }
+
+    /// Commits on both routing engines
+    pub fn synchronize(mut self) -> CommitConfiguration {
+        self.synchronize = true;
+        self
+    }
+
+    /// Comment recorded in commit history
+    pub fn log(mut self, comment: &str) -> CommitConfiguration {
+        self.log = Some(comment.to_string());
+        self
+    }
+
+    fn to_xml(&self) -> String {
+        let mut xml = String::from("<commit-configuration>");
+        if self.check {
+            xml.push_str("<check/>");
+        }
+        if self.synchronize {
+            xml.push_str("<synchronize/>");
+        }
+        if let Some(log) = &self.log {
+            xml.push_str(&format!("<log>{}</log>", escape(log)));
+        }
+        xml.push_str("</commit-configuration>");
+        xml
+    }
+}
+
+/// Format of configuration returned by `<get-configuration>`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConfigurationFormat {
+    #[default]
+    Xml,
+    Text,
+    Set,
+    Json,
+}
+
+/// Options of `<get-configuration>`
+#[derive(Debug, Default)]
+pub struct GetConfiguration {
+    committed: bool,
+    format: ConfigurationFormat,
+    inherit: Option<Inherit>,
+    groups: bool,
+    filter: Option<String>,
+}
+
+/// Display of values inherited from configuration groups
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Inherit {
+    /// Inherited values shown in place of `apply-groups`
+    Inherit,
+    /// Like `Inherit`, also including default values
+    Defaults,
+}
+
+impl GetConfiguration {
+    /// Reads committed configuration instead of the candidate
+    pub fn committed(mut self) -> GetConfiguration {
+        self.committed = true;
+        self
+    }
+
+    pub fn format(mut self, format: ConfigurationFormat) -> GetConfiguration {
+        self.format = format;
+        self
+    }
+
+    pub fn inherit(mut self, inherit: Inherit) -> GetConfiguration {
+        self.inherit = Some(inherit);
+        self
+    }
+
+    /// Marks values inherited from groups with `junos:group` attribute, requires `inherit`
+    pub fn groups(mut self) -> GetConfiguration {
+        self.groups = true;
+        self
+    }
+
+    /// Returns only hierarchy given as `<configuration>` subtree, e.g.
+    /// `<configuration><system/></configuration>`
+    pub fn filter(mut self, configuration: &str) -> GetConfiguration {
+        self.filter = Some(configuration.to_string());
+        self
+    }
+
+    fn to_xml(&self) -> String {
+        let mut attributes = String::new();
+        if self.committed {
+            attributes.push_str(r#" database="committed""#);
+        }
+        let format = match self.format {
+            ConfigurationFormat::Xml => None,
+            ConfigurationFormat::Text => Some("text"),
+            ConfigurationFormat::Set => Some("set"),
+            ConfigurationFormat::Json => Some("json"),
+        };
+        if let Some(format) = format {
+            attributes.push_str(&format!(r#" format="{}""#, format));
+        }
+        match self.inherit {
+            Some(Inherit::Inherit) => attributes.push_str(r#" inherit="inherit""#),
+            Some(Inherit::Defaults) => attributes.push_str(r#" inherit="defaults""#),
+            None => {}
+        }
+        if self.groups {
+            attributes.push_str(r#" groups="groups""#);
+        }
+        match &self.filter {
+            Some(filter) => format!(
+                "<get-configuration{}>{}</get-configuration>",
+                attributes, filter
+            ),
+            None => format!("<get-configuration{}/>", attributes),
+        }
+    }
+}
+
+fn load_configuration_xml(format: LoadFormat, action: LoadAction, config: &str) -> String {
+    match format {
+        LoadFormat::Xml => format!(
+            r#"<load-configuration action="{}" format="xml">{}</load-configuration>"#,
+            action.as_str(),
+            config
+        ),
+        LoadFormat::Text => format!(
+            r#"<load-configuration action="{}" format="text"><configuration-text>{}</configuration-text></load-configuration>"#,
+            action.as_str(),
+            escape(config)
+        ),
+        LoadFormat::Set => format!(
+            r#"<load-configuration action="set" format="text"><configuration-set>{}</configuration-set></load-configuration>"#,
+            escape(config)
+        ),
+    }
+}
+
+impl Connection {
+    /// Loads `config` into the candidate. XML configuration is the `<configuration>`
+    /// element, text and set formats are sent as they would be typed in the CLI.
+    pub fn load_configuration(
+        &mut self,
+        format: LoadFormat,
+        action: LoadAction,
+        config: &str,
+    ) -> Result<()> {
+        let load = load_configuration_xml(format, action, config);
+        self.execute(Rpc::new(RpcContent::Raw(RawXml(load))))?;
+        Ok(())
+    }
+
+    /// Commits the candidate, reply contains `<commit-results>`
+    pub fn commit_configuration(&mut self, options: CommitConfiguration) -> Result<Reply> {
+        self.execute(Rpc::new(RpcContent::Raw(RawXml(options.to_xml()))))
+    }
+
+    pub fn get_configuration(&mut self, options: GetConfiguration) -> Result<Reply> {
+        self.execute(Rpc::new(RpcContent::Raw(RawXml(options.to_xml()))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::mock::MockTransport;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_load_configuration() {
+        assert_eq!(
+            load_configuration_xml(
+                LoadFormat::Text,
+                LoadAction::Replace,
+                "system { host-name \"r1\"; }"
+            ),
+            r#"<load-configuration action="replace" format="text"><configuration-text>system { host-name &quot;r1&quot;; }</configuration-text></load-configuration>"#
+        );
+        assert_eq!(
+            load_configuration_xml(
+                LoadFormat::Set,
+                LoadAction::Merge,
+                "set system host-name r1"
+            ),
+            r#"<load-configuration action="set" format="text"><configuration-set>set system host-name r1</configuration-set></load-configuration>"#
+        );
+    }
+
+    #[test]
+    fn test_options() {
+        assert_eq!(
+            CommitConfiguration::default().check().synchronize().log("a & b").to_xml(),
+            "<commit-configuration><check/><synchronize/><log>a &amp; b</log></commit-configuration>"
+        );
+        assert_eq!(
+            GetConfiguration::default()
+                .committed()
+                .format(ConfigurationFormat::Text)
+                .inherit(Inherit::Inherit)
+                .groups()
+                .to_xml(),
+            r#"<get-configuration database="committed" format="text" inherit="inherit" groups="groups"/>"#
+        );
+    }
+
+    #[test]
+    fn test_rpcs() {
+        let transport = MockTransport::scripted([
+            (
+                r#"<load-configuration action="merge" format="xml"><configuration>"#,
+                "<load-configuration-results><ok/></load-configuration-results>",
+            ),
+            (
+                "<get-configuration><configuration><system/></configuration></get-configuration>",
+                "<configuration><system/></configuration>",
+            ),
+        ]);
+        let mut connection = Connection::new(transport).unwrap();
+        connection
+            .load_configuration(
+                LoadFormat::Xml,
+                LoadAction::Merge,
+                "<configuration><system><host-name>r1</host-name></system></configuration>",
+            )
+            .unwrap();
+        let reply = connection
+            .get_configuration(
+                GetConfiguration::default().filter("<configuration><system/></configuration>"),
+            )
+            .unwrap();
+        assert!(reply.raw().contains("<system/>"));
+    }
+}
@@ -1,19 +1,124 @@
+//! NETCONF client with a blocking API: [`Connection`] methods return when the reply
+//! is received, so no async runtime is needed. The `russh` transport runs its own
+//! runtime internally.
+use cancel::CancellationToken;
 use error::{Error, Result};
+use interceptor::RpcInterceptor;
 use message::*;
+use metrics::{MetricsObserver, Outcome, RpcMetrics};
+use profile::{DeviceProfile, SaveConfig};
 use quick_xml::de::from_str;
+use retry::RetryPolicy;
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::ops::{Deref, DerefMut};
 use std::str::FromStr;
-use transport::Transport;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use stream::DataStream;
+use subscription::{EstablishSubscription, EstablishSubscriptionReply, ModifySubscription};
+use time::OffsetDateTime;
+use transport::{MessageLimits, Transport};
+use wire::WireObserver;
 
+#[macro_use]
+mod macros;
+
+pub mod audit;
+pub mod cancel;
+pub mod coordinator;
 pub mod error;
+pub mod events;
 mod framer;
+pub mod handle;
+pub mod interceptor;
+#[cfg(feature = "json")]
+mod json;
+#[cfg(feature = "junos")]
+pub mod junos;
 pub mod message;
+pub mod metrics;
+pub mod monitoring;
+#[cfg(feature = "yang")]
+pub mod names;
+pub mod nmda;
+pub mod ping;
+pub mod pool;
+pub mod profile;
+pub mod retry;
+pub mod schema;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod stream;
+pub mod subscription;
 pub mod transport;
+pub mod wire;
+
+#[cfg(feature = "codec")]
+pub use framer::codec::{NetconfBytesCodec, NetconfCodec};
+
+const BASE_1_1_CAPABILITY: &str = "urn:ietf:params:netconf:base:1.1";
+const INTERLEAVE_CAPABILITY: &str = "urn:ietf:params:netconf:capability:interleave:1.0";
+const CAPABILITY_PREFIX: &str = "urn:ietf:params:netconf:capability:";
+const WITH_DEFAULTS_CAPABILITY: &str = "urn:ietf:params:netconf:capability:with-defaults:1.0";
+const URL_CAPABILITY: &str = "urn:ietf:params:netconf:capability:url:1.0";
+const TIME_CAPABILITY: &str = "urn:ietf:params:netconf:capability:time:1.0";
+
+/// Time [`Connection::new`] waits for server hello
+pub const DEFAULT_HELLO_TIMEOUT: Duration = Duration::from_secs(30);
 
 pub struct Connection {
     pub(crate) transport: Box<dyn Transport + Send + 'static>,
 
     session_id: Option<u64>,
-    skip_errors: bool,
+    capabilities: Vec<String>,
+    subscribed: bool,
+    fail_on_warnings: bool,
+    indent: Indent,
+    /// Message-ids of sent rpcs without reply, oldest first
+    in_flight: VecDeque<String>,
+    received: HashMap<String, String>,
+    /// Errors of rpcs whose reply was skipped by the transport
+    lost: HashMap<String, Error>,
+    notifications: VecDeque<String>,
+    interceptors: Vec<Box<dyn RpcInterceptor>>,
+    last_activity: Instant,
+    broken: bool,
+    closed: bool,
+    profile: Option<Arc<dyn DeviceProfile>>,
+    metrics: Option<Box<dyn MetricsObserver>>,
+    retry: Option<RetryPolicy>,
+    #[cfg(feature = "yang")]
+    name_checker: Option<Arc<names::NameChecker>>,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+/// Rpc sent with [`Connection::send_rpc`] whose reply has not been consumed yet
+#[derive(Debug)]
+pub struct PendingRpc {
+    message_id: String,
+    sent: Instant,
+    operation: String,
+    bytes_sent: usize,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+impl PendingRpc {
+    pub fn message_id(&self) -> &str {
+        &self.message_id
+    }
+}
+
+/// Error handling of [`Connection::run_batch`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchMode {
+    /// Rpcs are sent one at a time and the rest are skipped after a failure
+    StopOnError,
+    /// All rpcs are pipelined and every one gets a result
+    ContinueOnError,
 }
 
 impl Connection {
@@ -21,64 +126,2181 @@ impl Connection {
     where
         T: Transport + 'static,
     {
+        Connection::open(Box::from(transport), Hello::new(), DEFAULT_HELLO_TIMEOUT)
+    }
+
+    /// Like [`Connection::new`], but fails with [`Error::HelloTimeout`] if server
+    /// does not send its hello within `timeout`
+    pub fn new_with_hello_timeout<T>(transport: T, timeout: Duration) -> Result<Connection>
+    where
+        T: Transport + 'static,
+    {
+        Connection::open(Box::from(transport), Hello::new(), timeout)
+    }
+
+    /// Like [`Connection::new`], but advertises only base:1.0 capability so that
+    /// end-of-message framing is used even when the server supports base:1.1.
+    /// Workaround for servers with broken chunked framing.
+    pub fn new_base_1_0<T>(transport: T) -> Result<Connection>
+    where
+        T: Transport + 'static,
+    {
+        Connection::open(
+            Box::from(transport),
+            Hello::base_1_0(),
+            DEFAULT_HELLO_TIMEOUT,
+        )
+    }
+
+    /// Like [`Connection::new`], with quirks of `profile` applied instead of the profile
+    /// detected from server capabilities
+    pub fn new_with_profile<T>(transport: T, profile: Arc<dyn DeviceProfile>) -> Result<Connection>
+    where
+        T: Transport + 'static,
+    {
+        Connection::new_with_profile_and_hello_timeout(transport, profile, DEFAULT_HELLO_TIMEOUT)
+    }
+
+    /// Like [`Connection::new_with_profile`], but fails with [`Error::HelloTimeout`]
+    /// if server does not send its hello within `timeout`
+    pub fn new_with_profile_and_hello_timeout<T>(
+        transport: T,
+        profile: Arc<dyn DeviceProfile>,
+        timeout: Duration,
+    ) -> Result<Connection>
+    where
+        T: Transport + 'static,
+    {
+        let hello = if profile.base_1_0_only() {
+            Hello::base_1_0()
+        } else {
+            Hello::new()
+        };
+        let mut conn = Connection::connect(Box::from(transport), hello, timeout)?;
+        conn.set_profile(profile);
+        Ok(conn)
+    }
+
+    fn open(
+        transport: Box<dyn Transport + Send + 'static>,
+        hello: Hello,
+        hello_timeout: Duration,
+    ) -> Result<Connection> {
+        let mut conn = Connection::connect(transport, hello, hello_timeout)?;
+        if let Some(profile) = profile::detect(&conn.capabilities) {
+            debug!("Using {} device profile", profile.name());
+            conn.set_profile(profile);
+        }
+        Ok(conn)
+    }
+
+    fn connect(
+        transport: Box<dyn Transport + Send + 'static>,
+        hello: Hello,
+        hello_timeout: Duration,
+    ) -> Result<Connection> {
         let mut conn = Connection {
-            transport: Box::from(transport),
+            transport,
             session_id: None,
-            skip_errors: false,
+            capabilities: Vec::new(),
+            subscribed: false,
+            fail_on_warnings: false,
+            indent: Indent::default(),
+            in_flight: VecDeque::new(),
+            received: HashMap::new(),
+            lost: HashMap::new(),
+            notifications: VecDeque::new(),
+            interceptors: Vec::new(),
+            last_activity: Instant::now(),
+            broken: false,
+            closed: false,
+            profile: None,
+            metrics: None,
+            retry: None,
+            #[cfg(feature = "yang")]
+            name_checker: None,
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!(
+                "netconf",
+                host = tracing::field::Empty,
+                session_id = tracing::field::Empty
+            ),
         };
-        conn.session_id = conn.hello()?;
+        #[cfg(feature = "tracing")]
+        let _entered = conn.span.clone().entered();
+        conn.session_id = conn.hello(hello, hello_timeout)?;
+        #[cfg(feature = "tracing")]
+        conn.span.record("session_id", conn.session_id());
         Ok(conn)
     }
 
-    pub fn set_skip_errors(&mut self) {
-        self.skip_errors = true
+    fn set_profile(&mut self, profile: Arc<dyn DeviceProfile>) {
+        profile.fix_capabilities(&mut self.capabilities);
+        self.profile = Some(profile);
+    }
+
+    /// Device profile whose quirks are applied, if any
+    pub fn profile(&self) -> Option<&dyn DeviceProfile> {
+        self.profile.as_deref()
+    }
+
+    /// Treat `<rpc-error>` elements with severity `warning` as failures.
+    /// By default warnings are only logged and the reply is returned to the caller.
+    pub fn set_fail_on_warnings(&mut self) {
+        self.fail_on_warnings = true
+    }
+
+    /// Sets indentation of sent rpcs, [`Indent::Compact`] produces the smallest payloads.
+    /// By default rpcs are indented with two spaces.
+    pub fn set_rpc_indent(&mut self, indent: Indent) {
+        self.indent = indent;
+    }
+
+    /// Adds interceptor called for every rpc sent and message received after hello
+    pub fn add_interceptor<I>(&mut self, interceptor: I)
+    where
+        I: RpcInterceptor + 'static,
+    {
+        self.interceptors.push(Box::new(interceptor));
+    }
+
+    /// Appends every rpc sent and message received after this call to `path` with
+    /// timestamps and `host`, see [`audit::AuditLog`]
+    pub fn set_audit_log(&mut self, path: impl AsRef<std::path::Path>, host: &str) -> Result<()> {
+        self.add_interceptor(audit::AuditLog::open(path, host)?);
+        Ok(())
+    }
+
+    /// Sets maximum size of received messages and chunks, exceeding the limit fails
+    /// the rpc instead of buffering an arbitrarily large reply. By default there are no limits.
+    pub fn set_message_limits(&mut self, limits: MessageLimits) {
+        self.transport.set_message_limits(limits)
+    }
+
+    /// Registers observer receiving all raw data sent and received on the session,
+    /// see [`wire::WireLog`] for writing transcript to a file
+    pub fn set_wire_observer(&mut self, observer: impl WireObserver + 'static) {
+        self.transport.set_wire_observer(Box::new(observer))
+    }
+
+    /// Span of the session with `host` and `session_id` fields, parent of the `rpc`
+    /// spans with `operation` and `message_id` fields. Events of the crate are emitted
+    /// within these spans.
+    #[cfg(feature = "tracing")]
+    pub fn span(&self) -> &tracing::Span {
+        &self.span
+    }
+
+    /// Records `host` field of the session span, set by [`pool::ConnectionPool`] for its sessions
+    #[cfg(feature = "tracing")]
+    pub fn set_host(&self, host: &str) {
+        self.span.record("host", host);
+    }
+
+    /// Registers observer called with measurements of every rpc, e.g. for exporting metrics
+    pub fn set_metrics_observer(&mut self, observer: impl MetricsObserver + 'static) {
+        self.metrics = Some(Box::new(observer));
+    }
+
+    /// Retries rpcs failing with errors classified retryable by `policy`, e.g. `lock-denied`.
+    /// Retried rpc is sent with new message-id. Rpcs sent with [`Connection::send_rpc`]
+    /// are not retried.
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.retry = Some(policy);
+    }
+
+    /// Enables recovery from malformed chunks: rest of the broken message is skipped and
+    /// receiving fails with [`Error::FrameResynchronized`] without breaking the session.
+    pub fn set_frame_recovery(&mut self, recovery: bool) {
+        self.transport.set_frame_recovery(recovery)
+    }
+
+    /// Replaces invalid UTF-8 in received messages with U+FFFD instead of failing
+    /// with [`Error::InvalidUtf8`], for devices sending e.g. Latin-1 descriptions.
+    /// Either way the session stays usable, since the message was framed correctly.
+    pub fn set_lossy_utf8(&mut self, lossy: bool) {
+        self.transport.set_lossy_utf8(lossy)
+    }
+
+    /// Splits rpcs to chunks of at most `size` bytes after base 1.1 framing is negotiated,
+    /// for servers limiting chunk size
+    pub fn set_max_outbound_chunk_size(&mut self, size: Option<usize>) {
+        self.transport.set_max_outbound_chunk_size(size)
+    }
+
+    /// When off, rpcs sent with [`Connection::send_rpc`] are buffered and written together
+    /// by [`Connection::flush`] or when waiting for a reply
+    pub fn set_autoflush(&mut self, autoflush: bool) {
+        self.transport.set_autoflush(autoflush)
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.transport.flush().map_err(|err| {
+            self.broken = true;
+            session_error(err)
+        })
+    }
+
+    /// Checks element names of [`Connection::edit_config`] payloads with `checker` before
+    /// sending them. Checker can be shared by connections to devices with the same modules.
+    #[cfg(feature = "yang")]
+    pub fn set_name_checker(&mut self, checker: Arc<names::NameChecker>) {
+        self.name_checker = Some(checker);
+    }
+
+    /// Sets how long rpcs wait for reply before failing with [`Error::Timeout`],
+    /// `None` waits forever. Session should be closed after a timeout, since
+    /// the late reply would be left unread on the transport.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.transport.set_timeout(timeout)
+    }
+
+    /// Overrides the connection timeout for rpcs executed through returned handle,
+    /// e.g. `connection.with_timeout(Duration::from_secs(120)).get_config("running")`
+    pub fn with_timeout(&mut self, timeout: Duration) -> WithTimeout<'_> {
+        let previous = self.transport.timeout();
+        self.transport.set_timeout(Some(timeout));
+        WithTimeout {
+            connection: self,
+            previous,
+        }
+    }
+
+    /// Returns true if reading or writing the transport has failed, e.g. timed out,
+    /// after which the session is no longer usable
+    pub fn is_broken(&self) -> bool {
+        self.broken
+    }
+
+    /// Session can be handed to another user, it is open, not subscribed
+    /// and has no rpcs waiting for replies
+    pub(crate) fn is_reusable(&self) -> bool {
+        !self.broken
+            && !self.closed
+            && !self.subscribed
+            && self.in_flight.is_empty()
+            && self.received.is_empty()
+            && self.lost.is_empty()
+    }
+
+    /// Time since last message was sent or received, a streamed reply counts as
+    /// received when it starts
+    pub fn idle_time(&self) -> Duration {
+        self.last_activity.elapsed()
     }
 
     pub fn session_id(&self) -> u64 {
         self.session_id.unwrap_or(0)
     }
 
-    fn hello(&mut self) -> Result<Option<u64>> {
-        let hello = Hello::new();
-        let response = self.transport.execute_rpc(&hello.to_string())?;
-        log::trace!("Hello:\n{}", response);
+    /// Capabilities advertised by the server in its hello
+    pub fn capabilities(&self) -> &[String] {
+        &self.capabilities
+    }
+
+    /// Returns true if server advertised `capability`, ignoring capability parameters
+    pub fn has_capability(&self, capability: &str) -> bool {
+        self.capabilities
+            .iter()
+            .any(|c| c.split('?').next() == Some(capability))
+    }
+
+    /// Returns true if server advertised `name` capability of any version, e.g. `candidate`
+    fn has_capability_named(&self, name: &str) -> bool {
+        self.capabilities.iter().any(|c| {
+            c.strip_prefix(CAPABILITY_PREFIX)
+                .and_then(|c| c.split('?').next())
+                .and_then(|c| c.rsplit_once(':'))
+                .is_some_and(|(capability, _)| capability == name)
+        })
+    }
+
+    /// Value of parameter `name` of advertised `capability`, e.g. `scheme` of `:url`
+    fn capability_parameter(&self, capability: &str, name: &str) -> Option<&str> {
+        self.capabilities
+            .iter()
+            .filter_map(|c| c.split_once('?'))
+            .filter(|(c, _)| *c == capability)
+            .flat_map(|(_, parameters)| parameters.split('&'))
+            .filter_map(|parameter| parameter.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    }
+
+    /// `:candidate` datastore is supported
+    pub fn supports_candidate(&self) -> bool {
+        self.has_capability_named("candidate")
+    }
+
+    /// `:writable-running`, edit-config can target running datastore
+    pub fn supports_writable_running(&self) -> bool {
+        self.has_capability_named("writable-running")
+    }
+
+    /// `:startup` datastore is supported
+    pub fn supports_startup(&self) -> bool {
+        self.has_capability_named("startup")
+    }
+
+    /// `:confirmed-commit`, see [`Connection::confirmed_commit`]
+    pub fn supports_confirmed_commit(&self) -> bool {
+        self.has_capability_named("confirmed-commit")
+    }
+
+    /// `:rollback-on-error` error-option of edit-config
+    pub fn supports_rollback_on_error(&self) -> bool {
+        self.has_capability_named("rollback-on-error")
+    }
+
+    /// `:validate` operation
+    pub fn supports_validate(&self) -> bool {
+        self.has_capability_named("validate")
+    }
+
+    /// `:xpath` filters, see [`Filter::xpath`]
+    pub fn supports_xpath(&self) -> bool {
+        self.has_capability_named("xpath")
+    }
+
+    /// `:url` as source or target of operations
+    pub fn supports_url(&self) -> bool {
+        self.has_capability_named("url")
+    }
+
+    /// `:notification`, see [`Connection::create_subscription`]
+    pub fn supports_notifications(&self) -> bool {
+        self.has_capability_named("notification")
+    }
+
+    /// `:interleave`, rpcs can be executed while subscribed to notifications
+    pub fn supports_interleave(&self) -> bool {
+        self.has_capability_named("interleave")
+    }
+
+    /// `:time`, see [`Rpc::scheduled_at`]
+    pub fn supports_scheduling(&self) -> bool {
+        self.has_capability(TIME_CAPABILITY)
+    }
+
+    /// `:with-defaults` parameter of get and get-config
+    pub fn supports_with_defaults(&self) -> bool {
+        self.has_capability_named("with-defaults")
+    }
+
+    /// Reporting modes of `:with-defaults`, basic-mode first followed by also-supported
+    /// ones. Empty if the capability is not advertised or has no parameters.
+    pub fn with_defaults_modes(&self) -> Vec<WithDefaultsValue> {
+        let basic_mode = self.capability_parameter(WITH_DEFAULTS_CAPABILITY, "basic-mode");
+        let also_supported = self
+            .capability_parameter(WITH_DEFAULTS_CAPABILITY, "also-supported")
+            .into_iter()
+            .flat_map(|modes| modes.split(','));
+        basic_mode
+            .into_iter()
+            .chain(also_supported)
+            .filter_map(WithDefaultsValue::from_name)
+            .collect()
+    }
+
+    /// Url schemes supported as source or target of operations, empty if `:url`
+    /// is not advertised
+    pub fn url_schemes(&self) -> Vec<String> {
+        self.capability_parameter(URL_CAPABILITY, "scheme")
+            .map(|schemes| schemes.split(',').map(str::to_lowercase).collect())
+            .unwrap_or_default()
+    }
+
+    /// Fails if `url` is not an url or server does not support its scheme
+    fn check_url(&self, url: &str) -> Result<()> {
+        let Datastore::Url(_) = Datastore::from_str(url)? else {
+            return Err(Error::UnknownDatastore {
+                expected: vec!["ftp|sftp|scp|http|file".to_string()],
+                unknown: url.to_string(),
+            });
+        };
+        if !self.supports_url() {
+            return Err(Error::CapabilityMissing(URL_CAPABILITY.to_string()));
+        }
+        let scheme = url.split(':').next().unwrap_or_default().to_lowercase();
+        let schemes = self.url_schemes();
+        if !schemes.contains(&scheme) {
+            return Err(Error::CapabilityMissing(format!(
+                "{} with scheme {}, supported schemes are {}",
+                URL_CAPABILITY,
+                scheme,
+                schemes.join(", ")
+            )));
+        }
+        Ok(())
+    }
+
+    /// Fails with [`Error::CapabilityMissing`] if `value` is not among the reporting
+    /// modes advertised by server, instead of the server rejecting the rpc
+    fn check_with_defaults(&self, value: WithDefaultsValue) -> Result<()> {
+        if !self.supports_with_defaults() {
+            return Err(Error::CapabilityMissing(format!(
+                "{} required by with-defaults {}",
+                WITH_DEFAULTS_CAPABILITY,
+                value.name()
+            )));
+        }
+        let modes = self.with_defaults_modes();
+        // Capability without parameters is malformed, leave the check to the server
+        if modes.is_empty() || modes.contains(&value) {
+            return Ok(());
+        }
+        let supported: Vec<&str> = modes.iter().map(WithDefaultsValue::name).collect();
+        Err(Error::CapabilityMissing(format!(
+            "{} with mode {}, supported modes are {}",
+            WITH_DEFAULTS_CAPABILITY,
+            value.name(),
+            supported.join(", ")
+        )))
+    }
+
+    fn hello(&mut self, hello: Hello, timeout: Duration) -> Result<Option<u64>> {
+        let base_1_1 = hello.has_capability(BASE_1_1_CAPABILITY.to_string());
+        let previous = self.transport.timeout();
+        self.transport.set_timeout(Some(timeout));
+        let response = self.transport.execute_rpc(&hello.to_string());
+        self.transport.set_timeout(previous);
+        let response = response.map_err(|err| match session_error(err) {
+            Error::Io(err) if err.kind() == io::ErrorKind::TimedOut => Error::HelloTimeout,
+            Error::Timeout => Error::HelloTimeout,
+            Error::SessionClosed => {
+                Error::HelloFailed("session closed before server hello".to_string())
+            }
+            err => err,
+        })?;
+        trace!("Hello:\n{}", response);
 
-        let hello: Hello = from_str(&response)?;
-        if hello.has_capability("urn:ietf:params:netconf:base:1.1".to_string()) {
+        let response = strip_netconf_prefixes(&response).unwrap_or(response);
+        let hello: Hello = from_str(&response)
+            .map_err(|err| Error::HelloFailed(format!("malformed server hello: {}", err)))?;
+        if base_1_1 && hello.has_capability(BASE_1_1_CAPABILITY.to_string()) {
             self.transport.upgrade();
         }
+        self.capabilities = hello.capabilities();
         Ok(hello.session_id())
     }
 
-    pub fn get_config(&mut self, datastore: &str) -> Result<String> {
+    pub fn get(&mut self, filter: Option<Filter>) -> Result<Reply> {
+        self.run_rpc(Rpc::new(RpcContent::Get {
+            filter,
+            with_defaults: None,
+        }))
+    }
+
+    /// Executes get with empty subtree filter, a no-op keeping idle session alive
+    pub fn ping(&mut self) -> Result<()> {
+        self.get(Some(Filter::subtree("")))?;
+        Ok(())
+    }
+
+    /// Returns sessions active on the server, see [`monitoring::sessions`]
+    pub fn get_sessions(&mut self) -> Result<Vec<monitoring::Session>> {
+        monitoring::sessions(self)
+    }
+
+    pub fn get_config(&mut self, datastore: &str) -> Result<Reply> {
         let get_config = Rpc::new(RpcContent::GetConfig {
             source: Source {
                 datastore: Datastore::from_str(datastore)?,
             },
             filter: None,
+            with_defaults: None,
+        });
+        self.run_rpc(get_config)
+    }
+
+    /// Executes NMDA get-data, origins requested with [`nmda::GetData::with_origin`]
+    /// are parsed from the reply with [`nmda::origins`]
+    pub fn get_data(&mut self, get_data: nmda::GetData) -> Result<Reply> {
+        self.run_rpc(Rpc::new(RpcContent::GetData(get_data)))
+    }
+
+    /// Executes get-config and returns iterator over the top level data nodes
+    /// of the reply, parsed incrementally as the reply is received.
+    /// Can not be used while notifications are received on the session.
+    pub fn get_config_stream(&mut self, datastore: &str) -> Result<DataStream<'_>> {
+        self.stream_get_config(datastore, None)
+    }
+
+    /// Executes get-config and writes the data to `writer` as it is received, e.g. for
+    /// backing up large configurations to a file. Returns the number of bytes written.
+    pub fn get_config_to_writer<W: io::Write>(
+        &mut self,
+        datastore: &str,
+        filter: Option<Filter>,
+        writer: &mut W,
+    ) -> Result<u64> {
+        let mut counter = CountingWriter {
+            inner: writer,
+            written: 0,
+        };
+        self.stream_get_config(datastore, filter)?
+            .write_to(&mut counter)?;
+        Ok(counter.written)
+    }
+
+    fn stream_get_config(
+        &mut self,
+        datastore: &str,
+        filter: Option<Filter>,
+    ) -> Result<DataStream<'_>> {
+        let get_config = Rpc::new(RpcContent::GetConfig {
+            source: Source {
+                datastore: Datastore::from_str(datastore)?,
+            },
+            filter,
+            with_defaults: None,
+        });
+        // Replies of pipelined rpcs must be read off the wire before the streamed one
+        while !self.in_flight.is_empty() {
+            self.receive_next()?;
+        }
+        let pending = self.send(&get_config)?;
+        // Reply is read by the stream instead of matched by message-id
+        self.forget(pending.message_id());
+        let reader = match self.transport.receive_stream() {
+            Ok(reader) => reader,
+            Err(err) => {
+                self.broken = true;
+                return Err(session_error(err));
+            }
+        };
+        self.last_activity = Instant::now();
+        let reader: Box<dyn io::Read + '_> = if self.interceptors.is_empty() {
+            reader
+        } else {
+            Box::new(stream::Recorder::new(reader, &mut self.interceptors))
+        };
+        Ok(DataStream::new(reader, self.fail_on_warnings))
+    }
+
+    /// Loads `config` XML, i.e. contents of `<config>` element, into `target` datastore
+    pub fn edit_config(&mut self, target: &str, config: &str) -> Result<()> {
+        #[cfg(feature = "yang")]
+        if let Some(checker) = &self.name_checker {
+            checker.check(config)?;
+        }
+        let edit_config = Rpc::new(RpcContent::EditConfig {
+            target: Target {
+                datastore: Datastore::from_str(target)?,
+            },
+            default_operation: None,
+            test_option: None,
+            error_option: None,
+            config: RawXml(config.to_string()),
+        });
+        self.run_rpc(edit_config)?;
+        Ok(())
+    }
+
+    /// Validates contents of `source` datastore
+    pub fn validate(&mut self, source: &str) -> Result<()> {
+        let validate = Rpc::new(RpcContent::Validate {
+            source: Source {
+                datastore: Datastore::from_str(source)?,
+            },
+        });
+        self.run_rpc(validate)?;
+        Ok(())
+    }
+
+    /// Commits candidate datastore to running
+    pub fn commit(&mut self) -> Result<()> {
+        self.run_rpc(Rpc::new(RpcContent::Commit))?;
+        Ok(())
+    }
+
+    /// Commits candidate datastore with confirmed commit parameters
+    pub fn commit_with(&mut self, options: CommitOptions) -> Result<()> {
+        self.run_rpc(Rpc::new(RpcContent::CommitWith(options)))?;
+        Ok(())
+    }
+
+    /// Commits candidate datastore, reverted by the server unless confirmed with
+    /// [`Connection::commit`] within `timeout` or before the session is closed
+    pub fn confirmed_commit(&mut self, timeout: Option<Duration>) -> Result<()> {
+        self.commit_with(CommitOptions::confirmed(timeout))
+    }
+
+    /// Cancels pending confirmed commit, reverting running to its state before the commit.
+    /// Requires `:confirmed-commit:1.1`.
+    pub fn cancel_commit(&mut self) -> Result<()> {
+        self.check_confirmed_commit_1_1()?;
+        self.run_rpc(Rpc::new(RpcContent::CancelCommit))?;
+        Ok(())
+    }
+
+    /// Confirms commit made persistent with `persist_id`, from any session
+    pub fn confirm_persisted(&mut self, persist_id: &str) -> Result<()> {
+        self.check_confirmed_commit_1_1()?;
+        self.commit_with(CommitOptions::confirm_persisted(persist_id))
+    }
+
+    /// Cancels commit made persistent with `persist_id`, from any session
+    pub fn cancel_persisted(&mut self, persist_id: &str) -> Result<()> {
+        self.check_confirmed_commit_1_1()?;
+        self.run_rpc(Rpc::new(RpcContent::CancelPersisted {
+            persist_id: persist_id.to_string(),
+        }))?;
+        Ok(())
+    }
+
+    /// Opens new session over `transport` to confirm commit made persistent with
+    /// `persist_id`, e.g. when the session of the confirmed commit was lost before
+    /// confirming. The new session is closed afterwards.
+    pub fn confirm_in_new_session<T>(transport: T, persist_id: &str) -> Result<()>
+    where
+        T: Transport + 'static,
+    {
+        let mut connection = Connection::new(transport)?;
+        let result = connection.confirm_persisted(persist_id);
+        if let Err(err) = connection.close_session() {
+            warn!("Could not close session after confirming commit: {}", err);
+        }
+        result
+    }
+
+    /// Persist-id of confirmed commits requires `:confirmed-commit:1.1`
+    fn check_confirmed_commit_1_1(&self) -> Result<()> {
+        let capability = format!("{}confirmed-commit:1.1", CAPABILITY_PREFIX);
+        if !self.has_capability(&capability) {
+            return Err(Error::CapabilityMissing(capability));
+        }
+        Ok(())
+    }
+
+    /// Commits candidate with confirmed commit and runs `verify`, e.g. checking that the
+    /// device is still reachable. Commit is confirmed if `verify` returns true and cancelled
+    /// otherwise; returns whether it was confirmed. If the session is lost during `verify`,
+    /// the server reverts the commit itself after `timeout`. Servers with only
+    /// `:confirmed-commit:1.0` can't cancel, so they revert after `timeout` also when
+    /// verification fails.
+    pub fn commit_with_verification<F>(
+        &mut self,
+        timeout: Option<Duration>,
+        verify: F,
+    ) -> Result<bool>
+    where
+        F: FnOnce(&mut Connection) -> bool,
+    {
+        if !self.supports_confirmed_commit() {
+            return Err(Error::CapabilityMissing(format!(
+                "{}confirmed-commit:1.0",
+                CAPABILITY_PREFIX
+            )));
+        }
+        self.confirmed_commit(timeout)?;
+        if verify(self) {
+            self.commit()?;
+            Ok(true)
+        } else if self.check_confirmed_commit_1_1().is_ok() {
+            warn!("Verification failed, cancelling commit");
+            self.cancel_commit()?;
+            Ok(false)
+        } else {
+            warn!("Verification failed, commit is reverted when confirm timeout expires");
+            Ok(false)
+        }
+    }
+
+    /// Reverts candidate datastore to current contents of running
+    pub fn discard_changes(&mut self) -> Result<()> {
+        self.run_rpc(Rpc::new(RpcContent::DiscardChanges))?;
+        Ok(())
+    }
+
+    /// Replaces `target` datastore with contents of `source`, either can be an url
+    pub fn copy_config(&mut self, source: &str, target: &str) -> Result<()> {
+        let copy_config = Rpc::new(RpcContent::CopyConfig {
+            target: Target {
+                datastore: Datastore::from_str(target)?,
+            },
+            source: Source {
+                datastore: Datastore::from_str(source)?,
+            },
+        });
+        self.run_rpc(copy_config)?;
+        Ok(())
+    }
+
+    /// Copies `datastore` to `url` on a server supporting its scheme, e.g.
+    /// `sftp://backup@10.0.0.1/r1.xml`
+    pub fn backup_to_url(&mut self, datastore: &str, url: &str) -> Result<()> {
+        self.check_url(url)?;
+        self.copy_config(datastore, url)
+    }
+
+    /// Replaces `target` datastore with configuration from `url`
+    pub fn restore_from_url(&mut self, url: &str, target: &str) -> Result<()> {
+        self.check_url(url)?;
+        self.copy_config(url, target)
+    }
+
+    /// Saves running configuration to persist over reboot, by copying it to startup
+    /// or as defined by device profile. Fails with [`Error::CapabilityMissing`] if
+    /// copy to startup is needed but `:startup` is not advertised.
+    pub fn save_config(&mut self) -> Result<()> {
+        let save_config = self
+            .profile
+            .as_ref()
+            .map_or(SaveConfig::CopyToStartup, |profile| profile.save_config());
+        match save_config {
+            SaveConfig::CopyToStartup => {
+                if !self.supports_startup() {
+                    return Err(Error::CapabilityMissing(format!(
+                        "{}startup:1.0",
+                        CAPABILITY_PREFIX
+                    )));
+                }
+                self.copy_config("running", "startup")
+            }
+            SaveConfig::Operation(operation) => {
+                self.run_rpc(Rpc::new(RpcContent::Raw(RawXml(operation))))?;
+                Ok(())
+            }
+            SaveConfig::Persistent => Ok(()),
+        }
+    }
+
+    /// Locks `datastore` for exclusive use of this session
+    pub fn lock(&mut self, datastore: &str) -> Result<()> {
+        let lock = Rpc::new(RpcContent::Lock {
+            target: Target {
+                datastore: Datastore::from_str(datastore)?,
+            },
+        });
+        self.run_rpc(lock)?;
+        Ok(())
+    }
+
+    pub fn unlock(&mut self, datastore: &str) -> Result<()> {
+        let unlock = Rpc::new(RpcContent::Unlock {
+            target: Target {
+                datastore: Datastore::from_str(datastore)?,
+            },
         });
-        let response = self.transport.execute_rpc(&get_config.to_string())?;
-        log::trace!("Reply:\n{}", response);
+        self.run_rpc(unlock)?;
+        Ok(())
+    }
 
-        if !self.skip_errors {
-            let reply: RpcReply = from_str(&response)?;
-            if reply.has_errors() {
-                return Err(Error::Netconf(reply));
+    /// Terminates another session, releasing its locks and aborting its operations
+    pub fn kill_session(&mut self, session_id: u64) -> Result<()> {
+        self.run_rpc(Rpc::new(RpcContent::KillSession { session_id }))?;
+        Ok(())
+    }
+
+    /// Locks `datastore` and returns guard unlocking it when dropped, so that lock
+    /// is released also on early returns and panics. Rpcs are executed through the guard.
+    pub fn lock_guarded(&mut self, datastore: &str) -> Result<LockGuard<'_>> {
+        self.lock(datastore)?;
+        Ok(LockGuard {
+            connection: self,
+            datastore: Some(datastore.to_string()),
+        })
+    }
+
+    /// Runs `edits` with candidate datastore locked, then validates it when `:validate` is
+    /// supported and commits. On any failure changes are discarded; lock is always released.
+    pub fn transaction<F, T>(&mut self, edits: F) -> Result<T>
+    where
+        F: FnOnce(&mut Connection) -> Result<T>,
+    {
+        let mut guard = self.lock_guarded("candidate")?;
+        let result = edits(&mut guard).and_then(|value| {
+            if guard.supports_validate() {
+                guard.validate("candidate")?;
+            }
+            guard.commit()?;
+            Ok(value)
+        });
+        match result {
+            Ok(value) => {
+                guard.unlock()?;
+                Ok(value)
             }
+            Err(err) => {
+                if !guard.is_broken() {
+                    if let Err(err) = guard.discard_changes() {
+                        error!("Failed to discard changes: {}", err);
+                    }
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// Subscribes to event notifications of `stream`, or the default NETCONF stream if `None`.
+    /// Received notifications are read with [`Connection::notifications`].
+    pub fn create_subscription(&mut self, stream: Option<&str>) -> Result<()> {
+        self.create_subscription_between(stream, None, None)
+    }
+
+    /// Like [`Connection::create_subscription`], replaying notifications logged since
+    /// `start_time`, which requires `:replay` capability, and ending subscription at `stop_time`.
+    /// Server sends `notificationComplete` notification once subscription ends.
+    pub fn create_subscription_between(
+        &mut self,
+        stream: Option<&str>,
+        start_time: Option<OffsetDateTime>,
+        stop_time: Option<OffsetDateTime>,
+    ) -> Result<()> {
+        let create_subscription =
+            RpcContent::create_subscription_between(stream, start_time, stop_time)?;
+        self.run_rpc(Rpc::new(create_subscription))?;
+        self.subscribed = true;
+        Ok(())
+    }
+
+    /// Establishes dynamic subscription and returns its subscription id.
+    /// Received notifications are read with [`Connection::notifications`].
+    pub fn establish_subscription(&mut self, subscription: EstablishSubscription) -> Result<u32> {
+        let establish_subscription = Rpc::new(RpcContent::EstablishSubscription(subscription));
+        let reply = self.run_rpc(establish_subscription)?;
+        let reply: EstablishSubscriptionReply = from_str(reply.raw())?;
+        Ok(reply.id)
+    }
+
+    /// Changes parameters of an established dynamic subscription
+    pub fn modify_subscription(&mut self, subscription: ModifySubscription) -> Result<()> {
+        let modify_subscription = Rpc::new(RpcContent::ModifySubscription(subscription));
+        self.run_rpc(modify_subscription)?;
+        Ok(())
+    }
+
+    /// Ends dynamic subscription established on this session
+    pub fn delete_subscription(&mut self, id: u32) -> Result<()> {
+        let delete_subscription = Rpc::new(RpcContent::delete_subscription(id));
+        self.run_rpc(delete_subscription)?;
+        Ok(())
+    }
+
+    /// Requests on-change YANG Push subscription to send full datastore contents in next update
+    pub fn resync_subscription(&mut self, id: u32) -> Result<()> {
+        let resync_subscription = Rpc::new(RpcContent::resync_subscription(id));
+        self.run_rpc(resync_subscription)?;
+        Ok(())
+    }
+
+    /// Returns iterator blocking until next notification is received.
+    /// Iteration ends after the first error, e.g. [`Error::Timeout`] if timeout is set.
+    ///
+    /// Transport is read by the caller of each method, so there are no separate read and
+    /// write halves. With `:interleave` rpcs can be executed between notifications,
+    /// notifications received while waiting for replies are queued for this iterator.
+    pub fn notifications(&mut self) -> Notifications<'_> {
+        Notifications {
+            connection: self,
+            cancellation: None,
+            failed: false,
+        }
+    }
+
+    /// Like [`Connection::notifications`], but the iterator ends once `token` is cancelled.
+    /// Cancellation is checked between messages, so a blocked read is only interrupted
+    /// by the timeout set with [`Connection::set_timeout`], after which the session is unusable.
+    pub fn notifications_until(&mut self, token: CancellationToken) -> Notifications<'_> {
+        Notifications {
+            connection: self,
+            cancellation: Some(token),
+            failed: false,
         }
-        Ok(response.to_string())
     }
 
     pub fn close_session(&mut self) -> Result<()> {
         let close_session = Rpc::new(RpcContent::CloseSession);
-        let response = self.transport.execute_rpc(&close_session.to_string())?;
-        log::trace!("Reply:\n{}", response.trim());
+        self.run_rpc(close_session)?;
+        self.closed = true;
+        Ok(())
+    }
 
-        let reply: RpcReply = from_str(&response)?;
-        if reply.has_errors() {
-            Err(Error::Netconf(reply))
-        } else {
+    /// Gracefully ends the session with close-session and closes the transport.
+    /// Dropping connection without shutdown does not notify the server.
+    pub fn shutdown(mut self) -> Result<()> {
+        let result = if self.closed {
             Ok(())
-        }
+        } else {
+            self.close_session()
+        };
+        self.closed = true;
+        self.transport.close()?;
+        result
+    }
+
+    /// Sends rpc without waiting for the reply. Multiple rpcs can be in flight at once,
+    /// replies are matched to requests by message-id with [`Connection::wait_reply`].
+    ///
+    /// While a subscription is active, other rpcs than close-session require the
+    /// server to support `:interleave`, replies and notifications are then
+    /// separated by their root element.
+    pub fn send_rpc(&mut self, rpc: Rpc) -> Result<PendingRpc> {
+        self.send(&rpc)
+    }
+
+    fn send(&mut self, rpc: &Rpc) -> Result<PendingRpc> {
+        if self.closed {
+            return Err(Error::SessionClosed);
+        }
+        if self.subscribed
+            && !matches!(rpc.content(), RpcContent::CloseSession)
+            && !self.has_capability(INTERLEAVE_CAPABILITY)
+        {
+            return Err(Error::CapabilityMissing(INTERLEAVE_CAPABILITY.to_string()));
+        }
+        if let RpcContent::Get {
+            with_defaults: Some(with_defaults),
+            ..
+        }
+        | RpcContent::GetConfig {
+            with_defaults: Some(with_defaults),
+            ..
+        } = rpc.content()
+        {
+            self.check_with_defaults(with_defaults.value)?;
+        }
+        if rpc.scheduled_time().is_some() && !self.has_capability(TIME_CAPABILITY) {
+            return Err(Error::CapabilityMissing(TIME_CAPABILITY.to_string()));
+        }
+        let sent = Instant::now();
+        let message = self.intercept_send(rpc);
+        let operation = if self.metrics.is_some() || cfg!(feature = "tracing") {
+            metrics::operation_name(&message)
+        } else {
+            String::new()
+        };
+        let pending = PendingRpc {
+            message_id: rpc.message_id().to_string(),
+            sent,
+            #[cfg(feature = "tracing")]
+            span: tracing::debug_span!(
+                parent: &self.span,
+                "rpc",
+                operation = %operation,
+                message_id = %rpc.message_id()
+            ),
+            operation,
+            bytes_sent: message.len(),
+        };
+        #[cfg(feature = "tracing")]
+        let _entered = pending.span.clone().entered();
+        if let Err(err) = self.transport.send(&message) {
+            let err = session_error(err);
+            self.broken = true;
+            self.record(&pending, 0, Outcome::from(&err));
+            return Err(err);
+        }
+        self.last_activity = Instant::now();
+        self.in_flight.push_back(pending.message_id.clone());
+        Ok(pending)
+    }
+
+    /// Waits reply for the pending rpc. Replies to other in-flight rpcs
+    /// received meanwhile are kept until they are waited.
+    pub fn wait_reply(&mut self, pending: PendingRpc) -> Result<Reply> {
+        #[cfg(feature = "tracing")]
+        let _entered = pending.span.clone().entered();
+        let response = loop {
+            if let Some(response) = self.received.remove(&pending.message_id) {
+                break response;
+            }
+            let result = match self.lost.remove(&pending.message_id) {
+                Some(err) => Err(err),
+                None => self.receive_next(),
+            };
+            if let Err(err) = result {
+                // Late reply is dropped instead of kept for an rpc nobody waits
+                self.forget(&pending.message_id);
+                self.record(&pending, 0, Outcome::from(&err));
+                return Err(err);
+            }
+        };
+        trace!("Reply:\n{}", response.trim());
+
+        let received = response.len();
+        let reply = Reply::new(response, pending.message_id.clone(), pending.sent.elapsed());
+        let result = match reply.rpc_reply() {
+            Ok(rpc_reply) if is_rejected(rpc_reply, self.fail_on_warnings) => reply
+                .into_rpc_reply()
+                .and_then(|reply| Err(Error::Netconf(reply))),
+            Ok(_) => Ok(reply),
+            Err(err) => Err(err),
+        };
+        let outcome = match &result {
+            Ok(_) => Outcome::Ok,
+            Err(err) => Outcome::from(err),
+        };
+        self.record(&pending, received, outcome);
+        result
+    }
+
+    fn record(&mut self, pending: &PendingRpc, bytes_received: usize, outcome: Outcome) {
+        if let Some(metrics) = &mut self.metrics {
+            metrics.on_rpc(&RpcMetrics {
+                operation: &pending.operation,
+                message_id: &pending.message_id,
+                bytes_sent: pending.bytes_sent,
+                bytes_received,
+                duration: pending.sent.elapsed(),
+                outcome,
+            });
+        }
+    }
+
+    /// Runs `rpcs` in order and returns their results. With [`BatchMode::StopOnError`]
+    /// results end at the first failed rpc.
+    pub fn run_batch(&mut self, rpcs: Vec<Rpc>, mode: BatchMode) -> Vec<Result<Reply>> {
+        let mut results = Vec::with_capacity(rpcs.len());
+        match mode {
+            BatchMode::StopOnError => {
+                for rpc in rpcs {
+                    let result = self.run_rpc(rpc);
+                    let failed = result.is_err();
+                    results.push(result);
+                    if failed {
+                        break;
+                    }
+                }
+            }
+            BatchMode::ContinueOnError => {
+                let mut pending = Vec::with_capacity(rpcs.len());
+                for rpc in rpcs {
+                    let sent = self.send_rpc(rpc);
+                    let failed = sent.is_err();
+                    pending.push(sent);
+                    // Nothing can be sent after transport failure
+                    if failed {
+                        break;
+                    }
+                }
+                for pending in pending {
+                    results.push(pending.and_then(|pending| self.wait_reply(pending)));
+                }
+            }
+        }
+        results
+    }
+
+    /// Sends rpc and waits for its reply, e.g. for operations without a dedicated method
+    /// built with [`RpcContent::Raw`] or [`Rpc::from_operation`]
+    pub fn execute(&mut self, rpc: Rpc) -> Result<Reply> {
+        self.run_rpc(rpc)
+    }
+
+    fn run_rpc(&mut self, rpc: Rpc) -> Result<Reply> {
+        let result = self.send(&rpc).and_then(|pending| self.wait_reply(pending));
+        self.retry_rpc(rpc, result)
+    }
+
+    /// Retries `rpc` whose first attempt ended with `result` as the retry policy allows
+    pub(crate) fn retry_rpc(&mut self, mut rpc: Rpc, mut result: Result<Reply>) -> Result<Reply> {
+        let mut attempt = 1;
+        loop {
+            // Rpc may have been executed when session broke, e.g. timed out, and
+            // executing it again could repeat a commit or edit-config
+            let backoff = match (&result, &self.retry) {
+                (Err(err), Some(retry)) if !self.broken && retry.is_retryable(err) => {
+                    retry.backoff(attempt)
+                }
+                _ => None,
+            };
+            let (Some(delay), Err(err)) = (backoff, &result) else {
+                return result;
+            };
+            warn!(
+                "Attempt {} of rpc {} failed: {}, retrying in {:.1}s",
+                attempt,
+                rpc.message_id(),
+                err,
+                delay.as_secs_f32()
+            );
+            thread::sleep(delay);
+            rpc.renew_message_id();
+            attempt += 1;
+            result = self.send(&rpc).and_then(|pending| self.wait_reply(pending));
+        }
+    }
+
+    fn intercept_send(&mut self, rpc: &Rpc) -> String {
+        let mut message = rpc.to_xml(self.indent);
+        for interceptor in self.interceptors.iter_mut() {
+            interceptor.on_send(&mut message);
+        }
+        message
+    }
+
+    /// Removes `message_id` from rpcs in flight, returns false if it was not there
+    fn forget(&mut self, message_id: &str) -> bool {
+        match self.in_flight.iter().position(|id| id == message_id) {
+            Some(index) => self.in_flight.remove(index).is_some(),
+            None => false,
+        }
+    }
+
+    fn receive_next(&mut self) -> Result<()> {
+        let response = match self.transport.receive() {
+            Ok(response) => response,
+            // Message was skipped as a whole, so the session is still in sync
+            Err(err @ (Error::FrameResynchronized { .. } | Error::InvalidUtf8 { .. })) => {
+                // Server replies in the order rpcs were received, so the lost message is
+                // the reply of the oldest rpc in flight, unless it was a notification
+                return match self.in_flight.pop_front() {
+                    Some(message_id) => {
+                        self.lost.insert(message_id, err);
+                        Ok(())
+                    }
+                    None => Err(err),
+                };
+            }
+            Err(err) => {
+                self.broken = true;
+                return match err {
+                    Error::Io(err) if err.kind() == io::ErrorKind::TimedOut => Err(Error::Timeout),
+                    err => Err(session_error(err)),
+                };
+            }
+        };
+        self.last_activity = Instant::now();
+        let mut response = response;
+        if let Some(profile) = &self.profile {
+            profile.fix_message(&mut response);
+        }
+        let response = self
+            .interceptors
+            .iter_mut()
+            .fold(response, |mut message, i| {
+                i.on_receive(&mut message);
+                message
+            });
+        let response = strip_netconf_prefixes(&response).unwrap_or(response);
+        let message_id = match root_element(&response) {
+            Some((root, _)) if root == "notification" => {
+                self.notifications.push_back(response);
+                return Ok(());
+            }
+            Some((_, Some(id))) => id,
+            // Replies without message-id can only be matched when there is one rpc in flight
+            _ if self.in_flight.len() == 1 => self.in_flight[0].clone(),
+            _ => {
+                warn!("Dropping reply without message-id:\n{}", response.trim());
+                return Ok(());
+            }
+        };
+        if self.forget(&message_id) {
+            self.received.insert(message_id, response);
+        } else {
+            warn!("Dropping reply to unknown message-id '{}'", message_id);
+        }
+        Ok(())
+    }
+}
+
+/// Maps transport errors of a session closed by the server to [`Error::SessionClosed`]
+fn session_error(err: Error) -> Error {
+    match err {
+        Error::Io(err)
+            if matches!(
+                err.kind(),
+                io::ErrorKind::UnexpectedEof
+                    | io::ErrorKind::BrokenPipe
+                    | io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+            ) =>
+        {
+            Error::SessionClosed
+        }
+        err => err,
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        if !self.closed {
+            // Closing blocks on the server, so only the transport resources are released here
+            debug!(
+                "Connection with session-id {} dropped without shutdown",
+                self.session_id()
+            );
+        }
+    }
+}
+
+/// Iterator over received notifications, see [`Connection::notifications`]
+pub struct Notifications<'a> {
+    connection: &'a mut Connection,
+    cancellation: Option<CancellationToken>,
+    failed: bool,
+}
+
+impl Notifications<'_> {
+    fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+}
+
+impl Iterator for Notifications<'_> {
+    type Item = Result<Notification>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.failed && !self.is_cancelled() {
+            if let Some(notification) = self.connection.notifications.pop_front() {
+                trace!("Notification:\n{}", notification.trim());
+                return Some(Notification::from_str(&notification));
+            }
+            if let Err(err) = self.connection.receive_next() {
+                self.failed = true;
+                if matches!(err, Error::Timeout) && self.is_cancelled() {
+                    return None;
+                }
+                return Some(Err(err));
+            }
+        }
+        None
+    }
+}
+
+/// Locked datastore, see [`Connection::lock_guarded`]
+pub struct LockGuard<'a> {
+    connection: &'a mut Connection,
+    datastore: Option<String>,
+}
+
+impl LockGuard<'_> {
+    /// Unlocks datastore, returning the possible error instead of logging it on drop
+    pub fn unlock(mut self) -> Result<()> {
+        let datastore = self.datastore.take().unwrap();
+        self.connection.unlock(&datastore)
+    }
+}
+
+impl Deref for LockGuard<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Self::Target {
+        self.connection
+    }
+}
+
+impl DerefMut for LockGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.connection
+    }
+}
+
+impl Drop for LockGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(datastore) = self.datastore.take() {
+            if self.connection.is_broken() {
+                return;
+            }
+            if let Err(err) = self.connection.unlock(&datastore) {
+                error!("Failed to unlock {} datastore: {}", datastore, err);
+            }
+        }
+    }
+}
+
+struct CountingWriter<'a, W> {
+    inner: &'a mut W,
+    written: u64,
+}
+
+impl<W: io::Write> io::Write for CountingWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Connection using a temporary timeout, see [`Connection::with_timeout`]
+pub struct WithTimeout<'a> {
+    connection: &'a mut Connection,
+    previous: Option<Duration>,
+}
+
+impl Deref for WithTimeout<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Self::Target {
+        self.connection
+    }
+}
+
+impl DerefMut for WithTimeout<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.connection
+    }
+}
+
+impl Drop for WithTimeout<'_> {
+    fn drop(&mut self) {
+        self.connection.transport.set_timeout(self.previous);
+    }
+}
+
+/// Logs warnings of the reply and returns true if the reply must fail the rpc
+pub(crate) fn is_rejected(reply: &RpcReply, fail_on_warnings: bool) -> bool {
+    for warning in reply.warnings() {
+        warn!(
+            "Rpc warning, tag: {:?}, message: {}",
+            warning.tag(),
+            warning.message().unwrap_or_default().trim()
+        );
+    }
+    reply.has_errors() || (fail_on_warnings && reply.has_warnings())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::io::Read;
+    use transport::mock::MockTransport;
+
+    const NOTIFICATION: &str = r#"<notification xmlns="urn:ietf:params:xml:ns:netconf:notification:1.0"><eventTime>2024-05-01T10:00:00Z</eventTime><event/></notification>"#;
+
+    /// Replies to every rpc, but returns replies in reverse order
+    #[derive(Default)]
+    pub(crate) struct ReversingTransport {
+        pub(crate) capabilities: Vec<&'static str>,
+        pub(crate) notify: bool,
+        pub(crate) silent: bool,
+        pub(crate) replies: Vec<String>,
+    }
+
+    impl Transport for ReversingTransport {
+        fn send(&mut self, message: &str) -> Result<()> {
+            if self.silent {
+                return Ok(());
+            }
+            let reply = match root_element(message).and_then(|(_, id)| id) {
+                Some(id) if self.notify => {
+                    self.replies.push(format!(
+                        r#"<rpc-reply message-id="{}"><ok/></rpc-reply>"#,
+                        id
+                    ));
+                    NOTIFICATION.to_string()
+                }
+                Some(id) => format!(r#"<rpc-reply message-id="{}"><ok/></rpc-reply>"#, id),
+                None => {
+                    let capabilities: String = self
+                        .capabilities
+                        .iter()
+                        .map(|c| format!("<capability>{}</capability>", c))
+                        .collect();
+                    format!(
+                        r#"<hello xmlns="urn:ietf:params:xml:ns:netconf:base:1.0"><capabilities><capability>urn:ietf:params:netconf:base:1.0</capability>{}</capabilities><session-id>4</session-id></hello>"#,
+                        capabilities
+                    )
+                }
+            };
+            self.replies.push(reply);
+            Ok(())
+        }
+
+        fn receive(&mut self) -> Result<String> {
+            match self.replies.pop() {
+                Some(reply) => Ok(reply),
+                None => Err(io::Error::from(io::ErrorKind::TimedOut).into()),
+            }
+        }
+
+        fn receive_stream(&mut self) -> Result<Box<dyn Read + '_>> {
+            Err(io::Error::new(io::ErrorKind::Unsupported, "streaming not supported").into())
+        }
+
+        fn timeout(&self) -> Option<Duration> {
+            None
+        }
+
+        fn set_timeout(&mut self, _timeout: Option<Duration>) {}
+
+        fn set_message_limits(&mut self, _limits: MessageLimits) {}
+
+        fn close(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn upgrade(&mut self) {}
+    }
+
+    /// Fails receiving the next reply with `error`, skipping it like a framer would
+    struct SkippingTransport<T> {
+        inner: T,
+        error: Option<Error>,
+    }
+
+    impl<T: Transport> Transport for SkippingTransport<T> {
+        fn send(&mut self, message: &str) -> Result<()> {
+            self.inner.send(message)
+        }
+
+        fn receive(&mut self) -> Result<String> {
+            let reply = self.inner.receive()?;
+            match self.error.take() {
+                Some(err) if reply.starts_with("<rpc-reply") => Err(err),
+                Some(err) => {
+                    self.error = Some(err);
+                    Ok(reply)
+                }
+                None => Ok(reply),
+            }
+        }
+
+        fn receive_stream(&mut self) -> Result<Box<dyn Read + '_>> {
+            Err(io::Error::new(io::ErrorKind::Unsupported, "streaming not supported").into())
+        }
+
+        fn timeout(&self) -> Option<Duration> {
+            None
+        }
+
+        fn set_timeout(&mut self, _timeout: Option<Duration>) {}
+
+        fn set_message_limits(&mut self, _limits: MessageLimits) {}
+
+        fn close(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn upgrade(&mut self) {}
+    }
+
+    #[test]
+    fn test_invalid_utf8_keeps_session() {
+        let mut connection = Connection::new(SkippingTransport {
+            inner: ReversingTransport::default(),
+            error: Some(Error::InvalidUtf8 { offset: 12 }),
+        })
+        .unwrap();
+        assert!(matches!(
+            connection.get(None),
+            Err(Error::InvalidUtf8 { offset: 12 })
+        ));
+        assert!(!connection.is_broken());
+        assert!(connection.is_reusable());
+        connection.get_config("running").unwrap();
+    }
+
+    #[test]
+    fn test_lost_reply_of_oldest_rpc() {
+        let transport = MockTransport::scripted([
+            ("<get/>", "<data/>"),
+            ("<get-config>", "<data/>"),
+            ("<close-session/>", "<ok/>"),
+        ]);
+        let mut connection = Connection::new(SkippingTransport {
+            inner: transport,
+            error: Some(Error::FrameResynchronized { skipped: 10 }),
+        })
+        .unwrap();
+        let get = connection
+            .send_rpc(Rpc::new(RpcContent::Get {
+                filter: None,
+                with_defaults: None,
+            }))
+            .unwrap();
+        let get_config = connection
+            .send_rpc(Rpc::new(RpcContent::GetConfig {
+                source: Source {
+                    datastore: Datastore::Running,
+                },
+                filter: None,
+                with_defaults: None,
+            }))
+            .unwrap();
+        connection.wait_reply(get_config).unwrap();
+        assert!(matches!(
+            connection.wait_reply(get),
+            Err(Error::FrameResynchronized { skipped: 10 })
+        ));
+        assert!(connection.is_reusable());
+    }
+
+    #[test]
+    fn test_pipelined_rpcs() {
+        let mut connection = Connection::new(ReversingTransport::default()).unwrap();
+        assert_eq!(connection.session_id(), 4);
+
+        let first = connection
+            .send_rpc(Rpc::new(RpcContent::CloseSession))
+            .unwrap();
+        let second = connection
+            .send_rpc(Rpc::new(RpcContent::KillSession { session_id: 5 }))
+            .unwrap();
+        let (first_id, second_id) = (
+            first.message_id().to_string(),
+            second.message_id().to_string(),
+        );
+
+        let reply = connection.wait_reply(first).unwrap();
+        assert_eq!(reply.message_id(), first_id);
+        assert_eq!(connection.received.len(), 1);
+
+        let reply = connection.wait_reply(second).unwrap();
+        assert_eq!(reply.message_id(), second_id);
+        assert!(connection.received.is_empty());
+        assert!(connection.in_flight.is_empty());
+    }
+
+    #[test]
+    fn test_hello_timeout() {
+        let transport = ReversingTransport {
+            silent: true,
+            ..Default::default()
+        };
+        let result = Connection::new_with_hello_timeout(transport, Duration::from_millis(10));
+        assert!(matches!(result, Err(Error::HelloTimeout)));
+    }
+
+    #[test]
+    fn test_session_closed() {
+        let mut connection = Connection::new(ReversingTransport::default()).unwrap();
+        connection.close_session().unwrap();
+        assert!(matches!(connection.get(None), Err(Error::SessionClosed)));
+        connection.shutdown().unwrap();
+
+        let eof = io::Error::from(io::ErrorKind::UnexpectedEof);
+        assert!(matches!(session_error(eof.into()), Error::SessionClosed));
+    }
+
+    #[test]
+    fn test_capability_predicates() {
+        let connection = Connection::new(ReversingTransport {
+            capabilities: vec![
+                "urn:ietf:params:netconf:capability:candidate:1.0",
+                "urn:ietf:params:netconf:capability:confirmed-commit:1.1",
+                "urn:ietf:params:netconf:capability:url:1.0?scheme=http,ftp,file",
+                "urn:ietf:params:xml:ns:yang:ietf-interfaces?module=ietf-interfaces",
+            ],
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(connection.supports_candidate());
+        assert!(connection.supports_confirmed_commit());
+        assert!(connection.supports_url());
+        assert!(!connection.supports_xpath());
+        assert!(!connection.supports_writable_running());
+        assert!(!connection.supports_interleave());
+    }
+
+    #[test]
+    fn test_with_defaults_check() {
+        let get = |value: WithDefaultsValue| {
+            Rpc::new(RpcContent::Get {
+                filter: None,
+                with_defaults: Some(value.into()),
+            })
+        };
+        let mut connection = Connection::new(ReversingTransport::default()).unwrap();
+        assert!(matches!(
+            connection.execute(get(WithDefaultsValue::Trim)),
+            Err(Error::CapabilityMissing(_))
+        ));
+
+        let mut connection = Connection::new(ReversingTransport {
+            capabilities: vec![
+                "urn:ietf:params:netconf:capability:with-defaults:1.0?basic-mode=explicit&amp;also-supported=report-all,trim",
+            ],
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            connection.with_defaults_modes(),
+            vec![
+                WithDefaultsValue::Explicit,
+                WithDefaultsValue::ReportAll,
+                WithDefaultsValue::Trim
+            ]
+        );
+        assert!(connection.execute(get(WithDefaultsValue::Trim)).is_ok());
+        match connection.execute(get(WithDefaultsValue::ReportAllTagged)) {
+            Err(Error::CapabilityMissing(message)) => {
+                assert!(message.ends_with("supported modes are explicit, report-all, trim"))
+            }
+            other => panic!("expected missing capability, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_interleaved_notifications() {
+        let mut connection = Connection::new(ReversingTransport {
+            capabilities: vec![INTERLEAVE_CAPABILITY],
+            notify: true,
+            ..Default::default()
+        })
+        .unwrap();
+        connection.create_subscription(None).unwrap();
+
+        let kill_session = Rpc::new(RpcContent::KillSession { session_id: 5 });
+        let pending = connection.send_rpc(kill_session).unwrap();
+        connection.wait_reply(pending).unwrap();
+        assert_eq!(connection.notifications.len(), 2);
+        let notification = connection.notifications().next().unwrap().unwrap();
+        assert_eq!(notification.event_time(), "2024-05-01T10:00:00Z");
+    }
+
+    #[test]
+    fn test_cancelled_notifications() {
+        let mut connection = Connection::new(ReversingTransport {
+            capabilities: vec![INTERLEAVE_CAPABILITY],
+            notify: true,
+            ..Default::default()
+        })
+        .unwrap();
+        connection.create_subscription(None).unwrap();
+
+        let token = CancellationToken::new();
+        let mut notifications = connection.notifications_until(token.clone());
+        assert!(notifications.next().unwrap().is_ok());
+        token.cancel();
+        assert!(notifications.next().is_none());
+    }
+
+    #[test]
+    fn test_rpc_during_subscription_without_interleave() {
+        let mut connection = Connection::new(ReversingTransport::default()).unwrap();
+        connection.create_subscription(None).unwrap();
+
+        let kill_session = Rpc::new(RpcContent::KillSession { session_id: 5 });
+        assert!(matches!(
+            connection.send_rpc(kill_session),
+            Err(Error::CapabilityMissing(_))
+        ));
+        connection.close_session().unwrap();
+    }
+
+    #[test]
+    fn test_interceptors() {
+        struct Redact;
+
+        impl RpcInterceptor for Redact {
+            fn on_receive(&mut self, message: &mut String) {
+                *message = message.replace("<ok/>", "<ok></ok>");
+            }
+        }
+
+        struct Record(std::sync::Arc<std::sync::Mutex<Vec<String>>>);
+
+        impl RpcInterceptor for Record {
+            fn on_send(&mut self, rpc: &mut String) {
+                self.0.lock().unwrap().push(rpc.clone());
+            }
+
+            fn on_receive(&mut self, message: &mut String) {
+                self.0.lock().unwrap().push(message.clone());
+            }
+        }
+
+        let recorded = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut connection = Connection::new(ReversingTransport::default()).unwrap();
+        connection.add_interceptor(Redact);
+        connection.add_interceptor(Record(recorded.clone()));
+        connection.close_session().unwrap();
+
+        let recorded = recorded.lock().unwrap();
+        assert_eq!(recorded.len(), 2);
+        assert!(recorded[0].contains("<close-session/>"));
+        assert!(recorded[1].ends_with("<ok></ok></rpc-reply>"));
+    }
+
+    #[test]
+    fn test_commit_with_verification() {
+        let ok = "<ok/>";
+        let confirmed_commit = MockTransport::scripted([
+            ("<confirm-timeout>30</confirm-timeout>", ok),
+            ("<get>", "<data/>"),
+            ("<commit/>", ok),
+        ]);
+        let cancelled_commit =
+            MockTransport::scripted([("<confirmed/>", ok), ("<cancel-commit/>", ok)]);
+        for (mut transport, confirm) in [(confirmed_commit, true), (cancelled_commit, false)] {
+            transport.set_capabilities(["urn:ietf:params:netconf:capability:confirmed-commit:1.1"]);
+            let mut connection = Connection::new(transport).unwrap();
+            let confirmed = connection
+                .commit_with_verification(Some(Duration::from_secs(30)), |connection| {
+                    confirm && connection.ping().is_ok()
+                })
+                .unwrap();
+            assert_eq!(confirmed, confirm);
+        }
+
+        // Cancel-commit is not supported before 1.1, commit is left to time out
+        let mut transport = MockTransport::scripted([("<confirmed/>", ok)]);
+        transport.set_capabilities(["urn:ietf:params:netconf:capability:confirmed-commit:1.0"]);
+        let mut connection = Connection::new(transport).unwrap();
+        assert!(!connection
+            .commit_with_verification(None, |_| false)
+            .unwrap());
+
+        let mut connection = Connection::new(MockTransport::scripted::<_, &str, &str>([])).unwrap();
+        assert!(matches!(
+            connection.commit_with_verification(None, |_| true),
+            Err(Error::CapabilityMissing(_))
+        ));
+    }
+
+    #[test]
+    fn test_run_batch() {
+        let error = "<rpc-error><error-type>app</error-type><error-tag>invalid-value</error-tag><error-severity>error</error-severity></rpc-error>";
+        let script = [
+            ("<discard-changes/>", "<ok/>"),
+            ("<validate>", error),
+            ("<commit/>", "<ok/>"),
+        ];
+        let rpcs = || {
+            vec![
+                Rpc::new(RpcContent::DiscardChanges),
+                Rpc::new(RpcContent::Validate {
+                    source: Source {
+                        datastore: Datastore::Candidate,
+                    },
+                }),
+                Rpc::new(RpcContent::Commit),
+            ]
+        };
+
+        let mut connection = Connection::new(MockTransport::scripted(script)).unwrap();
+        let results = connection.run_batch(rpcs(), BatchMode::StopOnError);
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_ok());
+        assert!(matches!(results[1], Err(Error::Netconf(_))));
+
+        let mut connection = Connection::new(MockTransport::scripted(script)).unwrap();
+        let results = connection.run_batch(rpcs(), BatchMode::ContinueOnError);
+        assert_eq!(results.len(), 3);
+        assert!(results[1].is_err());
+        assert!(results[2].is_ok());
+    }
+
+    #[test]
+    fn test_transaction() {
+        let error = "<rpc-error><error-type>app</error-type><error-tag>invalid-value</error-tag><error-severity>error</error-severity></rpc-error>";
+        let mut transport = MockTransport::scripted([
+            ("<lock>", "<ok/>"),
+            ("<edit-config>", "<ok/>"),
+            ("<validate>", "<ok/>"),
+            ("<commit/>", "<ok/>"),
+            ("<unlock>", "<ok/>"),
+            ("<lock>", "<ok/>"),
+            ("<edit-config>", error),
+            ("<discard-changes/>", "<ok/>"),
+            ("<unlock>", "<ok/>"),
+        ]);
+        transport.set_capabilities(["urn:ietf:params:netconf:capability:validate:1.1"]);
+        let sent = transport.sent();
+        let mut connection = Connection::new(transport).unwrap();
+
+        let value = connection
+            .transaction(|tx| {
+                tx.edit_config("candidate", "<system/>")?;
+                Ok(42)
+            })
+            .unwrap();
+        assert_eq!(value, 42);
+
+        let result = connection.transaction(|tx| tx.edit_config("candidate", "<system/>"));
+        assert!(matches!(result, Err(Error::Netconf(_))));
+        // hello and nine rpcs
+        assert_eq!(sent.lock().unwrap().len(), 10);
+    }
+
+    #[test]
+    fn test_device_profile() {
+        struct Quirky;
+
+        impl DeviceProfile for Quirky {
+            fn name(&self) -> &str {
+                "quirky"
+            }
+
+            fn base_1_0_only(&self) -> bool {
+                true
+            }
+
+            fn fix_message(&self, message: &mut String) {
+                *message = message.replace("<okay/>", "<ok/>");
+            }
+        }
+
+        let transport = MockTransport::new(|_| Some("<okay/>".to_string()));
+        let sent = transport.sent();
+        let mut connection = Connection::new_with_profile(transport, Arc::new(Quirky)).unwrap();
+        assert!(!sent.lock().unwrap()[0].contains(BASE_1_1_CAPABILITY));
+        let reply = connection.get(None).unwrap();
+        assert!(reply.raw().contains("<ok/>"));
+
+        let mut transport = MockTransport::new(|_| None);
+        transport.set_capabilities([
+            "http://xml.juniper.net/netconf/junos/1.0",
+            "urn:ietf:params:xml:ns:netconf:capability:candidate:1.0",
+        ]);
+        let connection = Connection::new(transport).unwrap();
+        assert_eq!(connection.profile().map(|p| p.name()), Some("junos"));
+        assert!(connection.supports_candidate());
+    }
+
+    #[test]
+    fn test_url_backup_and_restore() {
+        let mut transport = MockTransport::scripted([
+            ("<url>sftp://10.0.0.1/r1.xml</url>", "<ok/>"),
+            ("<url>SFTP://10.0.0.1/r1.xml</url>", "<ok/>"),
+        ]);
+        transport.set_capabilities(["urn:ietf:params:netconf:capability:url:1.0?scheme=file,sftp"]);
+        let mut connection = Connection::new(transport).unwrap();
+        assert_eq!(connection.url_schemes(), vec!["file", "sftp"]);
+        connection
+            .backup_to_url("running", "sftp://10.0.0.1/r1.xml")
+            .unwrap();
+        connection
+            .restore_from_url("SFTP://10.0.0.1/r1.xml", "candidate")
+            .unwrap();
+        assert!(matches!(
+            connection.backup_to_url("running", "ftp://10.0.0.1/r1.xml"),
+            Err(Error::CapabilityMissing(_))
+        ));
+        assert!(matches!(
+            connection.restore_from_url("startup", "running"),
+            Err(Error::UnknownDatastore { .. })
+        ));
+    }
+
+    #[test]
+    fn test_confirm_in_new_session() {
+        let mut transport = MockTransport::scripted([
+            ("<persist-id>change-42</persist-id>", "<ok/>"),
+            ("<close-session/>", "<ok/>"),
+        ]);
+        transport.set_capabilities(["urn:ietf:params:netconf:capability:confirmed-commit:1.1"]);
+        let sent = transport.sent();
+        Connection::confirm_in_new_session(transport, "change-42").unwrap();
+        assert!(sent.lock().unwrap()[1].contains("<commit>"));
+
+        let mut connection = Connection::new(MockTransport::new(|_| None)).unwrap();
+        assert!(matches!(
+            connection.cancel_persisted("change-42"),
+            Err(Error::CapabilityMissing(_))
+        ));
+    }
+
+    #[test]
+    fn test_scheduled_rpc() {
+        let time = OffsetDateTime::from_unix_timestamp(1714600800).unwrap();
+        let commit = || Rpc::new(RpcContent::Commit).scheduled_at(time).unwrap();
+        let mut connection = Connection::new(MockTransport::new(|_| None)).unwrap();
+        assert!(matches!(
+            connection.execute(commit()),
+            Err(Error::CapabilityMissing(_))
+        ));
+
+        let mut transport = MockTransport::scripted([(
+            "<scheduled-time>2024-05-01T22:00:00Z</scheduled-time>",
+            "<ok/>",
+        )]);
+        transport.set_capabilities([TIME_CAPABILITY]);
+        let mut connection = Connection::new(transport).unwrap();
+        assert!(connection.supports_scheduling());
+        connection.execute(commit()).unwrap();
+    }
+
+    #[test]
+    fn test_save_config() {
+        let mut transport = MockTransport::scripted([("<startup/>", "<ok/>")]);
+        transport.set_capabilities(["urn:ietf:params:netconf:capability:startup:1.0"]);
+        let mut connection = Connection::new(transport).unwrap();
+        connection.save_config().unwrap();
+
+        let mut connection = Connection::new(MockTransport::new(|_| None)).unwrap();
+        assert!(matches!(
+            connection.save_config(),
+            Err(Error::CapabilityMissing(_))
+        ));
+
+        struct Vendor;
+
+        impl DeviceProfile for Vendor {
+            fn name(&self) -> &str {
+                "vendor"
+            }
+
+            fn save_config(&self) -> SaveConfig {
+                SaveConfig::Operation("<save-config/>".to_string())
+            }
+        }
+
+        let transport = MockTransport::scripted([("<save-config/>", "<ok/>")]);
+        let mut connection = Connection::new_with_profile(transport, Arc::new(Vendor)).unwrap();
+        connection.save_config().unwrap();
+    }
+
+    #[test]
+    fn test_get_config_to_writer() {
+        let transport = MockTransport::scripted([(
+            "<filter type=\"subtree\"><system/></filter>",
+            "<data><system><hostname>r1</hostname></system></data>",
+        )]);
+        let mut connection = Connection::new(transport).unwrap();
+        let mut backup = Vec::new();
+        let written = connection
+            .get_config_to_writer("running", Some(Filter::subtree("<system/>")), &mut backup)
+            .unwrap();
+        assert_eq!(backup, b"<system><hostname>r1</hostname></system>");
+        assert_eq!(written, backup.len() as u64);
+        assert!(connection.is_reusable());
+
+        connection.closed = true;
+        assert!(matches!(
+            connection.get_config_to_writer("running", None, &mut backup),
+            Err(Error::SessionClosed)
+        ));
+    }
+
+    #[test]
+    fn test_streamed_reply_intercepted() {
+        struct Record(Arc<std::sync::Mutex<Vec<String>>>);
+
+        impl RpcInterceptor for Record {
+            fn on_send(&mut self, rpc: &mut String) {
+                self.0.lock().unwrap().push(rpc.clone());
+            }
+
+            fn on_receive(&mut self, message: &mut String) {
+                self.0.lock().unwrap().push(message.clone());
+            }
+        }
+
+        let transport = MockTransport::scripted([("<get-config>", "<data><system/></data>")]);
+        let recorded = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut connection = Connection::new(transport).unwrap();
+        connection.add_interceptor(Record(recorded.clone()));
+        let nodes: Vec<String> = connection
+            .get_config_stream("running")
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(nodes, ["<system/>"]);
+
+        let recorded = recorded.lock().unwrap();
+        assert_eq!(recorded.len(), 2);
+        assert!(recorded[0].contains("<get-config>"));
+        assert!(recorded[1].contains("<data><system/></data></rpc-reply>"));
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_tracing_spans() {
+        use std::collections::BTreeMap;
+        use std::fmt::Debug;
+        use std::sync::Mutex;
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata, Subscriber};
+
+        #[derive(Default)]
+        struct Fields(BTreeMap<String, String>);
+
+        impl Visit for Fields {
+            fn record_str(&mut self, field: &Field, value: &str) {
+                self.0.insert(field.name().to_string(), value.to_string());
+            }
+
+            fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+                self.0
+                    .insert(field.name().to_string(), format!("{:?}", value));
+            }
+        }
+
+        /// Spans with their name and fields, and events with the name of current span
+        #[derive(Default)]
+        struct Capture {
+            spans: Mutex<Vec<(String, Fields)>>,
+            entered: Mutex<Vec<u64>>,
+            events: Mutex<Vec<(String, String)>>,
+        }
+
+        impl Subscriber for &'static Capture {
+            fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, span: &Attributes<'_>) -> Id {
+                let mut fields = Fields::default();
+                span.record(&mut fields);
+                let mut spans = self.spans.lock().unwrap();
+                spans.push((span.metadata().name().to_string(), fields));
+                Id::from_u64(spans.len() as u64)
+            }
+
+            fn record(&self, span: &Id, values: &Record<'_>) {
+                let mut spans = self.spans.lock().unwrap();
+                values.record(&mut spans[span.into_u64() as usize - 1].1);
+            }
+
+            fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+            fn event(&self, event: &Event<'_>) {
+                let mut fields = Fields::default();
+                event.record(&mut fields);
+                let span = match self.entered.lock().unwrap().last() {
+                    Some(id) => self.spans.lock().unwrap()[*id as usize - 1].0.clone(),
+                    None => String::new(),
+                };
+                let message = fields.0.remove("message").unwrap_or_default();
+                self.events.lock().unwrap().push((span, message));
+            }
+
+            fn enter(&self, span: &Id) {
+                self.entered.lock().unwrap().push(span.into_u64());
+            }
+
+            fn exit(&self, _span: &Id) {
+                self.entered.lock().unwrap().pop();
+            }
+        }
+
+        let capture: &'static Capture = Box::leak(Box::default());
+        tracing::subscriber::with_default(capture, || {
+            let transport = MockTransport::scripted([("<get-config>", "<data/>")]);
+            let mut connection = Connection::new(transport).unwrap();
+            connection.set_host("r1");
+            connection.get_config("running").unwrap();
+        });
+
+        let spans = capture.spans.lock().unwrap();
+        let (name, session) = &spans[0];
+        assert_eq!(name, "netconf");
+        assert_eq!(session.0.get("host").map(String::as_str), Some("r1"));
+        assert_eq!(session.0.get("session_id").map(String::as_str), Some("1"));
+        let (name, rpc) = &spans[1];
+        assert_eq!(name, "rpc");
+        assert_eq!(
+            rpc.0.get("operation").map(String::as_str),
+            Some("get-config")
+        );
+        assert!(rpc.0.contains_key("message_id"));
+        let events = capture.events.lock().unwrap();
+        assert!(
+            events
+                .iter()
+                .any(|(span, message)| span == "rpc" && message.starts_with("Reply:")),
+            "{:?}",
+            events
+        );
+    }
+
+    #[test]
+    fn test_retry_policy() {
+        let error = "<rpc-error><error-type>protocol</error-type><error-tag>lock-denied</error-tag><error-severity>error</error-severity></rpc-error>";
+        let mut attempts = 0;
+        let transport = MockTransport::new(move |rpc| {
+            assert!(rpc.contains("<lock>"));
+            attempts += 1;
+            match attempts {
+                1 | 2 => Some(error.to_string()),
+                _ => Some("<ok/>".to_string()),
+            }
+        });
+        let sent = transport.sent();
+        let mut connection = Connection::new(transport).unwrap();
+        connection.lock("candidate").unwrap_err();
+
+        connection.set_retry_policy(RetryPolicy::new(3).delay(Duration::from_millis(1)));
+        connection.lock("candidate").unwrap();
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 4);
+        assert_ne!(sent[2], sent[3], "retry has new message-id");
+    }
+
+    #[test]
+    fn test_retry_policy_broken_session() {
+        let transport = MockTransport::new(|_| None);
+        let sent = transport.sent();
+        let mut connection = Connection::new(transport).unwrap();
+        connection.set_retry_policy(
+            RetryPolicy::new(3)
+                .delay(Duration::from_millis(1))
+                .retry_if(|_| true),
+        );
+        assert!(matches!(connection.commit(), Err(Error::Timeout)));
+        assert!(connection.is_broken());
+        assert_eq!(sent.lock().unwrap().len(), 2, "commit is not sent again");
+        assert!(connection.in_flight.is_empty());
+    }
+
+    #[test]
+    fn test_metrics_observer() {
+        type Recorded = std::sync::Arc<std::sync::Mutex<Vec<(String, Outcome, usize)>>>;
+        struct Record(Recorded);
+
+        impl MetricsObserver for Record {
+            fn on_rpc(&mut self, metrics: &RpcMetrics<'_>) {
+                assert!(metrics.bytes_sent > 0);
+                self.0.lock().unwrap().push((
+                    metrics.operation.to_string(),
+                    metrics.outcome,
+                    metrics.bytes_received,
+                ));
+            }
+        }
+
+        let error = "<rpc-error><error-type>app</error-type><error-tag>invalid-value</error-tag><error-severity>error</error-severity></rpc-error>";
+        let transport = MockTransport::new(move |rpc| {
+            if rpc.contains("<commit/>") {
+                Some(error.to_string())
+            } else if rpc.contains("<get/>") {
+                Some("<data/>".to_string())
+            } else {
+                None
+            }
+        });
+        let recorded = Recorded::default();
+        let mut connection = Connection::new(transport).unwrap();
+        connection.set_metrics_observer(Record(recorded.clone()));
+        connection.get(None).unwrap();
+        connection.commit().unwrap_err();
+        connection.discard_changes().unwrap_err();
+
+        let recorded = recorded.lock().unwrap();
+        assert_eq!(recorded[0].0, "get");
+        assert_eq!(recorded[0].1, Outcome::Ok);
+        assert!(recorded[0].2 > 0);
+        assert_eq!(recorded[1].1, Outcome::RpcError);
+        assert_eq!(
+            recorded[2],
+            ("discard-changes".to_string(), Outcome::Timeout, 0)
+        );
+    }
+
+    #[test]
+    fn test_lock_guard_unlocks_on_drop() {
+        let sent = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        struct Record(std::sync::Arc<std::sync::Mutex<Vec<String>>>);
+
+        impl RpcInterceptor for Record {
+            fn on_send(&mut self, rpc: &mut String) {
+                self.0.lock().unwrap().push(rpc.clone());
+            }
+        }
+
+        let mut connection = Connection::new(ReversingTransport::default()).unwrap();
+        connection.add_interceptor(Record(sent.clone()));
+        {
+            let mut guard = connection.lock_guarded("candidate").unwrap();
+            guard.get_config("candidate").unwrap();
+        }
+
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 3);
+        assert!(sent[0].contains("<lock>"));
+        assert!(sent[2].contains("<unlock>"));
     }
 }
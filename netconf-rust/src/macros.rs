@@ -0,0 +1,35 @@
+//! Logging macros of the crate, emitting `tracing` events within the session and rpc
+//! spans when the `tracing` feature is enabled and `log` records otherwise. Events are
+//! also emitted as `log` records while no `tracing` subscriber is set.
+
+/// Event at `$level`, `target:` is recorded as `host` field of tracing events
+macro_rules! event {
+    ($level:ident, target: $target:expr, $($arg:tt)+) => {{
+        #[cfg(feature = "tracing")]
+        tracing::$level!(host = %$target, $($arg)+);
+        #[cfg(not(feature = "tracing"))]
+        log::$level!(target: $target, $($arg)+);
+    }};
+    ($level:ident, $($arg:tt)+) => {{
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($arg)+);
+        #[cfg(not(feature = "tracing"))]
+        log::$level!($($arg)+);
+    }};
+}
+
+macro_rules! error {
+    ($($arg:tt)+) => { event!(error, $($arg)+) };
+}
+
+macro_rules! warn {
+    ($($arg:tt)+) => { event!(warn, $($arg)+) };
+}
+
+macro_rules! debug {
+    ($($arg:tt)+) => { event!(debug, $($arg)+) };
+}
+
+macro_rules! trace {
+    ($($arg:tt)+) => { event!(trace, $($arg)+) };
+}
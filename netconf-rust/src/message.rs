@@ -1,13 +1,35 @@
 #![allow(dead_code)]
 use crate::error;
+use crate::events::NetconfEvent;
+use crate::nmda::GetData;
+use crate::subscription::{EstablishSubscription, ModifySubscription, SubscriptionId};
+use quick_xml::de::from_str;
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesEnd, BytesStart, Event};
+use quick_xml::name::{Namespace, ResolveResult};
 use quick_xml::se::Serializer;
+use quick_xml::{NsReader, Reader, Writer};
+use serde::de::{DeserializeOwned, IgnoredAny};
 use serde::Serialize;
 use serde_derive::{Deserialize, Serialize};
+use std::cell::OnceCell;
+use std::collections::BTreeMap;
 use std::fmt;
 use std::fmt::Display;
 use std::str::FromStr;
+use std::time::Duration;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
 use uuid::Uuid;
 
+#[cfg(feature = "json")]
+pub use crate::json::to_json;
+
+const BASE_NAMESPACE: &str = "urn:ietf:params:xml:ns:netconf:base:1.0";
+const NOTIFICATION_NAMESPACE: &str = "urn:ietf:params:xml:ns:netconf:notification:1.0";
+const TIME_NAMESPACE: &str = "urn:ietf:params:xml:ns:yang:ietf-netconf-time";
+const WITH_DEFAULTS_NAMESPACE: &str = "urn:ietf:params:xml:ns:yang:ietf-netconf-with-defaults";
+
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(rename(serialize = "hello"))]
 pub struct Hello {
@@ -32,6 +54,29 @@ impl Hello {
         }
     }
 
+    /// Hello advertising only base:1.0, so that end-of-message framing is kept
+    pub fn base_1_0() -> Hello {
+        Hello {
+            xmlns: BASE_NAMESPACE.to_string(),
+            session_id: None,
+            capabilities: Capabilities {
+                capability: vec!["urn:ietf:params:netconf:base:1.0".to_string()],
+            },
+        }
+    }
+
+    /// Server hello with `session_id` and `capabilities` in addition to base ones
+    #[cfg(feature = "server")]
+    pub(crate) fn server(session_id: u64, capabilities: &[String]) -> Hello {
+        let mut hello = Hello::new();
+        hello.session_id = Some(session_id);
+        hello
+            .capabilities
+            .capability
+            .extend(capabilities.iter().cloned());
+        hello
+    }
+
     pub fn capabilities(&self) -> Vec<String> {
         self.capabilities
             .capability
@@ -49,15 +94,153 @@ impl Hello {
     }
 }
 
+impl Default for Hello {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Display for Hello {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut buffer = String::with_capacity(206);
-        let ser = Serializer::new(&mut buffer);
+        let mut ser = Serializer::new(&mut buffer);
+        ser.indent(' ', 2);
         self.serialize(ser).unwrap();
         write!(f, "{}", buffer)
     }
 }
 
+/// Returns local name and message-id attribute of the root element
+pub(crate) fn root_element(xml: &str) -> Option<(String, Option<String>)> {
+    let mut reader = Reader::from_str(xml);
+    loop {
+        match reader.read_event().ok()? {
+            Event::Start(root) | Event::Empty(root) => {
+                let name = String::from_utf8_lossy(root.local_name().as_ref()).to_string();
+                let message_id = match root.try_get_attribute("message-id").ok()? {
+                    Some(id) => Some(id.unescape_value().ok()?.to_string()),
+                    None => None,
+                };
+                return Some((name, message_id));
+            }
+            Event::Eof => return None,
+            _ => {}
+        }
+    }
+}
+
+/// Removes prefixes of NETCONF base and notification elements, e.g. `<nc:rpc-reply>`
+/// of Huawei and older IOS-XE, which fail deserialization matching names literally.
+/// `None` if the root element is not prefixed, there is nothing to strip or `xml` is
+/// not well-formed.
+pub(crate) fn strip_netconf_prefixes(xml: &str) -> Option<String> {
+    if !has_prefixed_root(xml) {
+        return None;
+    }
+    let is_netconf = |namespace: &ResolveResult| {
+        [BASE_NAMESPACE, NOTIFICATION_NAMESPACE]
+            .iter()
+            .any(|ns| *namespace == ResolveResult::Bound(Namespace(ns.as_bytes())))
+    };
+    let mut reader = NsReader::from_str(xml);
+    let mut writer = Writer::new(Vec::with_capacity(xml.len()));
+    let mut stripped = false;
+    loop {
+        let (namespace, event) = reader.read_resolved_event().ok()?;
+        let netconf = is_netconf(&namespace);
+        let event = match event {
+            Event::Eof => break,
+            Event::Start(element) if netconf && element.name().prefix().is_some() => {
+                stripped = true;
+                Event::Start(unprefixed(&element)?)
+            }
+            Event::Empty(element) if netconf && element.name().prefix().is_some() => {
+                stripped = true;
+                Event::Empty(unprefixed(&element)?)
+            }
+            Event::End(element) if netconf && element.name().prefix().is_some() => {
+                let name = std::str::from_utf8(element.local_name().into_inner()).ok()?;
+                Event::End(BytesEnd::new(name.to_string()))
+            }
+            event => event,
+        };
+        writer.write_event(event).ok()?;
+    }
+    if !stripped {
+        return None;
+    }
+    String::from_utf8(writer.into_inner()).ok()
+}
+
+/// Only the root is checked, so replies are re-serialized only from devices prefixing
+/// NETCONF elements, not whenever the data has prefixed elements
+fn has_prefixed_root(xml: &str) -> bool {
+    let mut reader = Reader::from_str(xml);
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(root)) | Ok(Event::Empty(root)) => {
+                return root.name().prefix().is_some()
+            }
+            Ok(Event::Eof) | Err(_) => return false,
+            _ => {}
+        }
+    }
+}
+
+fn unprefixed(element: &BytesStart) -> Option<BytesStart<'static>> {
+    let name = std::str::from_utf8(element.local_name().into_inner()).ok()?;
+    let attributes: Vec<_> = element.attributes().collect::<Result<_, _>>().ok()?;
+    Some(BytesStart::new(name.to_string()).with_attributes(attributes))
+}
+
+/// Re-indents `xml` with two spaces per level, e.g. device replies with inconsistent whitespace.
+/// Input that is not well-formed XML is returned trimmed but otherwise unchanged.
+pub fn prettify(xml: &str) -> String {
+    Pretty::new(xml).to_string()
+}
+
+/// Re-indented XML for display, see [`prettify`]
+#[derive(Debug, Clone, Copy)]
+pub struct Pretty<'a> {
+    xml: &'a str,
+    indent: usize,
+}
+
+impl<'a> Pretty<'a> {
+    pub fn new(xml: &'a str) -> Pretty<'a> {
+        Pretty { xml, indent: 2 }
+    }
+
+    /// Spaces per nesting level, 0 puts all elements on their own line without indentation
+    pub fn indent(mut self, indent: usize) -> Pretty<'a> {
+        self.indent = indent;
+        self
+    }
+
+    fn format(&self) -> Result<String, quick_xml::Error> {
+        let mut reader = Reader::from_str(self.xml);
+        reader.config_mut().trim_text(true);
+        let mut writer =
+            Writer::new_with_indent(Vec::with_capacity(self.xml.len()), b' ', self.indent);
+        loop {
+            match reader.read_event()? {
+                Event::Eof => break,
+                event => writer.write_event(event)?,
+            }
+        }
+        Ok(String::from_utf8_lossy(&writer.into_inner()).into_owned())
+    }
+}
+
+impl Display for Pretty<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.format() {
+            Ok(pretty) => f.write_str(&pretty),
+            Err(_) => f.write_str(self.xml.trim()),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Capabilities {
     capability: Vec<String>,
@@ -70,6 +253,12 @@ pub struct Rpc {
     xmlns: String,
     #[serde(rename = "@message-id")]
     message_id: String,
+    /// Added to the operation by [`Rpc::to_xml`]
+    #[serde(skip)]
+    scheduled_time: Option<String>,
+    /// Extra attributes of the envelope, added by [`Rpc::to_xml`]
+    #[serde(skip)]
+    attributes: Vec<(String, String)>,
     #[serde(rename = "$value")]
     content: RpcContent,
 }
@@ -79,30 +268,282 @@ impl Rpc {
         Rpc {
             xmlns: "urn:ietf:params:xml:ns:netconf:base:1.0".to_string(),
             message_id: Uuid::new_v4().to_string(),
+            scheduled_time: None,
+            attributes: Vec::new(),
             content,
         }
     }
+
+    /// Rpc of user-defined operation, e.g. a vendor rpc, serialized with its serde
+    /// element name. Namespace is set with a `@xmlns` field.
+    pub fn from_operation<T: Serialize>(operation: &T) -> Result<Rpc, error::Error> {
+        let xml = quick_xml::se::to_string(operation)?;
+        Ok(Rpc::new(RpcContent::Raw(RawXml(xml))))
+    }
+
+    /// Schedules operation to be executed by the server at `time` instead of immediately,
+    /// requires `:time` capability, see [RFC7758](https://tools.ietf.org/html/rfc7758)
+    pub fn scheduled_at(mut self, time: OffsetDateTime) -> Result<Rpc, error::Error> {
+        self.scheduled_time = Some(time.format(&Rfc3339)?);
+        Ok(self)
+    }
+
+    pub fn scheduled_time(&self) -> Option<&str> {
+        self.scheduled_time.as_deref()
+    }
+
+    /// Adds attribute to the `<rpc>` element, e.g. `format="text"` for Junos
+    pub fn attribute(mut self, name: &str, value: &str) -> Rpc {
+        self.attributes.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Declares namespace `prefix` on the `<rpc>` element for use in the operation
+    pub fn namespace(self, prefix: &str, uri: &str) -> Rpc {
+        self.attribute(&format!("xmlns:{}", prefix), uri)
+    }
+
+    pub fn attributes(&self) -> &[(String, String)] {
+        &self.attributes
+    }
+
+    pub fn message_id(&self) -> &str {
+        &self.message_id
+    }
+
+    pub fn content(&self) -> &RpcContent {
+        &self.content
+    }
+
+    pub(crate) fn renew_message_id(&mut self) {
+        self.message_id = Uuid::new_v4().to_string();
+    }
+}
+
+/// Indentation of serialized messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indent {
+    /// No whitespace between elements, smallest payload on the wire
+    Compact,
+    /// Each element on its own line, indented with given number of spaces per level
+    Spaces(usize),
+}
+
+impl Default for Indent {
+    fn default() -> Self {
+        Indent::Spaces(2)
+    }
+}
+
+impl Indent {
+    fn apply(self, serializer: &mut Serializer<String>) {
+        if let Indent::Spaces(width) = self {
+            serializer.indent(' ', width);
+        }
+    }
 }
 
-impl Display for Rpc {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl Rpc {
+    /// Serializes rpc with `indent`, [`Display`] uses the default two spaces
+    pub fn to_xml(&self, indent: Indent) -> String {
         let mut buffer = String::with_capacity(256);
-        let ser = Serializer::new(&mut buffer);
+        let mut ser = Serializer::new(&mut buffer);
+        indent.apply(&mut ser);
         self.serialize(ser).unwrap();
-        write!(f, "{}", buffer)
+        if !self.attributes.is_empty() {
+            let message_id = format!(r#"message-id="{}""#, escape(&self.message_id));
+            let attributes: String = self
+                .attributes
+                .iter()
+                .map(|(name, value)| format!(r#" {}="{}""#, name, escape(value)))
+                .collect();
+            buffer = buffer.replacen(&message_id, &(message_id.clone() + &attributes), 1);
+        }
+        if let Some(raw) = self.content.raw_xml() {
+            buffer = buffer.replacen(RAW_XML_MARKER, raw, 1);
+        }
+        if let Some(time) = &self.scheduled_time {
+            let scheduling = format!(
+                r#"<time xmlns="{}"><scheduled-time>{}</scheduled-time></time>"#,
+                TIME_NAMESPACE, time
+            );
+            buffer = insert_into_operation(&buffer, &scheduling).unwrap_or(buffer);
+        }
+        buffer
+    }
+}
+
+/// Inserts `element` as the first child of the operation element of serialized rpc,
+/// `None` if the rpc has no operation element
+fn insert_into_operation(rpc: &str, element: &str) -> Option<String> {
+    let operation_start = rpc.find('>')? + 1;
+    let tag_start = operation_start + rpc[operation_start..].find('<')?;
+    let tag_end = tag_start + rpc[tag_start..].find('>')?;
+    let tag = &rpc[tag_start + 1..tag_end];
+    if let Some(tag) = tag.strip_suffix('/') {
+        let name = tag.split_whitespace().next()?;
+        return Some(format!(
+            "{}<{}>{}</{}>{}",
+            &rpc[..tag_start],
+            tag,
+            element,
+            name,
+            &rpc[tag_end + 1..]
+        ));
+    }
+    Some(format!(
+        "{}{}{}",
+        &rpc[..tag_end + 1],
+        element,
+        &rpc[tag_end + 1..]
+    ))
+}
+
+impl Display for Rpc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_xml(Indent::default()))
+    }
+}
+
+const RAW_XML_MARKER: &str = "__NETCONF_RAW_XML__";
+
+/// XML fragment embedded as-is into serialized rpc, e.g. edit-config payload
+#[derive(Debug)]
+pub struct RawXml(pub String);
+
+impl Serialize for RawXml {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(RAW_XML_MARKER)
     }
 }
 
 #[derive(Debug, Serialize)]
-#[serde(rename_all = "kebab-case")]
+#[serde(rename_all = "kebab-case", rename_all_fields = "kebab-case")]
 pub enum RpcContent {
     CloseSession,
-    KillSession,
+    KillSession {
+        session_id: u64,
+    },
+    EditConfig {
+        target: Target,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        default_operation: Option<DefaultOperation>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        test_option: Option<TestOption>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error_option: Option<ErrorOption>,
+        config: RawXml,
+    },
+    Validate {
+        source: Source,
+    },
+    CopyConfig {
+        target: Target,
+        source: Source,
+    },
+    /// Arbitrary operation element, e.g. vendor specific rpc
+    #[serde(rename = "$text")]
+    Raw(RawXml),
+    Commit,
+    #[serde(rename = "commit")]
+    CommitWith(CommitOptions),
+    DiscardChanges,
+    CancelCommit,
+    /// Cancels persistent confirmed commit, possibly from another session
+    #[serde(rename = "cancel-commit")]
+    CancelPersisted {
+        persist_id: String,
+    },
+    Get {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        filter: Option<Filter>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        with_defaults: Option<WithDefaults>,
+    },
     GetConfig {
         source: Source,
         #[serde(rename = "filter", skip_serializing_if = "Option::is_none")]
         filter: Option<Filter>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        with_defaults: Option<WithDefaults>,
     },
+    Lock {
+        target: Target,
+    },
+    Unlock {
+        target: Target,
+    },
+    CreateSubscription {
+        #[serde(rename = "@xmlns")]
+        xmlns: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        stream: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        filter: Option<Filter>,
+        #[serde(rename = "startTime", skip_serializing_if = "Option::is_none")]
+        start_time: Option<String>,
+        #[serde(rename = "stopTime", skip_serializing_if = "Option::is_none")]
+        stop_time: Option<String>,
+    },
+    EstablishSubscription(EstablishSubscription),
+    ModifySubscription(ModifySubscription),
+    DeleteSubscription(SubscriptionId),
+    ResyncSubscription(SubscriptionId),
+    GetData(GetData),
+}
+
+impl RpcContent {
+    /// Raw XML fragment of the operation, only one per rpc is supported
+    fn raw_xml(&self) -> Option<&str> {
+        match self {
+            RpcContent::EditConfig { config, .. } | RpcContent::Raw(config) => Some(&config.0),
+            RpcContent::Get { filter, .. }
+            | RpcContent::GetConfig { filter, .. }
+            | RpcContent::CreateSubscription { filter, .. } => filter
+                .as_ref()
+                .and_then(|filter| filter.subtree.as_ref())
+                .map(|subtree| subtree.0.as_str()),
+            RpcContent::GetData(get_data) => get_data.raw_xml(),
+            _ => None,
+        }
+    }
+
+    /// Create-subscription for `stream`, or the default NETCONF stream if `None`
+    pub fn create_subscription(stream: Option<&str>) -> RpcContent {
+        RpcContent::CreateSubscription {
+            xmlns: NOTIFICATION_NAMESPACE.to_string(),
+            stream: stream.map(str::to_string),
+            filter: None,
+            start_time: None,
+            stop_time: None,
+        }
+    }
+
+    /// Create-subscription replaying notifications since `start_time` and ending at `stop_time`
+    pub fn create_subscription_between(
+        stream: Option<&str>,
+        start_time: Option<OffsetDateTime>,
+        stop_time: Option<OffsetDateTime>,
+    ) -> Result<RpcContent, error::Error> {
+        Ok(RpcContent::CreateSubscription {
+            xmlns: NOTIFICATION_NAMESPACE.to_string(),
+            stream: stream.map(str::to_string),
+            filter: None,
+            start_time: start_time.map(|t| t.format(&Rfc3339)).transpose()?,
+            stop_time: stop_time.map(|t| t.format(&Rfc3339)).transpose()?,
+        })
+    }
+
+    pub fn delete_subscription(id: u32) -> RpcContent {
+        RpcContent::DeleteSubscription(SubscriptionId::subscribed_notifications(id))
+    }
+
+    pub fn resync_subscription(id: u32) -> RpcContent {
+        RpcContent::ResyncSubscription(SubscriptionId::yang_push(id))
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -111,6 +552,129 @@ pub struct Source {
     pub datastore: Datastore,
 }
 
+/// Reporting mode of default values, see [RFC6243](https://tools.ietf.org/html/rfc6243#section-3)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WithDefaultsValue {
+    ReportAll,
+    ReportAllTagged,
+    Trim,
+    Explicit,
+}
+
+impl WithDefaultsValue {
+    /// Parses mode as named in `:with-defaults` capability parameters
+    pub(crate) fn from_name(name: &str) -> Option<WithDefaultsValue> {
+        match name {
+            "report-all" => Some(WithDefaultsValue::ReportAll),
+            "report-all-tagged" => Some(WithDefaultsValue::ReportAllTagged),
+            "trim" => Some(WithDefaultsValue::Trim),
+            "explicit" => Some(WithDefaultsValue::Explicit),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WithDefaultsValue::ReportAll => "report-all",
+            WithDefaultsValue::ReportAllTagged => "report-all-tagged",
+            WithDefaultsValue::Trim => "trim",
+            WithDefaultsValue::Explicit => "explicit",
+        }
+    }
+}
+
+/// With-defaults parameter of get and get-config
+#[derive(Debug, Serialize)]
+pub struct WithDefaults {
+    #[serde(rename = "@xmlns")]
+    xmlns: String,
+    #[serde(rename = "$text")]
+    pub value: WithDefaultsValue,
+}
+
+impl From<WithDefaultsValue> for WithDefaults {
+    fn from(value: WithDefaultsValue) -> Self {
+        WithDefaults {
+            xmlns: WITH_DEFAULTS_NAMESPACE.to_string(),
+            value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DefaultOperation {
+    Merge,
+    Replace,
+    None,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TestOption {
+    TestThenSet,
+    Set,
+    TestOnly,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorOption {
+    StopOnError,
+    ContinueOnError,
+    RollbackOnError,
+}
+
+/// Confirmed commit parameters, see [RFC6241](https://tools.ietf.org/html/rfc6241#section-8.4)
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CommitOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    confirmed: Option<()>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    confirm_timeout: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    persist: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    persist_id: Option<String>,
+}
+
+impl CommitOptions {
+    /// Commit reverted unless confirmed within `timeout`, server default is 600 seconds
+    pub fn confirmed(timeout: Option<Duration>) -> CommitOptions {
+        CommitOptions {
+            confirmed: Some(()),
+            confirm_timeout: timeout.map(|timeout| timeout.as_secs()),
+            ..Default::default()
+        }
+    }
+
+    /// Confirms commit made persistent with `persist_id` in another session
+    pub fn confirm_persisted(persist_id: &str) -> CommitOptions {
+        CommitOptions::default().persist_id(persist_id)
+    }
+
+    /// Keeps confirmed commit pending after the session is closed, it is then
+    /// confirmed or extended with `id` as persist-id
+    pub fn persist(mut self, id: &str) -> CommitOptions {
+        self.persist = Some(id.to_string());
+        self
+    }
+
+    /// Identifies earlier persistent confirmed commit being confirmed or extended
+    pub fn persist_id(mut self, id: &str) -> CommitOptions {
+        self.persist_id = Some(id.to_string());
+        self
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Target {
+    #[serde(rename = "$value")]
+    pub datastore: Datastore,
+}
+
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Datastore {
@@ -133,15 +697,18 @@ impl FromStr for Datastore {
                 if datastore.starts_with("http")
                     || datastore.starts_with("file")
                     || datastore.starts_with("ftp")
+                    || datastore.starts_with("sftp")
+                    || datastore.starts_with("scp")
                 {
-                    Ok(Datastore::Url(datastore))
+                    // Only the scheme is case-insensitive
+                    Ok(Datastore::Url(s.to_string()))
                 } else {
                     Err(error::Error::UnknownDatastore {
                         expected: vec![
                             "running".to_string(),
                             "candidate".to_string(),
                             "startup".to_string(),
-                            "ftp|http|file".to_string(),
+                            "ftp|sftp|scp|http|file".to_string(),
                         ],
                         unknown: datastore,
                     })
@@ -155,7 +722,92 @@ impl FromStr for Datastore {
 pub struct Filter {
     #[serde(rename = "@type")]
     filter_type: String,
-    filter: String,
+    #[serde(rename = "@select", skip_serializing_if = "Option::is_none")]
+    select: Option<String>,
+    #[serde(flatten)]
+    namespaces: BTreeMap<String, String>,
+    #[serde(rename = "$value", skip_serializing_if = "Option::is_none")]
+    subtree: Option<RawXml>,
+}
+
+impl Filter {
+    /// Subtree filter selecting nodes matching `xml`
+    pub fn subtree(xml: &str) -> Filter {
+        Filter {
+            filter_type: "subtree".to_string(),
+            select: None,
+            namespaces: BTreeMap::new(),
+            subtree: Some(RawXml(xml.to_string())),
+        }
+    }
+
+    /// XPath filter selecting nodes matching `select` expression
+    pub fn xpath(select: &str) -> Filter {
+        Filter {
+            filter_type: "xpath".to_string(),
+            select: Some(select.to_string()),
+            namespaces: BTreeMap::new(),
+            subtree: None,
+        }
+    }
+
+    /// Declares namespace `prefix` used in the XPath expression
+    pub fn namespace(mut self, prefix: &str, uri: &str) -> Filter {
+        self.namespaces
+            .insert(format!("@xmlns:{}", prefix), uri.to_string());
+        self
+    }
+
+    /// Declares namespaces of prefixes in the XPath expression not declared with
+    /// [`Filter::namespace`], e.g. by [`DeviceProfile::namespace`](crate::profile::DeviceProfile::namespace)
+    pub fn resolve_prefixes(mut self, namespace: impl Fn(&str) -> Option<String>) -> Filter {
+        let prefixes = self
+            .select
+            .as_deref()
+            .map(xpath_prefixes)
+            .unwrap_or_default();
+        for prefix in prefixes {
+            let key = format!("@xmlns:{}", prefix);
+            if self.namespaces.contains_key(&key) {
+                continue;
+            }
+            if let Some(uri) = namespace(&prefix) {
+                self.namespaces.insert(key, uri);
+            }
+        }
+        self
+    }
+}
+
+/// Prefixes of names in XPath expression, skipping string literals and axes like `child::`
+fn xpath_prefixes(select: &str) -> Vec<String> {
+    let mut prefixes = Vec::new();
+    let mut name = String::new();
+    let mut quote = None;
+    let mut chars = select.chars().peekable();
+    while let Some(c) = chars.next() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '\'' || c == '"' => {
+                quote = Some(c);
+                name.clear();
+            }
+            None if c.is_alphanumeric() || matches!(c, '_' | '-' | '.') => name.push(c),
+            None if c == ':' && chars.peek() == Some(&':') => {
+                chars.next();
+                name.clear();
+            }
+            None => {
+                let starts_name = name.starts_with(|c: char| c.is_alphabetic() || c == '_');
+                if c == ':' && starts_name && !prefixes.contains(&name) {
+                    prefixes.push(name.clone());
+                }
+                name.clear();
+            }
+        }
+    }
+    prefixes
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -170,57 +822,275 @@ pub struct RpcReply {
 }
 
 impl RpcReply {
+    pub(crate) fn new(message_id: String, rpc_error: Vec<Error>) -> RpcReply {
+        RpcReply {
+            message_id,
+            rpc_error,
+            ok: None,
+        }
+    }
+
+    pub fn message_id(&self) -> &str {
+        &self.message_id
+    }
+
+    pub fn errors(&self) -> &[Error] {
+        &self.rpc_error
+    }
+
+    /// Returns true if reply contains any `<rpc-error>` with severity `error`
     pub fn has_errors(&self) -> bool {
-        !self.rpc_error.is_empty()
+        self.rpc_error
+            .iter()
+            .any(|error| error.error_severity == ErrorSeverity::Error)
+    }
+
+    /// Returns true if reply contains any `<rpc-error>` with severity `warning`
+    pub fn has_warnings(&self) -> bool {
+        self.warnings().next().is_some()
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &Error> {
+        self.rpc_error
+            .iter()
+            .filter(|error| error.error_severity == ErrorSeverity::Warning)
     }
 }
 
-impl Display for RpcReply {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl RpcReply {
+    /// Serializes reply with `indent`, [`Display`] uses the default two spaces
+    pub fn to_xml(&self, indent: Indent) -> String {
         let mut buffer = String::new();
-        let ser = Serializer::new(&mut buffer);
+        let mut ser = Serializer::new(&mut buffer);
+        indent.apply(&mut ser);
         self.serialize(ser).unwrap();
-        write!(f, "{}", buffer)
+        buffer
+    }
+}
+
+impl Display for RpcReply {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_xml(Indent::default()))
     }
 }
 
 impl std::error::Error for RpcReply {}
 
-#[derive(Debug, Deserialize, Serialize)]
-#[serde(rename = "rpc-error", rename_all = "kebab-case")]
-pub struct Error {
-    error_severity: ErrorSeverity,
-    error_type: ErrorType,
-    error_tag: ErrorTag,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    error_app_tag: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    error_path: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    error_message: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    error_info: Option<ErrorInfo>,
+/// Event notification, see [RFC5277](https://tools.ietf.org/html/rfc5277#section-4)
+#[derive(Debug, Deserialize)]
+pub struct Notification {
+    #[serde(rename = "eventTime")]
+    event_time: String,
+    #[serde(rename = "push-update")]
+    push_update: Option<PushUpdate>,
+    #[serde(rename = "push-change-update")]
+    push_change_update: Option<PushUpdate>,
+    #[serde(rename = "notificationComplete")]
+    notification_complete: Option<IgnoredAny>,
+    #[serde(skip)]
+    raw: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-#[serde(rename_all = "kebab-case")]
-enum ErrorType {
-    Transport,
-    Rpc,
-    Protocol,
-    App,
+#[derive(Debug, Deserialize)]
+struct PushUpdate {
+    id: u32,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-#[serde(rename_all = "kebab-case")]
-enum ErrorSeverity {
-    Error,
-    Warning,
+impl Notification {
+    pub fn event_time(&self) -> &str {
+        &self.event_time
+    }
+
+    /// Subscription id of YANG Push `push-update` or `push-change-update` notification
+    pub fn subscription_id(&self) -> Option<u32> {
+        self.push_update
+            .as_ref()
+            .or(self.push_change_update.as_ref())
+            .map(|update| update.id)
+    }
+
+    /// Typed base notification of the NETCONF stream, `None` for other events
+    pub fn netconf_event(&self) -> Result<Option<NetconfEvent>, error::Error> {
+        NetconfEvent::from_notification(&self.raw)
+    }
+
+    /// `notificationComplete` sent after the stop time of subscription
+    pub fn is_complete(&self) -> bool {
+        self.notification_complete.is_some()
+    }
+
+    /// Raw XML of the whole notification
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+}
+
+impl FromStr for Notification {
+    type Err = error::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut notification: Notification = from_str(s)?;
+        notification.raw = s.to_string();
+        Ok(notification)
+    }
+}
+
+impl Display for Notification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.raw)
+    }
+}
+
+/// Reply to an executed rpc, holding the raw reply body and
+/// round-trip information of the request.
+#[derive(Debug)]
+pub struct Reply {
+    raw: String,
+    message_id: String,
+    elapsed: Duration,
+    parsed: OnceCell<RpcReply>,
+}
+
+impl Reply {
+    pub(crate) fn new(raw: String, message_id: String, elapsed: Duration) -> Reply {
+        Reply {
+            raw,
+            message_id,
+            elapsed,
+            parsed: OnceCell::new(),
+        }
+    }
+
+    /// Raw XML body of the reply
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    pub fn into_raw(self) -> String {
+        self.raw
+    }
+
+    /// Reply body re-indented for display, see [`prettify`]
+    pub fn pretty(&self) -> Pretty<'_> {
+        Pretty::new(&self.raw)
+    }
+
+    /// Message-id of the request this is a reply to
+    pub fn message_id(&self) -> &str {
+        &self.message_id
+    }
+
+    /// Time between sending the request and receiving the full reply
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Parsed reply, deserialized on first access
+    pub fn rpc_reply(&self) -> Result<&RpcReply, error::Error> {
+        if let Some(reply) = self.parsed.get() {
+            return Ok(reply);
+        }
+        let reply: RpcReply = from_str(&self.raw)?;
+        Ok(self.parsed.get_or_init(|| reply))
+    }
+
+    /// Deserializes reply into user-defined type, with `rpc-reply` as its root element
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T, error::Error> {
+        Ok(from_str(&self.raw)?)
+    }
+
+    pub(crate) fn into_rpc_reply(self) -> Result<RpcReply, error::Error> {
+        match self.parsed.into_inner() {
+            Some(reply) => Ok(reply),
+            None => Ok(from_str(&self.raw)?),
+        }
+    }
+}
+
+impl Display for Reply {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.raw)
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
+#[serde(rename = "rpc-error", rename_all = "kebab-case")]
+pub struct Error {
+    error_severity: ErrorSeverity,
+    error_type: ErrorType,
+    error_tag: ErrorTag,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_app_tag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_info: Option<ErrorInfo>,
+}
+
+impl Error {
+    /// Error with severity error, e.g. for replies of test server handlers
+    pub fn new(error_type: ErrorType, tag: ErrorTag, message: &str) -> Error {
+        Error {
+            error_severity: ErrorSeverity::Error,
+            error_type,
+            error_tag: tag,
+            error_app_tag: None,
+            error_path: None,
+            error_message: Some(message.to_string()),
+            error_info: None,
+        }
+    }
+
+    pub fn severity(&self) -> &ErrorSeverity {
+        &self.error_severity
+    }
+
+    pub fn error_type(&self) -> &ErrorType {
+        &self.error_type
+    }
+
+    pub fn tag(&self) -> &ErrorTag {
+        &self.error_tag
+    }
+
+    pub fn app_tag(&self) -> Option<&str> {
+        self.error_app_tag.as_deref()
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        self.error_path.as_deref()
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        self.error_message.as_deref()
+    }
+
+    pub fn info(&self) -> Option<&ErrorInfo> {
+        self.error_info.as_ref()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorType {
+    Transport,
+    Rpc,
+    Protocol,
+    App,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorSeverity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
-enum ErrorTag {
+pub enum ErrorTag {
     InUse,
     InvalidValue,
     TooBig,
@@ -245,7 +1115,7 @@ enum ErrorTag {
 
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
-struct ErrorInfo {
+pub struct ErrorInfo {
     #[serde(skip_serializing_if = "Option::is_none")]
     bad_element: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -262,11 +1132,40 @@ struct ErrorInfo {
     session_id: Option<u64>,
 }
 
+impl ErrorInfo {
+    pub fn bad_element(&self) -> Option<&str> {
+        self.bad_element.as_deref()
+    }
+
+    pub fn bad_attribute(&self) -> Option<&str> {
+        self.bad_attribute.as_deref()
+    }
+
+    pub fn bad_namespace(&self) -> Option<&str> {
+        self.bad_namespace.as_deref()
+    }
+
+    pub fn ok_element(&self) -> Option<&str> {
+        self.ok_element.as_deref()
+    }
+
+    pub fn err_element(&self) -> Option<&str> {
+        self.err_element.as_deref()
+    }
+
+    pub fn noop_element(&self) -> Option<&str> {
+        self.noop_element.as_deref()
+    }
+
+    pub fn session_id(&self) -> Option<u64> {
+        self.session_id
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use pretty_assertions::assert_eq;
-    use quick_xml::de::from_str;
 
     #[test]
     fn test_deserialize_reply_with_errors() {
@@ -294,7 +1193,50 @@ mod tests {
 "#.trim();
 
         let reply: RpcReply = from_str(reply).unwrap();
-        println!("{:?}", reply);
+        assert!(reply.has_errors());
+        assert_eq!(reply.errors().len(), 2);
+
+        let error = &reply.errors()[1];
+        assert_eq!(error.error_type(), &ErrorType::App);
+        assert_eq!(error.tag(), &ErrorTag::BadElement);
+        assert_eq!(error.severity(), &ErrorSeverity::Error);
+        assert_eq!(
+            error.message(),
+            Some("Element is not valid in the specified context.")
+        );
+        assert_eq!(error.info().and_then(|i| i.bad_element()), Some("startu"));
+        assert_eq!(error.path(), None);
+    }
+
+    #[test]
+    fn test_deserialize_reply_with_warnings() {
+        let reply = r#"
+<rpc-reply message-id="67d83d6b-1f0b-47fb-8fdf-2cfc3fb2a371" xmlns="urn:ietf:params:xml:ns:netconf:base:1.0">
+  <rpc-error>
+    <error-type>app</error-type>
+    <error-tag>operation-failed</error-tag>
+    <error-severity>warning</error-severity>
+    <error-message>Statement is deprecated.</error-message>
+  </rpc-error>
+  <ok/>
+</rpc-reply>
+"#.trim();
+
+        let reply: RpcReply = from_str(reply).unwrap();
+        assert!(!reply.has_errors());
+        assert!(reply.has_warnings());
+        assert_eq!(reply.warnings().count(), 1);
+    }
+
+    #[test]
+    fn test_reply_parsed_lazily() {
+        let raw = r#"<rpc-reply message-id="101" xmlns="urn:ietf:params:xml:ns:netconf:base:1.0"><ok/></rpc-reply>"#;
+        let reply = Reply::new(raw.to_string(), "101".to_string(), Duration::from_millis(5));
+        assert!(reply.parsed.get().is_none());
+        assert_eq!(reply.rpc_reply().unwrap().message_id(), reply.message_id());
+        assert!(reply.parsed.get().is_some());
+        assert_eq!(reply.raw(), raw);
+        assert_eq!(reply.elapsed(), Duration::from_millis(5));
     }
 
     #[test]
@@ -335,27 +1277,603 @@ mod tests {
         let close_session = Rpc {
             xmlns: "urn:ietf:params:xml:ns:netconf:base:1.0".to_string(),
             message_id: "c1be0e7f-3cbc-413f-8aa8-18ed663221d4".to_string(),
+            scheduled_time: None,
+            attributes: Vec::new(),
             content: RpcContent::CloseSession,
         };
         assert_eq!(close_session.to_string(), expected.trim());
     }
 
+    #[test]
+    fn test_serialize_indent() {
+        let rpc = Rpc {
+            xmlns: "urn:ietf:params:xml:ns:netconf:base:1.0".to_string(),
+            message_id: "1".to_string(),
+            scheduled_time: None,
+            attributes: Vec::new(),
+            content: RpcContent::EditConfig {
+                target: Target {
+                    datastore: Datastore::Candidate,
+                },
+                default_operation: None,
+                test_option: None,
+                error_option: None,
+                config: RawXml("<system/>".to_string()),
+            },
+        };
+        assert_eq!(
+            rpc.to_xml(Indent::Compact),
+            r#"<rpc xmlns="urn:ietf:params:xml:ns:netconf:base:1.0" message-id="1"><edit-config><target><candidate/></target><config><system/></config></edit-config></rpc>"#
+        );
+        assert_eq!(
+            rpc.to_xml(Indent::Spaces(4)),
+            r#"<rpc xmlns="urn:ietf:params:xml:ns:netconf:base:1.0" message-id="1">
+    <edit-config>
+        <target>
+            <candidate/>
+        </target>
+        <config><system/></config>
+    </edit-config>
+</rpc>"#
+        );
+        assert_eq!(rpc.to_string(), rpc.to_xml(Indent::Spaces(2)));
+    }
+
     #[test]
     fn test_serialize_kill_session() {
         let expected = r#"
 <rpc xmlns="urn:ietf:params:xml:ns:netconf:base:1.0" message-id="c1be0e7f-3cbc-413f-8aa8-18ed663221d4">
-  <kill-session/>
+  <kill-session>
+    <session-id>4</session-id>
+  </kill-session>
 </rpc>
 "#.trim().to_string();
 
         let close_session = Rpc {
             xmlns: "urn:ietf:params:xml:ns:netconf:base:1.0".to_string(),
             message_id: "c1be0e7f-3cbc-413f-8aa8-18ed663221d4".to_string(),
-            content: RpcContent::KillSession,
+            scheduled_time: None,
+            attributes: Vec::new(),
+            content: RpcContent::KillSession { session_id: 4 },
         };
         assert_eq!(close_session.to_string(), expected.trim());
     }
 
+    #[test]
+    fn test_serialize_edit_config() {
+        let expected = r#"
+<rpc xmlns="urn:ietf:params:xml:ns:netconf:base:1.0" message-id="c1be0e7f-3cbc-413f-8aa8-18ed663221d4">
+  <edit-config>
+    <target>
+      <candidate/>
+    </target>
+    <default-operation>replace</default-operation>
+    <config><system xmlns="urn:ietf:params:xml:ns:yang:ietf-system"><hostname>r1</hostname></system></config>
+  </edit-config>
+</rpc>
+"#
+        .trim()
+        .to_string();
+
+        let edit_config = Rpc {
+            xmlns: "urn:ietf:params:xml:ns:netconf:base:1.0".to_string(),
+            message_id: "c1be0e7f-3cbc-413f-8aa8-18ed663221d4".to_string(),
+            scheduled_time: None,
+            attributes: Vec::new(),
+            content: RpcContent::EditConfig {
+                target: Target {
+                    datastore: Datastore::Candidate,
+                },
+                default_operation: Some(DefaultOperation::Replace),
+                test_option: None,
+                error_option: None,
+                config: RawXml(
+                    r#"<system xmlns="urn:ietf:params:xml:ns:yang:ietf-system"><hostname>r1</hostname></system>"#
+                        .to_string(),
+                ),
+            },
+        };
+        assert_eq!(edit_config.to_string(), expected);
+    }
+
+    #[test]
+    fn test_serialize_get_with_filter() {
+        let expected = r#"
+<rpc xmlns="urn:ietf:params:xml:ns:netconf:base:1.0" message-id="c1be0e7f-3cbc-413f-8aa8-18ed663221d4">
+  <get>
+    <filter type="subtree"><system xmlns="urn:ietf:params:xml:ns:yang:ietf-system"/></filter>
+  </get>
+</rpc>
+"#
+        .trim()
+        .to_string();
+
+        let get = Rpc {
+            xmlns: "urn:ietf:params:xml:ns:netconf:base:1.0".to_string(),
+            message_id: "c1be0e7f-3cbc-413f-8aa8-18ed663221d4".to_string(),
+            scheduled_time: None,
+            attributes: Vec::new(),
+            content: RpcContent::Get {
+                filter: Some(Filter::subtree(
+                    r#"<system xmlns="urn:ietf:params:xml:ns:yang:ietf-system"/>"#,
+                )),
+                with_defaults: None,
+            },
+        };
+        assert_eq!(get.to_string(), expected);
+    }
+
+    #[test]
+    fn test_xpath_prefixes() {
+        assert_eq!(
+            xpath_prefixes("/if:interfaces/if:interface[if:name='eth0:1']/child::ip:address"),
+            vec!["if", "ip"]
+        );
+        let filter = Filter::xpath("/xnm:a/ex:b")
+            .namespace("ex", "urn:example")
+            .resolve_prefixes(|prefix| Some(format!("urn:resolved:{}", prefix)));
+        assert_eq!(
+            filter.namespaces.get("@xmlns:xnm").map(String::as_str),
+            Some("urn:resolved:xnm")
+        );
+        assert_eq!(
+            filter.namespaces.get("@xmlns:ex").map(String::as_str),
+            Some("urn:example")
+        );
+    }
+
+    #[test]
+    fn test_serialize_get_config_with_xpath_filter() {
+        let expected = r#"
+<rpc xmlns="urn:ietf:params:xml:ns:netconf:base:1.0" message-id="c1be0e7f-3cbc-413f-8aa8-18ed663221d4">
+  <get-config>
+    <source>
+      <running/>
+    </source>
+    <filter type="xpath" select="/if:interfaces/if:interface[if:name='eth0']" xmlns:if="urn:ietf:params:xml:ns:yang:ietf-interfaces"/>
+  </get-config>
+</rpc>
+"#
+        .trim()
+        .to_string();
+
+        let get_config = Rpc {
+            xmlns: "urn:ietf:params:xml:ns:netconf:base:1.0".to_string(),
+            message_id: "c1be0e7f-3cbc-413f-8aa8-18ed663221d4".to_string(),
+            scheduled_time: None,
+            attributes: Vec::new(),
+            content: RpcContent::GetConfig {
+                source: Source {
+                    datastore: Datastore::Running,
+                },
+                filter: Some(
+                    Filter::xpath("/if:interfaces/if:interface[if:name='eth0']")
+                        .namespace("if", "urn:ietf:params:xml:ns:yang:ietf-interfaces"),
+                ),
+                with_defaults: None,
+            },
+        };
+        assert_eq!(get_config.to_string(), expected);
+    }
+
+    #[test]
+    fn test_serialize_lock() {
+        let expected = r#"
+<rpc xmlns="urn:ietf:params:xml:ns:netconf:base:1.0" message-id="c1be0e7f-3cbc-413f-8aa8-18ed663221d4">
+  <lock>
+    <target>
+      <candidate/>
+    </target>
+  </lock>
+</rpc>
+"#
+        .trim()
+        .to_string();
+
+        let lock = Rpc {
+            xmlns: "urn:ietf:params:xml:ns:netconf:base:1.0".to_string(),
+            message_id: "c1be0e7f-3cbc-413f-8aa8-18ed663221d4".to_string(),
+            scheduled_time: None,
+            attributes: Vec::new(),
+            content: RpcContent::Lock {
+                target: Target {
+                    datastore: Datastore::Candidate,
+                },
+            },
+        };
+        assert_eq!(lock.to_string(), expected);
+    }
+
+    #[test]
+    fn test_serialize_copy_config() {
+        let expected = r#"
+<rpc xmlns="urn:ietf:params:xml:ns:netconf:base:1.0" message-id="c1be0e7f-3cbc-413f-8aa8-18ed663221d4">
+  <copy-config>
+    <target>
+      <url>file:///Backup.xml</url>
+    </target>
+    <source>
+      <running/>
+    </source>
+  </copy-config>
+</rpc>
+"#
+        .trim()
+        .to_string();
+
+        let copy_config = Rpc {
+            xmlns: "urn:ietf:params:xml:ns:netconf:base:1.0".to_string(),
+            message_id: "c1be0e7f-3cbc-413f-8aa8-18ed663221d4".to_string(),
+            scheduled_time: None,
+            attributes: Vec::new(),
+            content: RpcContent::CopyConfig {
+                target: Target {
+                    datastore: Datastore::from_str("file:///Backup.xml").unwrap(),
+                },
+                source: Source {
+                    datastore: Datastore::Running,
+                },
+            },
+        };
+        assert_eq!(copy_config.to_string(), expected);
+    }
+
+    #[test]
+    fn test_serialize_confirmed_commit() {
+        let expected = r#"
+<rpc xmlns="urn:ietf:params:xml:ns:netconf:base:1.0" message-id="c1be0e7f-3cbc-413f-8aa8-18ed663221d4">
+  <commit>
+    <confirmed/>
+    <confirm-timeout>120</confirm-timeout>
+    <persist>change-42</persist>
+  </commit>
+</rpc>
+"#
+        .trim()
+        .to_string();
+
+        let commit = Rpc {
+            xmlns: "urn:ietf:params:xml:ns:netconf:base:1.0".to_string(),
+            message_id: "c1be0e7f-3cbc-413f-8aa8-18ed663221d4".to_string(),
+            scheduled_time: None,
+            attributes: Vec::new(),
+            content: RpcContent::CommitWith(
+                CommitOptions::confirmed(Some(Duration::from_secs(120))).persist("change-42"),
+            ),
+        };
+        assert_eq!(commit.to_string(), expected);
+    }
+
+    #[test]
+    fn test_serialize_cancel_persisted() {
+        let commit = Rpc {
+            xmlns: "urn:ietf:params:xml:ns:netconf:base:1.0".to_string(),
+            message_id: "1".to_string(),
+            scheduled_time: None,
+            attributes: Vec::new(),
+            content: RpcContent::CancelPersisted {
+                persist_id: "change-42".to_string(),
+            },
+        };
+        assert!(commit.to_string().contains(
+            "<cancel-commit>\n    <persist-id>change-42</persist-id>\n  </cancel-commit>"
+        ));
+    }
+
+    #[test]
+    fn test_serialize_scheduled() {
+        let time = OffsetDateTime::from_unix_timestamp(1714600800).unwrap();
+        let commit = Rpc::new(RpcContent::Commit).scheduled_at(time).unwrap();
+        assert!(commit.to_xml(Indent::Compact).ends_with(
+            r#"><commit><time xmlns="urn:ietf:params:xml:ns:yang:ietf-netconf-time"><scheduled-time>2024-05-01T22:00:00Z</scheduled-time></time></commit></rpc>"#
+        ));
+        let lock = Rpc::new(RpcContent::Lock {
+            target: Target {
+                datastore: Datastore::Candidate,
+            },
+        })
+        .scheduled_at(time)
+        .unwrap();
+        assert!(lock.to_xml(Indent::Compact).contains("<lock><time xmlns="));
+    }
+
+    #[test]
+    fn test_serialize_raw() {
+        let expected = r#"
+<rpc xmlns="urn:ietf:params:xml:ns:netconf:base:1.0" message-id="c1be0e7f-3cbc-413f-8aa8-18ed663221d4"><get-chassis-inventory/></rpc>
+"#
+        .trim()
+        .to_string();
+
+        let raw = Rpc {
+            xmlns: "urn:ietf:params:xml:ns:netconf:base:1.0".to_string(),
+            message_id: "c1be0e7f-3cbc-413f-8aa8-18ed663221d4".to_string(),
+            scheduled_time: None,
+            attributes: Vec::new(),
+            content: RpcContent::Raw(RawXml("<get-chassis-inventory/>".to_string())),
+        };
+        assert_eq!(raw.to_string(), expected);
+    }
+
+    #[test]
+    fn test_envelope_attributes() {
+        let rpc = Rpc {
+            xmlns: "urn:ietf:params:xml:ns:netconf:base:1.0".to_string(),
+            message_id: "1".to_string(),
+            scheduled_time: None,
+            attributes: Vec::new(),
+            content: RpcContent::Raw(RawXml("<get-configuration/>".to_string())),
+        }
+        .attribute("format", "text")
+        .namespace("junos", "http://xml.juniper.net/junos/*/junos");
+        assert_eq!(
+            rpc.to_xml(Indent::Compact),
+            r#"<rpc xmlns="urn:ietf:params:xml:ns:netconf:base:1.0" message-id="1" format="text" xmlns:junos="http://xml.juniper.net/junos/*/junos"><get-configuration/></rpc>"#
+        );
+    }
+
+    #[test]
+    fn test_custom_operation() {
+        #[derive(Serialize)]
+        #[serde(rename = "get-route-information", rename_all = "kebab-case")]
+        struct GetRouteInformation {
+            #[serde(rename = "@xmlns")]
+            xmlns: &'static str,
+            destination: &'static str,
+        }
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        #[serde(rename_all = "kebab-case")]
+        struct RouteReply {
+            route_information: RouteInformation,
+        }
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        #[serde(rename_all = "kebab-case")]
+        struct RouteInformation {
+            next_hop: Vec<String>,
+        }
+
+        let rpc = Rpc::from_operation(&GetRouteInformation {
+            xmlns: "http://example.com/routing",
+            destination: "10.0.0.0/8",
+        })
+        .unwrap();
+        assert!(rpc.to_xml(Indent::Compact).contains(
+            r#"<get-route-information xmlns="http://example.com/routing"><destination>10.0.0.0/8</destination></get-route-information>"#
+        ));
+
+        let reply = Reply::new(
+            r#"<rpc-reply message-id="1"><route-information><next-hop>10.1.1.1</next-hop><next-hop>10.1.1.2</next-hop></route-information></rpc-reply>"#.to_string(),
+            "1".to_string(),
+            Duration::ZERO,
+        );
+        assert_eq!(
+            reply.deserialize::<RouteReply>().unwrap(),
+            RouteReply {
+                route_information: RouteInformation {
+                    next_hop: vec!["10.1.1.1".to_string(), "10.1.1.2".to_string()],
+                }
+            }
+        );
+    }
+
+    #[test]
+    fn test_serialize_create_subscription() {
+        let expected = r#"
+<rpc xmlns="urn:ietf:params:xml:ns:netconf:base:1.0" message-id="c1be0e7f-3cbc-413f-8aa8-18ed663221d4">
+  <create-subscription xmlns="urn:ietf:params:xml:ns:netconf:notification:1.0">
+    <stream>NETCONF</stream>
+  </create-subscription>
+</rpc>
+"#
+        .trim()
+        .to_string();
+
+        let create_subscription = Rpc {
+            xmlns: "urn:ietf:params:xml:ns:netconf:base:1.0".to_string(),
+            message_id: "c1be0e7f-3cbc-413f-8aa8-18ed663221d4".to_string(),
+            scheduled_time: None,
+            attributes: Vec::new(),
+            content: RpcContent::create_subscription(Some("NETCONF")),
+        };
+        assert_eq!(create_subscription.to_string(), expected);
+    }
+
+    #[test]
+    fn test_serialize_create_subscription_between() {
+        let expected = r#"
+<rpc xmlns="urn:ietf:params:xml:ns:netconf:base:1.0" message-id="c1be0e7f-3cbc-413f-8aa8-18ed663221d4">
+  <create-subscription xmlns="urn:ietf:params:xml:ns:netconf:notification:1.0">
+    <startTime>2024-05-01T10:00:00Z</startTime>
+    <stopTime>2024-05-01T10:30:00Z</stopTime>
+  </create-subscription>
+</rpc>
+"#
+        .trim()
+        .to_string();
+
+        let start = OffsetDateTime::from_unix_timestamp(1714557600).unwrap();
+        let create_subscription = Rpc {
+            xmlns: "urn:ietf:params:xml:ns:netconf:base:1.0".to_string(),
+            message_id: "c1be0e7f-3cbc-413f-8aa8-18ed663221d4".to_string(),
+            scheduled_time: None,
+            attributes: Vec::new(),
+            content: RpcContent::create_subscription_between(
+                None,
+                Some(start),
+                Some(start + Duration::from_secs(1800)),
+            )
+            .unwrap(),
+        };
+        assert_eq!(create_subscription.to_string(), expected);
+    }
+
+    #[test]
+    fn test_prettify() {
+        let xml = r#"<rpc-reply message-id="1"><data>
+    <system><hostname>r1</hostname>
+</system><empty/></data></rpc-reply>"#;
+        let expected = r#"<rpc-reply message-id="1">
+  <data>
+    <system>
+      <hostname>r1</hostname>
+    </system>
+    <empty/>
+  </data>
+</rpc-reply>"#;
+        assert_eq!(prettify(xml), expected);
+        assert_eq!(
+            Pretty::new("<a><b>x</b></a>").indent(4).to_string(),
+            "<a>\n    <b>x</b>\n</a>"
+        );
+        assert_eq!(prettify(" <a><b></a> "), "<a><b></a>");
+    }
+
+    #[test]
+    fn test_deserialize_notification() {
+        let raw = r#"
+<notification xmlns="urn:ietf:params:xml:ns:netconf:notification:1.0">
+  <eventTime>2024-05-01T10:00:00Z</eventTime>
+  <link-down xmlns="urn:example:events"><if-name>eth0</if-name></link-down>
+</notification>
+"#
+        .trim();
+        let notification = Notification::from_str(raw).unwrap();
+        assert_eq!(notification.event_time(), "2024-05-01T10:00:00Z");
+        assert_eq!(notification.raw(), raw);
+        assert!(!notification.is_complete());
+    }
+
+    #[test]
+    fn test_strip_netconf_prefixes() {
+        let reply = r#"<nc:rpc-reply xmlns:nc="urn:ietf:params:xml:ns:netconf:base:1.0" message-id="7"><nc:rpc-error><nc:error-type>rpc</nc:error-type><nc:error-tag>missing-attribute</nc:error-tag><nc:error-severity>error</nc:error-severity></nc:rpc-error><nc:data><if:interfaces xmlns:if="urn:example:if"/></nc:data></nc:rpc-reply>"#;
+        let stripped = strip_netconf_prefixes(reply).unwrap();
+        assert_eq!(
+            stripped,
+            r#"<rpc-reply xmlns:nc="urn:ietf:params:xml:ns:netconf:base:1.0" message-id="7"><rpc-error><error-type>rpc</error-type><error-tag>missing-attribute</error-tag><error-severity>error</error-severity></rpc-error><data><if:interfaces xmlns:if="urn:example:if"/></data></rpc-reply>"#
+        );
+        let reply: RpcReply = from_str(&stripped).unwrap();
+        assert_eq!(reply.errors()[0].tag(), &ErrorTag::MissingAttribute);
+
+        assert_eq!(strip_netconf_prefixes("<rpc-reply><ok/></rpc-reply>"), None);
+        let data = r#"<rpc-reply message-id="8"><data><if:interfaces xmlns:if="urn:example:if"/></data></rpc-reply>"#;
+        assert_eq!(strip_netconf_prefixes(data), None);
+
+        let stripped = strip_netconf_prefixes(
+            r#"<ncEvent:notification xmlns:ncEvent="urn:ietf:params:xml:ns:netconf:notification:1.0"><ncEvent:eventTime>2024-05-01T10:00:00Z</ncEvent:eventTime></ncEvent:notification>"#,
+        )
+        .unwrap();
+        let notification = Notification::from_str(&stripped).unwrap();
+        assert_eq!(notification.event_time(), "2024-05-01T10:00:00Z");
+    }
+
+    #[test]
+    fn test_deserialize_notification_complete() {
+        let raw = r#"
+<notification xmlns="urn:ietf:params:xml:ns:netconf:notification:1.0">
+  <eventTime>2024-05-01T10:10:00Z</eventTime>
+  <notificationComplete xmlns="urn:ietf:params:xml:ns:netmod:notification"/>
+</notification>
+"#;
+        let notification = Notification::from_str(raw).unwrap();
+        assert!(notification.is_complete());
+    }
+
+    #[test]
+    fn test_serialize_establish_subscription() {
+        let expected = r#"
+<rpc xmlns="urn:ietf:params:xml:ns:netconf:base:1.0" message-id="c1be0e7f-3cbc-413f-8aa8-18ed663221d4">
+  <establish-subscription xmlns="urn:ietf:params:xml:ns:yang:ietf-subscribed-notifications">
+    <datastore xmlns="urn:ietf:params:xml:ns:yang:ietf-yang-push" xmlns:ds="urn:ietf:params:xml:ns:yang:ietf-datastores">ds:operational</datastore>
+    <datastore-xpath-filter xmlns="urn:ietf:params:xml:ns:yang:ietf-yang-push">/interfaces</datastore-xpath-filter>
+    <periodic xmlns="urn:ietf:params:xml:ns:yang:ietf-yang-push">
+      <period>500</period>
+    </periodic>
+  </establish-subscription>
+</rpc>
+"#
+        .trim()
+        .to_string();
+
+        let establish_subscription = Rpc {
+            xmlns: "urn:ietf:params:xml:ns:netconf:base:1.0".to_string(),
+            message_id: "c1be0e7f-3cbc-413f-8aa8-18ed663221d4".to_string(),
+            scheduled_time: None,
+            attributes: Vec::new(),
+            content: RpcContent::EstablishSubscription(
+                EstablishSubscription::periodic("operational", Duration::from_secs(5))
+                    .xpath_filter("/interfaces"),
+            ),
+        };
+        assert_eq!(establish_subscription.to_string(), expected);
+    }
+
+    #[test]
+    fn test_serialize_modify_subscription() {
+        let expected = r#"
+<rpc xmlns="urn:ietf:params:xml:ns:netconf:base:1.0" message-id="c1be0e7f-3cbc-413f-8aa8-18ed663221d4">
+  <modify-subscription xmlns="urn:ietf:params:xml:ns:yang:ietf-subscribed-notifications">
+    <id>22</id>
+    <periodic xmlns="urn:ietf:params:xml:ns:yang:ietf-yang-push">
+      <period>1000</period>
+    </periodic>
+  </modify-subscription>
+</rpc>
+"#
+        .trim()
+        .to_string();
+
+        let modify_subscription = Rpc {
+            xmlns: "urn:ietf:params:xml:ns:netconf:base:1.0".to_string(),
+            message_id: "c1be0e7f-3cbc-413f-8aa8-18ed663221d4".to_string(),
+            scheduled_time: None,
+            attributes: Vec::new(),
+            content: RpcContent::ModifySubscription(
+                ModifySubscription::new(22).period(Duration::from_secs(10)),
+            ),
+        };
+        assert_eq!(modify_subscription.to_string(), expected);
+    }
+
+    #[test]
+    fn test_serialize_resync_subscription() {
+        let expected = r#"
+<rpc xmlns="urn:ietf:params:xml:ns:netconf:base:1.0" message-id="c1be0e7f-3cbc-413f-8aa8-18ed663221d4">
+  <resync-subscription xmlns="urn:ietf:params:xml:ns:yang:ietf-yang-push">
+    <id>22</id>
+  </resync-subscription>
+</rpc>
+"#
+        .trim()
+        .to_string();
+
+        let resync_subscription = Rpc {
+            xmlns: "urn:ietf:params:xml:ns:netconf:base:1.0".to_string(),
+            message_id: "c1be0e7f-3cbc-413f-8aa8-18ed663221d4".to_string(),
+            scheduled_time: None,
+            attributes: Vec::new(),
+            content: RpcContent::resync_subscription(22),
+        };
+        assert_eq!(resync_subscription.to_string(), expected);
+    }
+
+    #[test]
+    fn test_deserialize_push_update() {
+        let raw = r#"
+<notification xmlns="urn:ietf:params:xml:ns:netconf:notification:1.0">
+  <eventTime>2024-05-01T10:00:00Z</eventTime>
+  <push-update xmlns="urn:ietf:params:xml:ns:yang:ietf-yang-push">
+    <id>1011</id>
+    <datastore-contents><interfaces xmlns="urn:ietf:params:xml:ns:yang:ietf-interfaces"/></datastore-contents>
+  </push-update>
+</notification>
+"#
+        .trim();
+        let notification = Notification::from_str(raw).unwrap();
+        assert_eq!(notification.subscription_id(), Some(1011));
+    }
+
     #[test]
     fn test_serialize_get_config() {
         let expected = r#"
@@ -373,13 +1891,39 @@ mod tests {
         let close_session = Rpc {
             xmlns: "urn:ietf:params:xml:ns:netconf:base:1.0".to_string(),
             message_id: "c1be0e7f-3cbc-413f-8aa8-18ed663221d4".to_string(),
+            scheduled_time: None,
+            attributes: Vec::new(),
             content: RpcContent::GetConfig {
                 source: Source {
                     datastore: Datastore::Running,
                 },
                 filter: None,
+                with_defaults: None,
             },
         };
         assert_eq!(close_session.to_string(), expected.trim());
     }
+
+    #[test]
+    fn test_serialize_get_with_defaults() {
+        let expected = r#"
+<rpc xmlns="urn:ietf:params:xml:ns:netconf:base:1.0" message-id="c1be0e7f-3cbc-413f-8aa8-18ed663221d4">
+  <get>
+    <with-defaults xmlns="urn:ietf:params:xml:ns:yang:ietf-netconf-with-defaults">report-all-tagged</with-defaults>
+  </get>
+</rpc>
+"#;
+
+        let get = Rpc {
+            xmlns: "urn:ietf:params:xml:ns:netconf:base:1.0".to_string(),
+            message_id: "c1be0e7f-3cbc-413f-8aa8-18ed663221d4".to_string(),
+            scheduled_time: None,
+            attributes: Vec::new(),
+            content: RpcContent::Get {
+                filter: None,
+                with_defaults: Some(WithDefaultsValue::ReportAllTagged.into()),
+            },
+        };
+        assert_eq!(get.to_string(), expected.trim());
+    }
 }
@@ -0,0 +1,86 @@
+//! Per rpc measurements for feeding application metrics, e.g. Prometheus counters
+//! and histograms, see [`Connection::set_metrics_observer`](crate::Connection::set_metrics_observer)
+
+use crate::error::Error;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Ok,
+    /// Reply contained `<rpc-error>`
+    RpcError,
+    Timeout,
+    /// Sending, receiving or parsing the reply failed
+    Failed,
+}
+
+impl From<&Error> for Outcome {
+    fn from(err: &Error) -> Self {
+        match err {
+            Error::Netconf(_) => Outcome::RpcError,
+            Error::Timeout => Outcome::Timeout,
+            _ => Outcome::Failed,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RpcMetrics<'a> {
+    /// Name of the operation element, e.g. `get-config`
+    pub operation: &'a str,
+    pub message_id: &'a str,
+    /// Size of the serialized rpc without framing
+    pub bytes_sent: usize,
+    /// Size of the reply without framing, 0 if none was received
+    pub bytes_received: usize,
+    /// Time from sending the rpc until its reply was received
+    pub duration: Duration,
+    pub outcome: Outcome,
+}
+
+/// Called once for every rpc sent with [`Connection`](crate::Connection), after its
+/// reply is received or waiting for it fails
+pub trait MetricsObserver: Send {
+    fn on_rpc(&mut self, metrics: &RpcMetrics<'_>);
+}
+
+/// Local name of the operation, i.e. first child of `<rpc>`
+pub(crate) fn operation_name(rpc: &str) -> String {
+    let mut reader = Reader::from_str(rpc);
+    let mut depth = 0;
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(start)) if depth == 1 => {
+                return String::from_utf8_lossy(start.local_name().as_ref()).into_owned()
+            }
+            Ok(Event::Empty(empty)) if depth == 1 => {
+                return String::from_utf8_lossy(empty.local_name().as_ref()).into_owned()
+            }
+            Ok(Event::Start(_)) => depth += 1,
+            Ok(Event::Eof) | Err(_) => return String::new(),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operation_name() {
+        let rpc = r#"<rpc xmlns="urn:ietf:params:xml:ns:netconf:base:1.0" message-id="1">
+  <get-config><source><running/></source></get-config>
+</rpc>"#;
+        assert_eq!(operation_name(rpc), "get-config");
+        assert_eq!(
+            operation_name(
+                r#"<rpc message-id="2"><jnx:get-software-information xmlns:jnx="urn:x"/></rpc>"#
+            ),
+            "get-software-information"
+        );
+        assert_eq!(operation_name("<rpc/>"), "");
+    }
+}
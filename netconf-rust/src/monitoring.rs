@@ -0,0 +1,243 @@
+//! Queries of ietf-netconf-monitoring state, see [RFC6022](https://tools.ietf.org/html/rfc6022)
+use crate::error::Result;
+use crate::message::{Filter, Reply};
+use crate::Connection;
+use quick_xml::de::from_str;
+use serde_derive::Deserialize;
+
+pub const MONITORING_NAMESPACE: &str = "urn:ietf:params:xml:ns:yang:ietf-netconf-monitoring";
+
+#[derive(Debug, Deserialize)]
+struct MonitoringReply {
+    data: Data,
+}
+
+#[derive(Debug, Deserialize)]
+struct Data {
+    #[serde(rename = "netconf-state")]
+    netconf_state: Option<NetconfState>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct NetconfState {
+    sessions: Option<Sessions>,
+    datastores: Option<Datastores>,
+    statistics: Option<Statistics>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Sessions {
+    #[serde(default)]
+    session: Vec<Session>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Datastores {
+    #[serde(default)]
+    datastore: Vec<Datastore>,
+}
+
+/// Management session active on the server
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Session {
+    pub session_id: u64,
+    /// Transport identity, e.g. `ncm:netconf-ssh`
+    pub transport: String,
+    pub username: String,
+    pub source_host: Option<String>,
+    pub login_time: String,
+    #[serde(default)]
+    pub in_rpcs: u32,
+    #[serde(default)]
+    pub in_bad_rpcs: u32,
+    #[serde(default)]
+    pub out_rpc_errors: u32,
+    #[serde(default)]
+    pub out_notifications: u32,
+}
+
+/// Datastore of the server with its current locks
+#[derive(Debug, Deserialize)]
+pub struct Datastore {
+    pub name: String,
+    locks: Option<Locks>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Locks {
+    global_lock: Option<GlobalLock>,
+    #[serde(default)]
+    partial_lock: Vec<PartialLock>,
+}
+
+/// Lock of the whole datastore acquired with `<lock>`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct GlobalLock {
+    pub locked_by_session: u64,
+    pub locked_time: String,
+}
+
+/// Lock of datastore nodes acquired with `<partial-lock>`, see RFC5717
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PartialLock {
+    pub lock_id: u32,
+    pub locked_by_session: u64,
+    pub locked_time: String,
+    #[serde(default)]
+    pub select: Vec<String>,
+    #[serde(default)]
+    pub locked_node: Vec<String>,
+}
+
+impl Datastore {
+    pub fn global_lock(&self) -> Option<&GlobalLock> {
+        self.locks
+            .as_ref()
+            .and_then(|locks| locks.global_lock.as_ref())
+    }
+
+    pub fn partial_locks(&self) -> &[PartialLock] {
+        self.locks
+            .as_ref()
+            .map(|locks| locks.partial_lock.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Session holding the global lock
+    pub fn locked_by(&self) -> Option<u64> {
+        self.global_lock().map(|lock| lock.locked_by_session)
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.global_lock().is_some() || !self.partial_locks().is_empty()
+    }
+}
+
+/// Server wide counters since `netconf_start_time`
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Statistics {
+    pub netconf_start_time: Option<String>,
+    #[serde(default)]
+    pub in_bad_hellos: u32,
+    #[serde(default)]
+    pub in_sessions: u32,
+    #[serde(default)]
+    pub dropped_sessions: u32,
+    #[serde(default)]
+    pub in_rpcs: u32,
+    #[serde(default)]
+    pub in_bad_rpcs: u32,
+    #[serde(default)]
+    pub out_rpc_errors: u32,
+    #[serde(default)]
+    pub out_notifications: u32,
+}
+
+/// Returns sessions from `/netconf-state/sessions`
+pub fn sessions(connection: &mut Connection) -> Result<Vec<Session>> {
+    let state = query(connection, "sessions")?;
+    Ok(state.sessions.unwrap_or_default().session)
+}
+
+/// Returns datastores and their lock holders from `/netconf-state/datastores`
+pub fn datastores(connection: &mut Connection) -> Result<Vec<Datastore>> {
+    let state = query(connection, "datastores")?;
+    Ok(state.datastores.unwrap_or_default().datastore)
+}
+
+/// Returns counters from `/netconf-state/statistics`
+pub fn statistics(connection: &mut Connection) -> Result<Statistics> {
+    let state = query(connection, "statistics")?;
+    Ok(state.statistics.unwrap_or_default())
+}
+
+fn query(connection: &mut Connection, container: &str) -> Result<NetconfState> {
+    let reply = connection.get(Some(filter(container)))?;
+    parse(&reply)
+}
+
+fn filter(container: &str) -> Filter {
+    Filter::subtree(&format!(
+        r#"<netconf-state xmlns="{}"><{}/></netconf-state>"#,
+        MONITORING_NAMESPACE, container
+    ))
+}
+
+fn parse(reply: &Reply) -> Result<NetconfState> {
+    let reply: MonitoringReply = from_str(reply.raw())?;
+    Ok(reply.data.netconf_state.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::time::Duration;
+
+    fn reply(state: &str) -> NetconfState {
+        let raw = format!(
+            r#"<rpc-reply xmlns="urn:ietf:params:xml:ns:netconf:base:1.0" message-id="1"><data><netconf-state xmlns="urn:ietf:params:xml:ns:yang:ietf-netconf-monitoring">{}</netconf-state></data></rpc-reply>"#,
+            state
+        );
+        parse(&Reply::new(raw, "1".to_string(), Duration::ZERO)).unwrap()
+    }
+
+    #[test]
+    fn test_sessions() {
+        let state = reply(
+            r#"<sessions>
+  <session><session-id>3</session-id><transport xmlns:ncm="urn:ietf:params:xml:ns:yang:ietf-netconf-monitoring">ncm:netconf-ssh</transport><username>admin</username><source-host>192.0.2.1</source-host><login-time>2024-05-01T10:00:00Z</login-time><in-rpcs>7</in-rpcs><in-bad-rpcs>0</in-bad-rpcs><out-rpc-errors>1</out-rpc-errors><out-notifications>0</out-notifications></session>
+  <session><session-id>4</session-id><transport>ncm:netconf-ssh</transport><username>oper</username><login-time>2024-05-01T11:00:00Z</login-time></session>
+</sessions>"#,
+        );
+        let sessions = state.sessions.unwrap().session;
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].username, "admin");
+        assert_eq!(sessions[0].in_rpcs, 7);
+        assert_eq!(sessions[1].source_host, None);
+    }
+
+    #[test]
+    fn test_datastores() {
+        let state = reply(
+            r#"<datastores>
+  <datastore><name>running</name><locks><global-lock><locked-by-session>3</locked-by-session><locked-time>2024-05-01T10:00:00Z</locked-time></global-lock></locks></datastore>
+  <datastore><name>candidate</name><locks><partial-lock><lock-id>1</lock-id><locked-by-session>4</locked-by-session><locked-time>2024-05-01T11:00:00Z</locked-time><select>/interfaces</select><locked-node>/if:interfaces</locked-node></partial-lock></locks></datastore>
+  <datastore><name>startup</name></datastore>
+</datastores>"#,
+        );
+        let datastores = state.datastores.unwrap().datastore;
+        assert_eq!(datastores[0].locked_by(), Some(3));
+        assert_eq!(datastores[1].locked_by(), None);
+        assert_eq!(datastores[1].partial_locks()[0].locked_by_session, 4);
+        assert!(datastores[1].is_locked());
+        assert!(!datastores[2].is_locked());
+    }
+
+    #[test]
+    fn test_statistics() {
+        let state = reply(
+            r#"<statistics><netconf-start-time>2024-05-01T09:00:00Z</netconf-start-time><in-bad-hellos>0</in-bad-hellos><in-sessions>12</in-sessions><dropped-sessions>2</dropped-sessions><in-rpcs>100</in-rpcs><in-bad-rpcs>1</in-bad-rpcs><out-rpc-errors>3</out-rpc-errors><out-notifications>0</out-notifications></statistics>"#,
+        );
+        let statistics = state.statistics.unwrap();
+        assert_eq!(statistics.in_sessions, 12);
+        assert_eq!(statistics.dropped_sessions, 2);
+    }
+
+    #[test]
+    fn test_empty_data() {
+        let raw = r#"<rpc-reply xmlns="urn:ietf:params:xml:ns:netconf:base:1.0" message-id="1"><data/></rpc-reply>"#;
+        let state = parse(&Reply::new(
+            raw.to_string(),
+            "1".to_string(),
+            Duration::ZERO,
+        ))
+        .unwrap();
+        assert!(state.sessions.is_none());
+    }
+}
@@ -0,0 +1,241 @@
+//! Client-side check of element names in edit-config payloads against YANG modules of
+//! the device, enabled with the `yang` feature.
+//!
+//! This is not YANG validation: modules are only tokenized, not parsed, and the check
+//! is that every element is a data node defined in a module with the element's
+//! namespace. Nesting, types, keys and constraints are left to the device; the goal
+//! is catching typos before sending the rpc.
+use crate::error::{Error, Result};
+use crate::schema::{tokenize, Cache, Module};
+use crate::Connection;
+use quick_xml::events::Event;
+use quick_xml::name::ResolveResult;
+use quick_xml::NsReader;
+use std::collections::{HashMap, HashSet};
+
+const DATA_NODES: [&str; 6] = [
+    "container",
+    "leaf",
+    "leaf-list",
+    "list",
+    "anydata",
+    "anyxml",
+];
+
+/// Data node names of loaded modules grouped by namespace
+#[derive(Debug, Default)]
+pub struct NameChecker {
+    namespaces: HashMap<String, HashSet<String>>,
+    /// Nodes of groupings, which take namespace of the module using them
+    grouped: HashSet<String>,
+    /// `anydata` and `anyxml` nodes whose contents are not modelled
+    opaque: HashSet<String>,
+}
+
+impl NameChecker {
+    pub fn new(modules: &[Module]) -> NameChecker {
+        let mut checker = NameChecker::default();
+        let mut namespaces = HashMap::new();
+        for module in modules {
+            let tokens = tokenize(module.source());
+            if let Some(namespace) = argument(&tokens, "namespace") {
+                namespaces.insert(module.name().to_string(), namespace.to_string());
+            }
+        }
+        for module in modules {
+            let tokens = tokenize(module.source());
+            // Submodule nodes belong to namespace of the module it belongs to
+            let namespace = argument(&tokens, "namespace")
+                .or_else(|| {
+                    argument(&tokens, "belongs-to")
+                        .and_then(|parent| namespaces.get(parent).map(String::as_str))
+                })
+                .map(str::to_string);
+            let Some(namespace) = namespace else {
+                warn!("Namespace of module {} not found", module.name());
+                continue;
+            };
+            checker.add_nodes(&tokens, namespace);
+        }
+        checker
+    }
+
+    /// Fetches `modules` with their dependencies to `cache` and loads them
+    pub fn load(
+        connection: &mut Connection,
+        cache: &Cache,
+        modules: &[&str],
+    ) -> Result<NameChecker> {
+        let mut loaded = Vec::new();
+        for module in modules {
+            loaded.extend(cache.fetch_with_dependencies(connection, module, None)?);
+        }
+        Ok(NameChecker::new(&loaded))
+    }
+
+    /// Checks names of elements in `config`, i.e. contents of `<config>` element, fails
+    /// with [`Error::UnknownElement`] on first element not defined in the loaded modules
+    pub fn check(&self, config: &str) -> Result<()> {
+        let mut reader = NsReader::from_str(config);
+        let mut opaque_depth = 0;
+        loop {
+            let (namespace, event) = reader.read_resolved_event()?;
+            match event {
+                Event::Start(ref start) | Event::Empty(ref start) => {
+                    let is_start = matches!(event, Event::Start(_));
+                    if opaque_depth > 0 {
+                        opaque_depth += usize::from(is_start);
+                        continue;
+                    }
+                    let name = String::from_utf8_lossy(start.local_name().as_ref()).into_owned();
+                    let ResolveResult::Bound(namespace) = namespace else {
+                        return Err(Error::UnknownElement(format!(
+                            "element {} has no namespace",
+                            name
+                        )));
+                    };
+                    let namespace = String::from_utf8_lossy(namespace.as_ref());
+                    let Some(nodes) = self.namespaces.get(namespace.as_ref()) else {
+                        return Err(Error::UnknownElement(format!(
+                            "unknown namespace {} of element {}",
+                            namespace, name
+                        )));
+                    };
+                    if !nodes.contains(&name) && !self.grouped.contains(&name) {
+                        return Err(Error::UnknownElement(format!(
+                            "unknown element {} in namespace {}",
+                            name, namespace
+                        )));
+                    }
+                    if is_start && self.opaque.contains(&name) {
+                        opaque_depth = 1;
+                    }
+                }
+                Event::End(_) if opaque_depth > 0 => opaque_depth -= 1,
+                Event::Eof => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+
+    fn add_nodes(&mut self, tokens: &[String], namespace: String) {
+        let nodes = self.namespaces.entry(namespace).or_default();
+        // Depth of enclosing grouping statements, names there are valid in any namespace
+        let mut groupings = Vec::new();
+        let mut depth = 0;
+        for (i, token) in tokens.iter().enumerate() {
+            match token.as_str() {
+                "{" => depth += 1,
+                "}" => {
+                    depth -= 1;
+                    if groupings.last() == Some(&depth) {
+                        groupings.pop();
+                    }
+                }
+                // Keywords start statements, skip identical words used as arguments
+                _ if i > 0 && !matches!(tokens[i - 1].as_str(), "{" | "}" | ";") => {}
+                "grouping" => groupings.push(depth),
+                keyword if DATA_NODES.contains(&keyword) => {
+                    let Some(name) = tokens.get(i + 1) else {
+                        continue;
+                    };
+                    if matches!(keyword, "anydata" | "anyxml") {
+                        self.opaque.insert(name.clone());
+                    }
+                    if groupings.is_empty() {
+                        nodes.insert(name.clone());
+                    } else {
+                        self.grouped.insert(name.clone());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Argument of the first statement with `keyword`
+fn argument<'a>(tokens: &'a [String], keyword: &str) -> Option<&'a str> {
+    tokens
+        .windows(2)
+        .find(|pair| pair[0] == keyword)
+        .map(|pair| pair[1].as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERFACES: &str = r#"module example-interfaces {
+  namespace "urn:example:interfaces";
+  prefix if;
+  include example-interfaces-state;
+  grouping counters {
+    leaf in-octets { type uint64; }
+  }
+  container interfaces {
+    description "list of interfaces";
+    list interface {
+      key name;
+      leaf name { type string; }
+      leaf enabled { type boolean; units container; }
+      anydata vendor-extensions;
+    }
+  }
+}"#;
+
+    const STATE: &str = r#"submodule example-interfaces-state {
+  belongs-to example-interfaces { prefix if; }
+  container interfaces-state {
+    uses counters;
+  }
+}"#;
+
+    fn checker() -> NameChecker {
+        NameChecker::new(&[
+            Module::new("example-interfaces", INTERFACES.to_string()),
+            Module::new("example-interfaces-state", STATE.to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_known_elements() {
+        let config = r#"<interfaces xmlns="urn:example:interfaces">
+  <interface><name>eth0</name><enabled>true</enabled>
+    <vendor-extensions><anything xmlns="urn:vendor"/></vendor-extensions>
+  </interface>
+</interfaces>
+<interfaces-state xmlns="urn:example:interfaces"><in-octets>0</in-octets></interfaces-state>"#;
+        checker().check(config).unwrap();
+    }
+
+    #[test]
+    fn test_unknown_element() {
+        let config = r#"<interfaces xmlns="urn:example:interfaces"><interface><nmae>eth0</nmae></interface></interfaces>"#;
+        let err = checker().check(config).unwrap_err();
+        assert!(err.to_string().contains("unknown element nmae"), "{}", err);
+    }
+
+    #[test]
+    fn test_unknown_namespace() {
+        let config = r#"<interfaces xmlns="urn:example:interface"/>"#;
+        let err = checker().check(config).unwrap_err();
+        assert!(err.to_string().contains("unknown namespace"), "{}", err);
+
+        let err = checker().check("<interfaces/>").unwrap_err();
+        assert!(err.to_string().contains("no namespace"), "{}", err);
+    }
+
+    #[test]
+    fn test_keyword_as_argument() {
+        // "container" as argument of units is not a data node
+        let err = checker()
+            .check(r#"<container xmlns="urn:example:interfaces"/>"#)
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("unknown element container"),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,195 @@
+//! NMDA datastore access with get-data, see [RFC8526](https://tools.ietf.org/html/rfc8526)
+use crate::error::Result;
+use crate::message::RawXml;
+use quick_xml::events::Event;
+use quick_xml::name::{Namespace, ResolveResult};
+use quick_xml::NsReader;
+use serde_derive::Serialize;
+
+const NMDA_NAMESPACE: &str = "urn:ietf:params:xml:ns:yang:ietf-netconf-nmda";
+const DATASTORES_NAMESPACE: &str = "urn:ietf:params:xml:ns:yang:ietf-datastores";
+const ORIGIN_NAMESPACE: &str = "urn:ietf:params:xml:ns:yang:ietf-origin";
+
+/// Get-data rpc reading any NMDA datastore, e.g. `operational`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct GetData {
+    #[serde(rename = "@xmlns")]
+    xmlns: String,
+    datastore: DatastoreIdentity,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    subtree_filter: Option<RawXml>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    xpath_filter: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    with_origin: Option<()>,
+}
+
+#[derive(Debug, Serialize)]
+struct DatastoreIdentity {
+    #[serde(rename = "@xmlns:ds")]
+    xmlns_ds: String,
+    #[serde(rename = "$text")]
+    identity: String,
+}
+
+impl GetData {
+    /// Reads `datastore` of ietf-datastores, e.g. `running` or `operational`
+    pub fn new(datastore: &str) -> GetData {
+        GetData {
+            xmlns: NMDA_NAMESPACE.to_string(),
+            datastore: DatastoreIdentity {
+                xmlns_ds: DATASTORES_NAMESPACE.to_string(),
+                identity: format!("ds:{}", datastore),
+            },
+            subtree_filter: None,
+            xpath_filter: None,
+            with_origin: None,
+        }
+    }
+
+    /// Selects nodes matching `xml`
+    pub fn subtree_filter(mut self, xml: &str) -> GetData {
+        self.subtree_filter = Some(RawXml(xml.to_string()));
+        self.xpath_filter = None;
+        self
+    }
+
+    /// Selects nodes matching XPath expression
+    pub fn xpath_filter(mut self, xpath: &str) -> GetData {
+        self.xpath_filter = Some(xpath.to_string());
+        self.subtree_filter = None;
+        self
+    }
+
+    /// Requests origin annotations of the nodes, only supported by `operational`,
+    /// parse them with [`origins`]
+    pub fn with_origin(mut self) -> GetData {
+        self.with_origin = Some(());
+        self
+    }
+
+    pub(crate) fn raw_xml(&self) -> Option<&str> {
+        self.subtree_filter.as_ref().map(|filter| filter.0.as_str())
+    }
+}
+
+/// Origin of a data node, e.g. whether it was configured or learned
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeOrigin {
+    /// Names of the node and its ancestors below `<data>`, e.g. `/interfaces/interface/mtu`
+    pub path: String,
+    /// Origin identity without prefix, e.g. `intended`, `learned` or `system`
+    pub origin: String,
+    /// Origin was annotated on the node itself instead of inherited from its parent
+    pub explicit: bool,
+}
+
+/// Origins of nodes in get-data reply requested [`GetData::with_origin`], in document
+/// order. Nodes without annotation inherit the origin of their parent, nodes without
+/// origin at all are left out.
+pub fn origins(reply: &str) -> Result<Vec<NodeOrigin>> {
+    let mut reader = NsReader::from_str(reply);
+    // Element names and origins below <data>, depth counts also the reply element
+    let mut path: Vec<(String, Option<String>)> = Vec::new();
+    let mut depth = 0;
+    let mut origins = Vec::new();
+    loop {
+        let (element, empty) = match reader.read_event()? {
+            Event::Start(element) => (element, false),
+            Event::Empty(element) => (element, true),
+            Event::End(_) => {
+                if depth > 2 {
+                    path.pop();
+                }
+                depth -= 1;
+                continue;
+            }
+            Event::Eof => return Ok(origins),
+            _ => continue,
+        };
+        depth += 1;
+        if depth > 2 {
+            let mut origin = None;
+            for attribute in element.attributes() {
+                let attribute = attribute.map_err(quick_xml::Error::from)?;
+                let (namespace, name) = reader.resolve_attribute(attribute.key);
+                if namespace == ResolveResult::Bound(Namespace(ORIGIN_NAMESPACE.as_bytes()))
+                    && name.as_ref() == b"origin"
+                {
+                    let value = attribute.unescape_value()?;
+                    let identity = value.rsplit(':').next().unwrap_or_default();
+                    origin = Some(identity.to_string());
+                }
+            }
+            let explicit = origin.is_some();
+            let origin = origin.or_else(|| path.last().and_then(|(_, origin)| origin.clone()));
+            let name = String::from_utf8_lossy(element.local_name().as_ref()).into_owned();
+            path.push((name, origin.clone()));
+            if let Some(origin) = origin {
+                let names: Vec<&str> = path.iter().map(|(name, _)| name.as_str()).collect();
+                origins.push(NodeOrigin {
+                    path: format!("/{}", names.join("/")),
+                    origin,
+                    explicit,
+                });
+            }
+        }
+        if empty {
+            if depth > 2 {
+                path.pop();
+            }
+            depth -= 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{Indent, Rpc, RpcContent};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_serialize_get_data() {
+        let rpc = Rpc::new(RpcContent::GetData(
+            GetData::new("operational")
+                .subtree_filter("<interfaces/>")
+                .with_origin(),
+        ));
+        assert!(rpc.to_xml(Indent::Compact).contains(
+            r#"<get-data xmlns="urn:ietf:params:xml:ns:yang:ietf-netconf-nmda"><datastore xmlns:ds="urn:ietf:params:xml:ns:yang:ietf-datastores">ds:operational</datastore><subtree-filter><interfaces/></subtree-filter><with-origin/></get-data>"#
+        ));
+    }
+
+    #[test]
+    fn test_origins() {
+        let reply = r#"
+<rpc-reply xmlns="urn:ietf:params:xml:ns:netconf:base:1.0" message-id="1">
+  <data xmlns="urn:ietf:params:xml:ns:yang:ietf-netconf-nmda"
+        xmlns:or="urn:ietf:params:xml:ns:yang:ietf-origin">
+    <interfaces xmlns="urn:ietf:params:xml:ns:yang:ietf-interfaces" or:origin="or:intended">
+      <interface>
+        <name>eth0</name>
+        <mtu or:origin="or:system">1500</mtu>
+      </interface>
+    </interfaces>
+    <system xmlns="urn:ietf:params:xml:ns:yang:ietf-system"><uptime/></system>
+  </data>
+</rpc-reply>"#;
+        let origin = |path: &str, origin: &str, explicit| NodeOrigin {
+            path: path.to_string(),
+            origin: origin.to_string(),
+            explicit,
+        };
+        assert_eq!(
+            origins(reply).unwrap(),
+            vec![
+                origin("/interfaces", "intended", true),
+                origin("/interfaces/interface", "intended", false),
+                origin("/interfaces/interface/name", "intended", false),
+                origin("/interfaces/interface/mtu", "system", true),
+            ]
+        );
+    }
+}
@@ -0,0 +1,86 @@
+use crate::error::Error;
+use crate::Connection;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Background thread sending [`Connection::ping`] on a shared connection
+/// whenever it has been idle for `interval`, for devices with short NETCONF
+/// idle timeouts. Thread stops on first failure or when `Pinger` is dropped.
+pub struct Pinger {
+    _stop: Sender<()>,
+    failures: Receiver<Error>,
+}
+
+impl Pinger {
+    pub fn start(connection: Arc<Mutex<Connection>>, interval: Duration) -> Pinger {
+        let (stop, stopped) = channel::<()>();
+        let (failed, failures) = channel();
+        thread::spawn(move || loop {
+            let wait = match connection.lock() {
+                Ok(connection) => interval.saturating_sub(connection.idle_time()),
+                Err(_) => return,
+            };
+            match stopped.recv_timeout(wait) {
+                Err(RecvTimeoutError::Timeout) => {}
+                _ => return,
+            }
+
+            let mut connection = match connection.lock() {
+                Ok(connection) => connection,
+                Err(_) => return,
+            };
+            if connection.idle_time() < interval {
+                continue;
+            }
+            if let Err(err) = connection.ping() {
+                error!(
+                    "Ping failed on session {}: {}",
+                    connection.session_id(),
+                    err
+                );
+                let _ = failed.send(err);
+                return;
+            }
+        });
+        Pinger {
+            _stop: stop,
+            failures,
+        }
+    }
+
+    /// Returns error if ping has failed, after which pinging has stopped
+    pub fn failure(&self) -> Option<Error> {
+        self.failures.try_recv().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interceptor::RpcInterceptor;
+    use crate::tests::ReversingTransport;
+
+    struct Count(Arc<Mutex<usize>>);
+
+    impl RpcInterceptor for Count {
+        fn on_send(&mut self, _rpc: &mut String) {
+            *self.0.lock().unwrap() += 1;
+        }
+    }
+
+    #[test]
+    fn test_pinger_pings_idle_connection() {
+        let pings = Arc::new(Mutex::new(0));
+        let mut connection = Connection::new(ReversingTransport::default()).unwrap();
+        connection.add_interceptor(Count(pings.clone()));
+
+        let connection = Arc::new(Mutex::new(connection));
+        let pinger = Pinger::start(connection, Duration::from_millis(10));
+        thread::sleep(Duration::from_millis(50));
+
+        assert!(*pings.lock().unwrap() > 0);
+        assert!(pinger.failure().is_none());
+    }
+}
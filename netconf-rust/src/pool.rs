@@ -0,0 +1,220 @@
+use crate::error::Result;
+use crate::Connection;
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+use std::sync::Mutex;
+
+type Connect = dyn Fn(&str) -> Result<Connection> + Send + Sync;
+type HealthCheck = dyn Fn(&mut Connection) -> Result<()> + Send + Sync;
+
+/// Pool of idle NETCONF sessions keyed by host, shared between threads.
+/// Sessions are opened with the `connect` function given to [`ConnectionPool::new`].
+pub struct ConnectionPool {
+    connect: Box<Connect>,
+    health_check: Option<Box<HealthCheck>>,
+    max_idle: usize,
+    idle: Mutex<HashMap<String, Vec<Connection>>>,
+}
+
+impl ConnectionPool {
+    pub fn new<F>(connect: F) -> ConnectionPool
+    where
+        F: Fn(&str) -> Result<Connection> + Send + Sync + 'static,
+    {
+        ConnectionPool {
+            connect: Box::new(connect),
+            health_check: None,
+            max_idle: 4,
+            idle: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Sets maximum number of idle sessions kept per host, default is 4
+    pub fn set_max_idle(&mut self, max_idle: usize) {
+        self.max_idle = max_idle;
+    }
+
+    /// Sets check run on idle session before it is checked out,
+    /// sessions failing the check are closed and replaced with a new one
+    pub fn set_health_check<F>(&mut self, health_check: F)
+    where
+        F: Fn(&mut Connection) -> Result<()> + Send + Sync + 'static,
+    {
+        self.health_check = Some(Box::new(health_check));
+    }
+
+    /// Opens sessions to `host` until there are `count` idle sessions
+    pub fn warm_up(&self, host: &str, count: usize) -> Result<()> {
+        let count = count.min(self.max_idle);
+        while self.idle_count(host) < count {
+            let connection = self.open(host)?;
+            self.release(host, connection);
+        }
+        Ok(())
+    }
+
+    /// Returns number of idle sessions to `host`
+    pub fn idle_count(&self, host: &str) -> usize {
+        let idle = self.idle.lock().unwrap();
+        idle.get(host).map_or(0, Vec::len)
+    }
+
+    /// Takes idle session to `host` from the pool, or opens a new one.
+    /// Session is returned to the pool when [`PooledConnection`] is dropped.
+    pub fn checkout(&self, host: &str) -> Result<PooledConnection<'_>> {
+        while let Some(mut connection) = self.take_idle(host) {
+            match &self.health_check {
+                Some(check) => match check(&mut connection) {
+                    Ok(_) => return Ok(self.pooled(host, connection)),
+                    Err(err) => {
+                        debug!(target: host, "Discarding pooled session: {}", err);
+                        self.discard(connection);
+                    }
+                },
+                None => return Ok(self.pooled(host, connection)),
+            }
+        }
+        let connection = self.open(host)?;
+        Ok(self.pooled(host, connection))
+    }
+
+    fn open(&self, host: &str) -> Result<Connection> {
+        let connection = (self.connect)(host)?;
+        #[cfg(feature = "tracing")]
+        connection.set_host(host);
+        Ok(connection)
+    }
+
+    fn pooled(&self, host: &str, connection: Connection) -> PooledConnection<'_> {
+        PooledConnection {
+            pool: self,
+            host: host.to_string(),
+            connection: Some(connection),
+        }
+    }
+
+    fn take_idle(&self, host: &str) -> Option<Connection> {
+        let mut idle = self.idle.lock().unwrap();
+        idle.get_mut(host).and_then(Vec::pop)
+    }
+
+    /// Returns session to the pool, closed, subscribed and sessions with unclaimed
+    /// replies are discarded instead
+    fn release(&self, host: &str, connection: Connection) {
+        if connection.is_broken() {
+            return;
+        }
+        if !connection.is_reusable() {
+            debug!(target: host, "Discarding session in use instead of pooling it");
+            self.discard(connection);
+            return;
+        }
+        let mut idle = self.idle.lock().unwrap();
+        let connections = idle.entry(host.to_string()).or_default();
+        if connections.len() < self.max_idle {
+            connections.push(connection);
+        } else {
+            drop(idle);
+            self.discard(connection);
+        }
+    }
+
+    fn discard(&self, connection: Connection) {
+        if let Err(err) = connection.shutdown() {
+            debug!("Failed to close pooled session: {}", err);
+        }
+    }
+}
+
+/// Session checked out from [`ConnectionPool`], returned to the pool when dropped
+pub struct PooledConnection<'a> {
+    pool: &'a ConnectionPool,
+    host: String,
+    connection: Option<Connection>,
+}
+
+impl PooledConnection<'_> {
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// Removes session from the pool, it will not be returned when dropped
+    pub fn detach(mut self) -> Connection {
+        self.connection.take().unwrap()
+    }
+}
+
+impl Deref for PooledConnection<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Self::Target {
+        self.connection.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PooledConnection<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.connection.as_mut().unwrap()
+    }
+}
+
+impl Drop for PooledConnection<'_> {
+    fn drop(&mut self) {
+        if let Some(connection) = self.connection.take() {
+            self.pool.release(&self.host, connection);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{Rpc, RpcContent};
+    use crate::tests::ReversingTransport;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_checkout_and_return() {
+        let pool = ConnectionPool::new(|_| Connection::new(ReversingTransport::default()));
+        pool.warm_up("router1", 2).unwrap();
+        assert_eq!(pool.idle_count("router1"), 2);
+        {
+            let mut first = pool.checkout("router1").unwrap();
+            let _second = pool.checkout("router1").unwrap();
+            let _third = pool.checkout("router1").unwrap();
+            assert_eq!(pool.idle_count("router1"), 0);
+            first.get_config("running").unwrap();
+        }
+        assert_eq!(pool.idle_count("router1"), 3);
+
+        let detached = pool.checkout("router1").unwrap().detach();
+        assert_eq!(detached.session_id(), 4);
+        assert_eq!(pool.idle_count("router1"), 2);
+    }
+
+    #[test]
+    fn test_sessions_in_use_are_not_returned() {
+        let pool = ConnectionPool::new(|_| Connection::new(ReversingTransport::default()));
+
+        pool.checkout("router1").unwrap().close_session().unwrap();
+        assert_eq!(pool.idle_count("router1"), 0, "closed session");
+
+        pool.checkout("router1")
+            .unwrap()
+            .create_subscription(None)
+            .unwrap();
+        assert_eq!(pool.idle_count("router1"), 0, "subscribed session");
+
+        pool.checkout("router1")
+            .unwrap()
+            .send_rpc(Rpc::new(RpcContent::CloseSession))
+            .unwrap();
+        assert_eq!(pool.idle_count("router1"), 0, "rpc in flight");
+
+        {
+            let mut connection = pool.checkout("router1").unwrap();
+            connection.ping().unwrap();
+        }
+        assert_eq!(pool.idle_count("router1"), 1);
+    }
+}
@@ -0,0 +1,196 @@
+//! Vendor specific behaviour consulted by [`Connection`](crate::Connection), so that
+//! quirks are handled in one place instead of by every consumer.
+use std::sync::Arc;
+
+/// Port of NETCONF over SSH, see [RFC6242](https://tools.ietf.org/html/rfc6242#section-3)
+pub const NETCONF_PORT: u16 = 830;
+
+const XML_NS_CAPABILITY_PREFIX: &str = "urn:ietf:params:xml:ns:netconf:";
+const CAPABILITY_PREFIX: &str = "urn:ietf:params:netconf:";
+
+/// How running configuration is saved to persist over reboot, see
+/// [`Connection::save_config`](crate::Connection::save_config)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SaveConfig {
+    /// Copy-config from running to startup, requires `:startup`
+    CopyToStartup,
+    /// Vendor operation element, e.g. `<save-config/>`
+    Operation(String),
+    /// Committed configuration persists without saving
+    Persistent,
+}
+
+/// Quirks of a device family. Every method has a default for standard compliant servers.
+pub trait DeviceProfile: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Advertise only base:1.0 in client hello, forcing end-of-message framing
+    fn base_1_0_only(&self) -> bool {
+        false
+    }
+
+    /// Port connected when none is given for the host
+    fn default_port(&self) -> u16 {
+        NETCONF_PORT
+    }
+
+    /// Fixes capabilities advertised in server hello
+    fn fix_capabilities(&self, _capabilities: &mut Vec<String>) {}
+
+    /// Namespace of vendor `prefix`, used to declare prefixes of XPath filters,
+    /// see [`Filter::resolve_prefixes`](crate::message::Filter::resolve_prefixes)
+    fn namespace(&self, _prefix: &str) -> Option<String> {
+        None
+    }
+
+    /// Fixes received reply or notification before it is parsed
+    fn fix_message(&self, _message: &mut String) {}
+
+    /// How [`Connection::save_config`](crate::Connection::save_config) persists running
+    /// configuration, copy to startup by default
+    fn save_config(&self) -> SaveConfig {
+        SaveConfig::CopyToStartup
+    }
+}
+
+/// Juniper Junos
+#[derive(Debug, Default)]
+pub struct Junos;
+
+impl DeviceProfile for Junos {
+    fn name(&self) -> &str {
+        "junos"
+    }
+
+    /// Junos advertises some standard capabilities only in their namespace form,
+    /// `urn:ietf:params:xml:ns:netconf:...`; the canonical form is added for them.
+    fn fix_capabilities(&self, capabilities: &mut Vec<String>) {
+        let missing: Vec<String> = capabilities
+            .iter()
+            .filter_map(|c| c.strip_prefix(XML_NS_CAPABILITY_PREFIX))
+            .map(|c| format!("{}{}", CAPABILITY_PREFIX, c))
+            .filter(|c| !capabilities.contains(c))
+            .collect();
+        capabilities.extend(missing);
+    }
+
+    /// Namespace of `junos` prefix contains the release, e.g.
+    /// `http://xml.juniper.net/junos/23.4R1/junos`, so it is not resolved
+    fn namespace(&self, prefix: &str) -> Option<String> {
+        (prefix == "xnm").then(|| "http://xml.juniper.net/xnm/1.1/xnm".to_string())
+    }
+
+    /// Commit writes the configuration to disk
+    fn save_config(&self) -> SaveConfig {
+        SaveConfig::Persistent
+    }
+}
+
+/// Nokia SR OS in model-driven mode
+#[derive(Debug, Default)]
+pub struct SrOs;
+
+impl DeviceProfile for SrOs {
+    fn name(&self) -> &str {
+        "sros"
+    }
+
+    fn namespace(&self, prefix: &str) -> Option<String> {
+        let namespace = match prefix {
+            "nokia-conf" => "urn:nokia.com:sros:ns:yang:sr:conf",
+            "nokia-state" => "urn:nokia.com:sros:ns:yang:sr:state",
+            _ => return None,
+        };
+        Some(namespace.to_string())
+    }
+}
+
+/// Cisco IOS-XR
+#[derive(Debug, Default)]
+pub struct IosXr;
+
+impl DeviceProfile for IosXr {
+    fn name(&self) -> &str {
+        "iosxr"
+    }
+
+    /// Native module names are used as prefixes, e.g. `Cisco-IOS-XR-ifmgr-cfg`
+    fn namespace(&self, prefix: &str) -> Option<String> {
+        prefix
+            .starts_with("Cisco-IOS-XR-")
+            .then(|| format!("http://cisco.com/ns/yang/{}", prefix))
+    }
+
+    /// Committed configuration persists, there is no startup configuration
+    fn save_config(&self) -> SaveConfig {
+        SaveConfig::Persistent
+    }
+}
+
+/// Returns built-in profile of the device advertising `capabilities`
+pub fn detect(capabilities: &[String]) -> Option<Arc<dyn DeviceProfile>> {
+    let advertises = |marker: &str| capabilities.iter().any(|c| c.contains(marker));
+    if advertises("xml.juniper.net") {
+        Some(Arc::new(Junos))
+    } else if advertises("urn:nokia.com:sros:") {
+        Some(Arc::new(SrOs))
+    } else if advertises("cisco.com/ns/yang/Cisco-IOS-XR-") {
+        Some(Arc::new(IosXr))
+    } else {
+        None
+    }
+}
+
+/// Returns built-in profile by its name, e.g. from configuration
+pub fn by_name(name: &str) -> Option<Arc<dyn DeviceProfile>> {
+    match name {
+        "junos" => Some(Arc::new(Junos)),
+        "sros" => Some(Arc::new(SrOs)),
+        "iosxr" => Some(Arc::new(IosXr)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_detect() {
+        let junos = vec!["http://xml.juniper.net/netconf/junos/1.0".to_string()];
+        assert_eq!(detect(&junos).unwrap().name(), "junos");
+        let xr = vec![
+            "http://cisco.com/ns/yang/Cisco-IOS-XR-ifmgr-cfg?module=Cisco-IOS-XR-ifmgr-cfg"
+                .to_string(),
+        ];
+        assert_eq!(detect(&xr).unwrap().name(), "iosxr");
+        assert!(detect(&["urn:ietf:params:netconf:base:1.1".to_string()]).is_none());
+    }
+
+    #[test]
+    fn test_junos_capabilities() {
+        let mut capabilities = vec![
+            "urn:ietf:params:netconf:base:1.0".to_string(),
+            "urn:ietf:params:xml:ns:netconf:base:1.0".to_string(),
+            "urn:ietf:params:xml:ns:netconf:capability:candidate:1.0".to_string(),
+        ];
+        Junos.fix_capabilities(&mut capabilities);
+        assert_eq!(capabilities.len(), 4);
+        assert!(
+            capabilities.contains(&"urn:ietf:params:netconf:capability:candidate:1.0".to_string())
+        );
+    }
+
+    #[test]
+    fn test_namespaces() {
+        assert_eq!(
+            IosXr.namespace("Cisco-IOS-XR-ifmgr-cfg").as_deref(),
+            Some("http://cisco.com/ns/yang/Cisco-IOS-XR-ifmgr-cfg")
+        );
+        assert_eq!(SrOs.namespace("junos"), None);
+        assert_eq!(Junos.namespace("junos"), None);
+        assert_eq!(by_name("sros").unwrap().default_port(), NETCONF_PORT);
+        assert!(by_name("unknown").is_none());
+    }
+}
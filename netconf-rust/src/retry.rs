@@ -0,0 +1,117 @@
+//! Retrying of rpcs failing for transient reasons, see [`Connection::set_retry_policy`](crate::Connection::set_retry_policy)
+use crate::error::Error;
+use crate::message::ErrorTag;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+const MAX_DELAY: Duration = Duration::from_secs(60);
+
+type Classifier = Arc<dyn Fn(&Error) -> bool + Send + Sync>;
+
+/// How many times and how long apart failed rpcs are retried
+#[derive(Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    delay: Duration,
+    max_delay: Duration,
+    retryable: Classifier,
+}
+
+impl RetryPolicy {
+    /// Retries errors [`is_transient`] until rpc has been sent `max_attempts` times,
+    /// waiting one second before the first retry
+    pub fn new(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            delay: Duration::from_secs(1),
+            max_delay: MAX_DELAY,
+            retryable: Arc::new(is_transient),
+        }
+    }
+
+    /// Delay before the first retry, doubled after each retry
+    pub fn delay(mut self, delay: Duration) -> RetryPolicy {
+        self.delay = delay;
+        self
+    }
+
+    /// Upper bound of the growing delay, one minute by default
+    pub fn max_delay(mut self, max_delay: Duration) -> RetryPolicy {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Replaces [`is_transient`] with `retryable` deciding which errors are retried.
+    /// Errors breaking the session, e.g. timeouts, are never retried.
+    pub fn retry_if(
+        mut self,
+        retryable: impl Fn(&Error) -> bool + Send + Sync + 'static,
+    ) -> RetryPolicy {
+        self.retryable = Arc::new(retryable);
+        self
+    }
+
+    /// Delay before retry after failed `attempt`, counted from 1
+    pub(crate) fn backoff(&self, attempt: u32) -> Option<Duration> {
+        (attempt < self.max_attempts).then(|| {
+            self.delay
+                .saturating_mul(2u32.saturating_pow(attempt - 1))
+                .min(self.max_delay)
+        })
+    }
+
+    pub(crate) fn is_retryable(&self, err: &Error) -> bool {
+        (self.retryable)(err)
+    }
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("delay", &self.delay)
+            .field("max_delay", &self.max_delay)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Returns true for rpc-errors `lock-denied`, `resource-denied` and `in-use`, which
+/// may succeed when tried again later. Timeouts are not transient, since the rpc may
+/// have been executed and the session is broken.
+pub fn is_transient(err: &Error) -> bool {
+    match err {
+        Error::Netconf(reply) => reply.errors().iter().any(|e| {
+            matches!(
+                e.tag(),
+                ErrorTag::LockDenied | ErrorTag::ResourceDenied | ErrorTag::InUse
+            )
+        }),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy::new(4)
+            .delay(Duration::from_secs(1))
+            .max_delay(Duration::from_secs(3));
+        assert_eq!(policy.backoff(1), Some(Duration::from_secs(1)));
+        assert_eq!(policy.backoff(2), Some(Duration::from_secs(2)));
+        assert_eq!(policy.backoff(3), Some(Duration::from_secs(3)));
+        assert_eq!(policy.backoff(4), None);
+    }
+
+    #[test]
+    fn test_is_transient() {
+        assert!(!is_transient(&Error::Timeout));
+        assert!(!is_transient(&Error::SessionClosed));
+        let policy = RetryPolicy::new(2).retry_if(|err| matches!(err, Error::SessionClosed));
+        assert!(policy.is_retryable(&Error::SessionClosed));
+    }
+}
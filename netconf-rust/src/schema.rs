@@ -0,0 +1,441 @@
+//! On-disk cache of YANG modules downloaded with `<get-schema>`, see
+//! [RFC6022](https://tools.ietf.org/html/rfc6022#section-3.1)
+use crate::error::Result;
+use crate::message::{RawXml, Rpc, RpcContent};
+use crate::monitoring::MONITORING_NAMESPACE;
+use crate::Connection;
+use quick_xml::de::from_str;
+use serde_derive::Deserialize;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// YANG module source with its name and revision
+#[derive(Debug, Clone)]
+pub struct Module {
+    name: String,
+    revision: Option<String>,
+    source: String,
+}
+
+/// Module referenced by `import` or `include` statement
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Dependency {
+    pub name: String,
+    /// `revision-date` of the statement, any revision if `None`
+    pub revision: Option<String>,
+}
+
+impl Module {
+    pub fn new(name: &str, source: String) -> Module {
+        Module {
+            name: name.to_string(),
+            revision: latest_revision(&source),
+            source,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Most recent `revision` statement of the module
+    pub fn revision(&self) -> Option<&str> {
+        self.revision.as_deref()
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Modules and submodules referenced with `import` and `include`
+    pub fn dependencies(&self) -> Vec<Dependency> {
+        let tokens = tokenize(&self.source);
+        let mut dependencies = Vec::new();
+        for (i, token) in tokens.iter().enumerate() {
+            if !matches!(token.as_str(), "import" | "include") {
+                continue;
+            }
+            let Some(name) = tokens.get(i + 1) else {
+                continue;
+            };
+            let mut revision = None;
+            if tokens.get(i + 2).is_some_and(|t| t == "{") {
+                let mut depth = 0;
+                for (j, token) in tokens.iter().enumerate().skip(i + 2) {
+                    match token.as_str() {
+                        "{" => depth += 1,
+                        "}" => depth -= 1,
+                        "revision-date" if depth == 1 => revision = tokens.get(j + 1).cloned(),
+                        _ => {}
+                    }
+                    if depth == 0 {
+                        break;
+                    }
+                }
+            }
+            dependencies.push(Dependency {
+                name: name.clone(),
+                revision,
+            });
+        }
+        dependencies
+    }
+
+    fn key(&self) -> String {
+        key(&self.name, self.revision())
+    }
+}
+
+/// Cache of YANG modules stored in a directory as `module@revision.yang` files.
+///
+/// Cache has no in-memory state, so it can be shared across connections and threads,
+/// e.g. in an `Arc`, and between processes using the same directory.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    /// Opens cache in `dir`, creating the directory if missing
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Cache> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Cache { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns cached module, the latest cached revision if `revision` is `None`
+    pub fn get(&self, name: &str, revision: Option<&str>) -> Result<Option<Module>> {
+        let path = match revision {
+            Some(revision) => self.path(&key(name, Some(revision))),
+            None => match self.latest(name)? {
+                Some(path) => path,
+                None => return Ok(None),
+            },
+        };
+        match fs::read_to_string(&path) {
+            Ok(source) => Ok(Some(Module::new(name, source))),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Stores module under its name and revision
+    pub fn insert(&self, module: &Module) -> Result<()> {
+        let path = self.path(&module.key());
+        // Write to a temporary file first so that concurrent readers never see partial module
+        let temporary = self
+            .dir
+            .join(format!(".{}.{}", module.key(), uuid::Uuid::new_v4()));
+        fs::write(&temporary, &module.source)?;
+        fs::rename(&temporary, path)?;
+        Ok(())
+    }
+
+    /// Returns module from cache or downloads it with `<get-schema>`. Without `revision`
+    /// the revision advertised by the server in its hello is used, or the latest cached
+    /// one if the server does not advertise the module, e.g. for submodules.
+    pub fn fetch(
+        &self,
+        connection: &mut Connection,
+        name: &str,
+        revision: Option<&str>,
+    ) -> Result<Module> {
+        let advertised = advertised_revision(connection.capabilities(), name);
+        let revision = revision.or(advertised.as_deref());
+        if let Some(module) = self.get(name, revision)? {
+            return Ok(module);
+        }
+        debug!("Downloading schema {}", key(name, revision));
+        let module = Module::new(name, get_schema(connection, name, revision)?);
+        self.insert(&module)?;
+        Ok(module)
+    }
+
+    /// Fetches module and all modules it imports or includes, transitively
+    pub fn fetch_with_dependencies(
+        &self,
+        connection: &mut Connection,
+        name: &str,
+        revision: Option<&str>,
+    ) -> Result<Vec<Module>> {
+        let mut modules = Vec::new();
+        let mut seen = HashSet::new();
+        let mut pending = vec![Dependency {
+            name: name.to_string(),
+            revision: revision.map(str::to_string),
+        }];
+        while let Some(dependency) = pending.pop() {
+            if !seen.insert(dependency.clone()) {
+                continue;
+            }
+            let module =
+                self.fetch(connection, &dependency.name, dependency.revision.as_deref())?;
+            pending.extend(module.dependencies());
+            modules.push(module);
+        }
+        Ok(modules)
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.yang", key))
+    }
+
+    fn latest(&self, name: &str) -> Result<Option<PathBuf>> {
+        let prefix = format!("{}@", name);
+        let mut latest: Option<(String, PathBuf)> = None;
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let Some(stem) = path
+                .extension()
+                .filter(|extension| *extension == "yang")
+                .and_then(|_| path.file_stem())
+                .and_then(|stem| stem.to_str())
+            else {
+                continue;
+            };
+            let revision = match stem.strip_prefix(&prefix) {
+                Some(revision) => revision.to_string(),
+                None if stem == name => String::new(),
+                None => continue,
+            };
+            if latest.as_ref().is_none_or(|(latest, _)| revision > *latest) {
+                latest = Some((revision, path));
+            }
+        }
+        Ok(latest.map(|(_, path)| path))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct GetSchemaReply {
+    data: SchemaData,
+}
+
+#[derive(Debug, Deserialize)]
+struct SchemaData {
+    #[serde(rename = "$text", default)]
+    source: String,
+}
+
+fn get_schema(connection: &mut Connection, name: &str, revision: Option<&str>) -> Result<String> {
+    let version = revision
+        .map(|revision| format!("<version>{}</version>", revision))
+        .unwrap_or_default();
+    let rpc = Rpc::new(RpcContent::Raw(RawXml(format!(
+        r#"<get-schema xmlns="{}"><identifier>{}</identifier>{}<format>yang</format></get-schema>"#,
+        MONITORING_NAMESPACE, name, version
+    ))));
+    let reply = connection.execute(rpc)?;
+    let reply: GetSchemaReply = from_str(reply.raw())?;
+    Ok(reply.data.source)
+}
+
+/// Revision of `module` from `?module=name&revision=date` capability
+fn advertised_revision(capabilities: &[String], module: &str) -> Option<String> {
+    capabilities.iter().find_map(|capability| {
+        let (_, query) = capability.split_once('?')?;
+        let mut name = None;
+        let mut revision = None;
+        for parameter in query.split(['&', ';']) {
+            match parameter.split_once('=') {
+                Some(("module", value)) => name = Some(value),
+                Some(("revision", value)) => revision = Some(value),
+                _ => {}
+            }
+        }
+        (name? == module).then(|| revision.map(str::to_string))?
+    })
+}
+
+fn key(name: &str, revision: Option<&str>) -> String {
+    match revision {
+        Some(revision) => format!("{}@{}", name, revision),
+        None => name.to_string(),
+    }
+}
+
+fn latest_revision(source: &str) -> Option<String> {
+    let tokens = tokenize(source);
+    tokens
+        .windows(2)
+        .filter(|pair| pair[0] == "revision")
+        .map(|pair| pair[1].clone())
+        .max()
+}
+
+/// Splits YANG source to statement keywords, arguments and braces, skipping comments
+/// and unquoting strings. Enough for finding top level statements, not a YANG parser.
+pub(crate) fn tokenize(source: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            '"' | '\'' => {
+                let mut token = String::new();
+                while let Some(next) = chars.next() {
+                    match next {
+                        '\\' if c == '"' => token.extend(chars.next()),
+                        next if next == c => break,
+                        next => token.push(next),
+                    }
+                }
+                tokens.push(token);
+            }
+            '{' | '}' | ';' => tokens.push(c.to_string()),
+            c if c.is_whitespace() => {}
+            c => {
+                let mut token = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || matches!(next, '{' | '}' | ';' | '"' | '\'') {
+                        break;
+                    }
+                    token.push(next);
+                    chars.next();
+                }
+                tokens.push(token);
+            }
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::mock::MockTransport;
+    use pretty_assertions::assert_eq;
+
+    const MODULE: &str = r#"module example {
+  namespace "urn:example";
+  prefix ex;
+  // import ignored-comment { prefix ic; }
+  import ietf-inet-types { prefix inet; revision-date 2013-07-15; }
+  import "ietf-yang-types" {
+    prefix yang;
+  }
+  include example-sub;
+  /* revision 2099-01-01; */
+  revision 2024-05-01 { description "Second"; }
+  revision 2023-01-01 { description "First"; }
+}"#;
+
+    fn temp_cache() -> Cache {
+        let dir = std::env::temp_dir().join(format!("netconf-schema-{}", uuid::Uuid::new_v4()));
+        Cache::new(dir).unwrap()
+    }
+
+    #[test]
+    fn test_module_dependencies() {
+        let module = Module::new("example", MODULE.to_string());
+        assert_eq!(module.revision(), Some("2024-05-01"));
+        assert_eq!(
+            module.dependencies(),
+            vec![
+                Dependency {
+                    name: "ietf-inet-types".to_string(),
+                    revision: Some("2013-07-15".to_string()),
+                },
+                Dependency {
+                    name: "ietf-yang-types".to_string(),
+                    revision: None,
+                },
+                Dependency {
+                    name: "example-sub".to_string(),
+                    revision: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cache_get_latest() {
+        let cache = temp_cache();
+        cache
+            .insert(&Module::new(
+                "example",
+                "module example { revision 2023-01-01; }".to_string(),
+            ))
+            .unwrap();
+        cache
+            .insert(&Module::new("example", MODULE.to_string()))
+            .unwrap();
+
+        let latest = cache.get("example", None).unwrap().unwrap();
+        assert_eq!(latest.revision(), Some("2024-05-01"));
+        let older = cache.get("example", Some("2023-01-01")).unwrap().unwrap();
+        assert_eq!(older.revision(), Some("2023-01-01"));
+        assert!(cache.get("example", Some("2020-01-01")).unwrap().is_none());
+        assert!(cache.get("other", None).unwrap().is_none());
+        fs::remove_dir_all(cache.dir()).unwrap();
+    }
+
+    #[test]
+    fn test_fetch_with_dependencies() {
+        let schema = |source: &str| {
+            format!(
+                r#"<data xmlns="urn:ietf:params:xml:ns:yang:ietf-netconf-monitoring">{}</data>"#,
+                source.replace('<', "&lt;")
+            )
+        };
+        let mut transport = MockTransport::scripted([
+            (
+                "<identifier>example</identifier><version>2024-05-01</version>",
+                schema(MODULE),
+            ),
+            (
+                "<identifier>example-sub</identifier><format>",
+                schema("submodule example-sub { belongs-to example { prefix ex; } }"),
+            ),
+            (
+                "<identifier>ietf-yang-types</identifier><version>2013-07-15</version>",
+                schema("module ietf-yang-types { revision 2013-07-15; }"),
+            ),
+            (
+                "<identifier>ietf-inet-types</identifier><version>2013-07-15</version>",
+                schema("module ietf-inet-types { revision 2013-07-15; }"),
+            ),
+        ]);
+        transport.set_capabilities([
+            "urn:example?module=example&amp;revision=2024-05-01",
+            "urn:ietf:params:xml:ns:yang:ietf-yang-types?module=ietf-yang-types&amp;revision=2013-07-15",
+        ]);
+        let mut connection = Connection::new(transport).unwrap();
+        let cache = temp_cache();
+
+        let modules = cache
+            .fetch_with_dependencies(&mut connection, "example", None)
+            .unwrap();
+        assert_eq!(modules.len(), 4);
+        assert_eq!(modules[0].source(), MODULE);
+        assert!(cache.dir().join("ietf-inet-types@2013-07-15.yang").exists());
+        assert!(cache.dir().join("example-sub.yang").exists());
+
+        // Served from cache, scripted transport panics on further rpcs
+        let module = cache.fetch(&mut connection, "example", None).unwrap();
+        assert_eq!(module.revision(), Some("2024-05-01"));
+        fs::remove_dir_all(cache.dir()).unwrap();
+    }
+}
@@ -0,0 +1,238 @@
+//! Minimal NETCONF server over plain TCP for integration tests, connect with
+//! [`TcpTransport`](crate::transport::tcp::TcpTransport). Exchanges hello, switches to
+//! chunked framing when both peers support base:1.1 and dispatches rpcs to handlers
+//! by operation name. Close-session is handled by the server.
+
+use crate::error::{Error, Result};
+use crate::framer::Framer;
+use crate::message::{self, ErrorTag, ErrorType, Hello};
+use crate::BASE_1_1_CAPABILITY;
+use quick_xml::de::from_str;
+use quick_xml::escape::escape;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::collections::HashMap;
+use std::io;
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+const BASE_NAMESPACE: &str = "urn:ietf:params:xml:ns:netconf:base:1.0";
+
+/// Handles operation element of rpc, returning contents of the reply, e.g. `<data>` element.
+/// Empty contents are replied with `<ok/>`.
+pub type Handler =
+    dyn Fn(&str) -> std::result::Result<String, Box<message::Error>> + Send + Sync + 'static;
+
+pub struct Server {
+    listener: TcpListener,
+    capabilities: Vec<String>,
+    handlers: HashMap<String, Box<Handler>>,
+}
+
+impl Server {
+    /// Listens on `addr`, e.g. `127.0.0.1:0` for any free port
+    pub fn bind(addr: impl ToSocketAddrs) -> Result<Server> {
+        Ok(Server {
+            listener: TcpListener::bind(addr)?,
+            capabilities: Vec::new(),
+            handlers: HashMap::new(),
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Advertises capability in hello in addition to base:1.0 and base:1.1
+    pub fn capability(mut self, capability: &str) -> Server {
+        self.capabilities.push(capability.to_string());
+        self
+    }
+
+    /// Handles rpcs with operation `name`, e.g. `get-config`. Other operations
+    /// are replied with operation-not-supported error.
+    pub fn handler<F>(mut self, name: &str, handler: F) -> Server
+    where
+        F: Fn(&str) -> std::result::Result<String, Box<message::Error>> + Send + Sync + 'static,
+    {
+        self.handlers.insert(name.to_string(), Box::new(handler));
+        self
+    }
+
+    /// Accepts connections until listener fails, each session in its own thread
+    pub fn serve(self) -> Result<()> {
+        let server = Arc::new(self);
+        let session_ids = AtomicU64::new(1);
+        for stream in server.listener.incoming() {
+            let stream = stream?;
+            let server = Arc::clone(&server);
+            let session_id = session_ids.fetch_add(1, Ordering::Relaxed);
+            thread::spawn(move || {
+                if let Err(err) = server.session(stream, session_id) {
+                    warn!("Session {} failed: {}", session_id, err);
+                }
+            });
+        }
+        Ok(())
+    }
+
+    /// Serves in background thread, returning address to connect to
+    pub fn spawn(self) -> Result<SocketAddr> {
+        let addr = self.local_addr()?;
+        thread::spawn(move || self.serve());
+        Ok(addr)
+    }
+
+    fn session(&self, mut stream: TcpStream, session_id: u64) -> Result<()> {
+        let mut framer = Framer::new();
+        let hello = Hello::server(session_id, &self.capabilities);
+        framer.write_xml(&hello.to_string(), &mut stream)?;
+        let client: Hello = from_str(&framer.read_xml(&mut stream)?)?;
+        if client.has_capability(BASE_1_1_CAPABILITY.to_string()) {
+            framer.upgrade();
+        }
+
+        loop {
+            let message = match framer.read_xml(&mut stream) {
+                Err(Error::Io(err)) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                message => message?,
+            };
+            let Some(rpc) = parse_rpc(&message) else {
+                let error = rpc_error(ErrorTag::MalformedMessage, "malformed rpc");
+                framer.write_xml(&reply(None, Err(error)), &mut stream)?;
+                continue;
+            };
+            if rpc.operation == "close-session" {
+                framer.write_xml(&reply(rpc.message_id, Ok(String::new())), &mut stream)?;
+                return Ok(());
+            }
+            let result = match self.handlers.get(&rpc.operation) {
+                Some(handler) => handler(rpc.xml),
+                None => Err(rpc_error(
+                    ErrorTag::OperationNotSupported,
+                    &format!("operation {} is not supported", rpc.operation),
+                )),
+            };
+            framer.write_xml(&reply(rpc.message_id, result), &mut stream)?;
+        }
+    }
+}
+
+struct ParsedRpc<'a> {
+    message_id: Option<String>,
+    /// Local name of the operation element
+    operation: String,
+    /// Operation element
+    xml: &'a str,
+}
+
+fn parse_rpc(message: &str) -> Option<ParsedRpc<'_>> {
+    let mut reader = Reader::from_str(message);
+    let mut message_id = None;
+    let mut start = None;
+    loop {
+        match reader.read_event().ok()? {
+            Event::Start(element) if start.is_none() => {
+                if element.local_name().as_ref() != b"rpc" {
+                    return None;
+                }
+                message_id = element
+                    .try_get_attribute("message-id")
+                    .ok()?
+                    .and_then(|attribute| attribute.unescape_value().ok())
+                    .map(|value| value.into_owned());
+                start = Some(reader.buffer_position() as usize);
+            }
+            Event::Start(element) | Event::Empty(element) => {
+                let operation = String::from_utf8(element.local_name().as_ref().to_vec()).ok()?;
+                let end = message.rfind("</")?;
+                let xml = message.get(start?..end)?.trim();
+                return Some(ParsedRpc {
+                    message_id,
+                    operation,
+                    xml,
+                });
+            }
+            Event::Eof => return None,
+            _ => {}
+        }
+    }
+}
+
+fn rpc_error(tag: ErrorTag, message: &str) -> Box<message::Error> {
+    Box::new(message::Error::new(ErrorType::Protocol, tag, message))
+}
+
+fn reply(
+    message_id: Option<String>,
+    result: std::result::Result<String, Box<message::Error>>,
+) -> String {
+    let body = match result {
+        Ok(body) if body.trim().is_empty() => "<ok/>".to_string(),
+        Ok(body) => body,
+        // Serializing struct of strings and enums can't fail
+        Err(error) => quick_xml::se::to_string(&error).unwrap(),
+    };
+    let message_id = message_id
+        .map(|id| format!(r#" message-id="{}""#, escape(&id)))
+        .unwrap_or_default();
+    format!(
+        r#"<rpc-reply xmlns="{}"{}>{}</rpc-reply>"#,
+        BASE_NAMESPACE, message_id, body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::tcp::TcpTransport;
+    use crate::Connection;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_server() {
+        let addr = Server::bind("127.0.0.1:0")
+            .unwrap()
+            .capability("urn:ietf:params:netconf:capability:candidate:1.0")
+            .handler("get-config", |rpc| {
+                assert!(rpc.starts_with("<get-config>"));
+                Ok("<data><system><hostname>r1</hostname></system></data>".to_string())
+            })
+            .handler("lock", |_| {
+                Err(rpc_error(ErrorTag::LockDenied, "locked by session 7"))
+            })
+            .spawn()
+            .unwrap();
+
+        let transport = TcpTransport::dial(addr).unwrap();
+        let mut connection = Connection::new(transport).unwrap();
+        assert_eq!(connection.session_id(), 1);
+        assert!(connection
+            .capabilities()
+            .iter()
+            .any(|capability| capability.ends_with("candidate:1.0")));
+
+        let reply = connection.get_config("running").unwrap();
+        assert!(reply.raw().contains("<hostname>r1</hostname>"));
+        assert!(matches!(connection.lock("running"), Err(Error::Netconf(_))));
+        assert!(matches!(
+            connection.discard_changes(),
+            Err(Error::Netconf(_))
+        ));
+        connection.close_session().unwrap();
+    }
+
+    #[test]
+    fn test_parse_rpc() {
+        let rpc = parse_rpc(
+            r#"<nc:rpc xmlns:nc="urn:ietf:params:xml:ns:netconf:base:1.0" message-id="1"><nc:get/></nc:rpc>"#,
+        )
+        .unwrap();
+        assert_eq!(rpc.message_id.as_deref(), Some("1"));
+        assert_eq!(rpc.operation, "get");
+        assert_eq!(rpc.xml, "<nc:get/>");
+        assert!(parse_rpc("<hello/>").is_none());
+    }
+}
@@ -0,0 +1,333 @@
+use crate::error::{Error, Result};
+use crate::interceptor::RpcInterceptor;
+use crate::message::RpcReply;
+use quick_xml::events::Event;
+use quick_xml::{Reader, Writer};
+use std::io;
+use std::io::{BufReader, Read, Write};
+
+/// Iterator over children of `<data>` element in a reply, parsed incrementally
+/// from the transport so that whole reply is never held in memory.
+///
+/// Each item is one top level data node serialized as XML. Any `<rpc-error>`
+/// elements are collected and returned as the last item.
+pub struct DataStream<'a> {
+    reader: Reader<BufReader<Box<dyn Read + 'a>>>,
+    buffer: Vec<u8>,
+    depth: usize,
+    in_data: bool,
+    finished: bool,
+    fail_on_warnings: bool,
+    message_id: String,
+    errors: Vec<crate::message::Error>,
+}
+
+impl<'a> DataStream<'a> {
+    pub(crate) fn new(from: Box<dyn Read + 'a>, fail_on_warnings: bool) -> DataStream<'a> {
+        DataStream {
+            reader: Reader::from_reader(BufReader::new(from)),
+            buffer: Vec::new(),
+            depth: 0,
+            in_data: false,
+            finished: false,
+            fail_on_warnings,
+            message_id: String::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    fn next_element(&mut self) -> Result<Option<String>> {
+        loop {
+            self.buffer.clear();
+            match self.reader.read_event_into(&mut self.buffer)? {
+                Event::Start(start) => {
+                    match (self.depth, start.local_name().as_ref()) {
+                        (0, b"rpc-reply") => {
+                            if let Some(id) = start
+                                .try_get_attribute("message-id")
+                                .map_err(quick_xml::Error::from)?
+                            {
+                                self.message_id = id.unescape_value()?.to_string();
+                            }
+                        }
+                        (1, b"data") => self.in_data = true,
+                        (1, b"rpc-error") => {
+                            let start = start.into_owned();
+                            let error = read_element(&mut self.reader, Event::Start(start))?;
+                            self.errors.push(quick_xml::de::from_str(&error)?);
+                            continue;
+                        }
+                        (2, _) if self.in_data => {
+                            let start = start.into_owned();
+                            return read_element(&mut self.reader, Event::Start(start)).map(Some);
+                        }
+                        _ => {}
+                    }
+                    self.depth += 1;
+                }
+                Event::Empty(empty) if self.depth == 2 && self.in_data => {
+                    let empty = empty.into_owned();
+                    let mut writer = Writer::new(Vec::new());
+                    writer.write_event(Event::Empty(empty))?;
+                    return Ok(Some(
+                        String::from_utf8_lossy(&writer.into_inner()).to_string(),
+                    ));
+                }
+                Event::End(_) => {
+                    self.depth = self.depth.saturating_sub(1);
+                    if self.depth == 1 {
+                        self.in_data = false;
+                    }
+                }
+                Event::Eof => return self.finish().map(|_| None),
+                _ => {}
+            }
+        }
+    }
+
+    /// Writes contents of `<data>` element to `writer` as they are received, without
+    /// holding even a single data node in memory. Fails with [`Error::Netconf`] after
+    /// writing if the reply contains `<rpc-error>`.
+    pub fn write_to<W: Write>(mut self, writer: W) -> Result<()> {
+        let mut writer = Writer::new(writer);
+        loop {
+            self.buffer.clear();
+            let event = self.reader.read_event_into(&mut self.buffer)?;
+            let in_data = self.in_data && self.depth >= 2;
+            match event {
+                Event::Start(start) => {
+                    match (self.depth, start.local_name().as_ref()) {
+                        _ if in_data => writer.write_event(Event::Start(start))?,
+                        (0, b"rpc-reply") => {
+                            if let Some(id) = start
+                                .try_get_attribute("message-id")
+                                .map_err(quick_xml::Error::from)?
+                            {
+                                self.message_id = id.unescape_value()?.to_string();
+                            }
+                        }
+                        (1, b"data") => self.in_data = true,
+                        (1, b"rpc-error") => {
+                            let start = start.into_owned();
+                            let error = read_element(&mut self.reader, Event::Start(start))?;
+                            self.errors.push(quick_xml::de::from_str(&error)?);
+                            continue;
+                        }
+                        _ => {}
+                    }
+                    self.depth += 1;
+                }
+                Event::End(end) => {
+                    self.depth = self.depth.saturating_sub(1);
+                    if self.depth == 1 {
+                        self.in_data = false;
+                    } else if self.in_data && self.depth >= 2 {
+                        writer.write_event(Event::End(end))?;
+                    }
+                }
+                Event::Eof => {
+                    self.finished = true;
+                    return self.finish();
+                }
+                event if in_data => writer.write_event(event)?,
+                _ => {}
+            }
+        }
+    }
+
+    /// Fails if `<rpc-error>` elements were received
+    fn finish(&mut self) -> Result<()> {
+        let reply = RpcReply::new(
+            std::mem::take(&mut self.message_id),
+            std::mem::take(&mut self.errors),
+        );
+        if crate::is_rejected(&reply, self.fail_on_warnings) {
+            return Err(Error::Netconf(reply));
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for DataStream<'_> {
+    type Item = Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        match self.next_element() {
+            Ok(Some(element)) => Some(Ok(element)),
+            Ok(None) => {
+                self.finished = true;
+                None
+            }
+            Err(err) => {
+                self.finished = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+impl Drop for DataStream<'_> {
+    fn drop(&mut self) {
+        // Rest of the message must be consumed, otherwise it would be read as reply to the next rpc
+        if let Err(err) = io::copy(self.reader.get_mut(), &mut io::sink()) {
+            error!("Failed to drain rest of the reply: {}", err);
+        }
+    }
+}
+
+/// Keeps copy of streamed reply and passes it to interceptors when it has been read
+pub(crate) struct Recorder<'a> {
+    inner: Box<dyn Read + 'a>,
+    message: Vec<u8>,
+    interceptors: &'a mut [Box<dyn RpcInterceptor>],
+}
+
+impl<'a> Recorder<'a> {
+    pub(crate) fn new(
+        inner: Box<dyn Read + 'a>,
+        interceptors: &'a mut [Box<dyn RpcInterceptor>],
+    ) -> Recorder<'a> {
+        Recorder {
+            inner,
+            message: Vec::new(),
+            interceptors,
+        }
+    }
+}
+
+impl Read for Recorder<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.message.extend_from_slice(&buf[..read]);
+        Ok(read)
+    }
+}
+
+impl Drop for Recorder<'_> {
+    fn drop(&mut self) {
+        let mut message = String::from_utf8_lossy(&self.message).into_owned();
+        for interceptor in self.interceptors.iter_mut() {
+            interceptor.on_receive(&mut message);
+        }
+    }
+}
+
+/// Reads element starting with `start` until matching end tag and returns it as XML
+fn read_element<R: io::BufRead>(reader: &mut Reader<R>, start: Event<'static>) -> Result<String> {
+    let mut writer = Writer::new(Vec::new());
+    writer.write_event(start)?;
+    let mut buffer = Vec::new();
+    let mut depth = 1;
+    while depth > 0 {
+        buffer.clear();
+        let event = reader.read_event_into(&mut buffer)?;
+        match event {
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth -= 1,
+            Event::Eof => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+            _ => {}
+        }
+        writer.write_event(event)?;
+    }
+    Ok(String::from_utf8_lossy(&writer.into_inner()).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framer::Framer;
+    use pretty_assertions::assert_eq;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_data_stream() {
+        let reply = r#"
+<rpc-reply message-id="101" xmlns="urn:ietf:params:xml:ns:netconf:base:1.0">
+  <data>
+    <interfaces xmlns="urn:ietf:params:xml:ns:yang:ietf-interfaces">
+      <interface><name>eth0</name></interface>
+    </interfaces>
+    <system xmlns="urn:ietf:params:xml:ns:yang:ietf-system"/>
+  </data>
+</rpc-reply>
+]]>]]>"#;
+        let mut framer = Framer::new();
+        let stream = DataStream::new(Box::new(framer.message_reader(Cursor::new(reply))), false);
+        let elements: Vec<String> = stream.map(|e| e.unwrap()).collect();
+        assert_eq!(
+            elements,
+            vec![
+                r#"<interfaces xmlns="urn:ietf:params:xml:ns:yang:ietf-interfaces">
+      <interface><name>eth0</name></interface>
+    </interfaces>"#,
+                r#"<system xmlns="urn:ietf:params:xml:ns:yang:ietf-system"/>"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_write_to() {
+        let reply = r#"
+<rpc-reply message-id="101" xmlns="urn:ietf:params:xml:ns:netconf:base:1.0">
+  <data>
+    <interfaces xmlns="urn:ietf:params:xml:ns:yang:ietf-interfaces">
+      <interface><name>eth&amp;0</name></interface>
+    </interfaces>
+    <system xmlns="urn:ietf:params:xml:ns:yang:ietf-system"/>
+  </data>
+</rpc-reply>
+]]>]]>"#;
+        let mut framer = Framer::new();
+        let stream = DataStream::new(Box::new(framer.message_reader(Cursor::new(reply))), false);
+        let mut output = Vec::new();
+        stream.write_to(&mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap().trim(),
+            r#"<interfaces xmlns="urn:ietf:params:xml:ns:yang:ietf-interfaces">
+      <interface><name>eth&amp;0</name></interface>
+    </interfaces>
+    <system xmlns="urn:ietf:params:xml:ns:yang:ietf-system"/>"#
+        );
+    }
+
+    #[test]
+    fn test_data_stream_with_errors() {
+        let reply = r#"
+<rpc-reply message-id="101" xmlns="urn:ietf:params:xml:ns:netconf:base:1.0">
+  <rpc-error>
+    <error-type>protocol</error-type>
+    <error-tag>bad-element</error-tag>
+    <error-severity>error</error-severity>
+    <error-message>Element is not valid in the specified context.</error-message>
+  </rpc-error>
+</rpc-reply>
+]]>]]>"#;
+        let mut framer = Framer::new();
+        let mut stream =
+            DataStream::new(Box::new(framer.message_reader(Cursor::new(reply))), false);
+        match stream.next() {
+            Some(Err(Error::Netconf(reply))) => {
+                assert_eq!(reply.message_id(), "101");
+                assert_eq!(reply.errors().len(), 1);
+            }
+            other => panic!("expected rpc-error, got {:?}", other),
+        }
+        assert!(stream.next().is_none());
+    }
+
+    #[test]
+    fn test_dropped_stream_consumes_message() {
+        let messages = r#"<rpc-reply message-id="1"><data><a/><b/></data></rpc-reply>]]>]]><rpc-reply message-id="2"><ok/></rpc-reply>]]>]]>"#;
+        let mut framer = Framer::new();
+        let mut channel = Cursor::new(messages);
+        {
+            let mut stream = DataStream::new(Box::new(framer.message_reader(&mut channel)), false);
+            assert_eq!(stream.next().unwrap().unwrap(), "<a/>");
+        }
+        let next = framer.read_xml(&mut channel).unwrap();
+        assert_eq!(next, r#"<rpc-reply message-id="2"><ok/></rpc-reply>"#);
+    }
+}
@@ -0,0 +1,231 @@
+use serde_derive::{Deserialize, Serialize};
+use std::time::Duration;
+
+const SUBSCRIBED_NOTIFICATIONS_NAMESPACE: &str =
+    "urn:ietf:params:xml:ns:yang:ietf-subscribed-notifications";
+const YANG_PUSH_NAMESPACE: &str = "urn:ietf:params:xml:ns:yang:ietf-yang-push";
+const DATASTORES_NAMESPACE: &str = "urn:ietf:params:xml:ns:yang:ietf-datastores";
+
+/// Dynamic subscription to an event stream or YANG Push datastore updates, see
+/// [RFC8639](https://tools.ietf.org/html/rfc8639) and [RFC8641](https://tools.ietf.org/html/rfc8641)
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct EstablishSubscription {
+    #[serde(rename = "@xmlns")]
+    xmlns: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_xpath_filter: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    datastore: Option<PushDatastore>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    datastore_xpath_filter: Option<PushXpathFilter>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    periodic: Option<Periodic>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    on_change: Option<OnChange>,
+}
+
+impl EstablishSubscription {
+    /// Subscription to event `stream`
+    pub fn stream(stream: &str) -> EstablishSubscription {
+        EstablishSubscription {
+            stream: Some(stream.to_string()),
+            ..Self::empty()
+        }
+    }
+
+    /// YANG Push subscription sending full contents of `datastore` every `period`
+    pub fn periodic(datastore: &str, period: Duration) -> EstablishSubscription {
+        EstablishSubscription {
+            datastore: Some(PushDatastore::new(datastore)),
+            periodic: Some(Periodic {
+                xmlns: YANG_PUSH_NAMESPACE.to_string(),
+                period: centiseconds(period),
+            }),
+            ..Self::empty()
+        }
+    }
+
+    /// YANG Push subscription sending changes of `datastore`, at most once per `dampening_period`
+    pub fn on_change(datastore: &str, dampening_period: Option<Duration>) -> EstablishSubscription {
+        EstablishSubscription {
+            datastore: Some(PushDatastore::new(datastore)),
+            on_change: Some(OnChange {
+                xmlns: YANG_PUSH_NAMESPACE.to_string(),
+                dampening_period: dampening_period.map(centiseconds),
+            }),
+            ..Self::empty()
+        }
+    }
+
+    /// Limits subscription to nodes or events selected by XPath expression
+    pub fn xpath_filter(mut self, xpath: &str) -> EstablishSubscription {
+        if self.stream.is_some() {
+            self.stream_xpath_filter = Some(xpath.to_string());
+        } else {
+            self.datastore_xpath_filter = Some(PushXpathFilter {
+                xmlns: YANG_PUSH_NAMESPACE.to_string(),
+                xpath: xpath.to_string(),
+            });
+        }
+        self
+    }
+
+    fn empty() -> EstablishSubscription {
+        EstablishSubscription {
+            xmlns: SUBSCRIBED_NOTIFICATIONS_NAMESPACE.to_string(),
+            stream: None,
+            stream_xpath_filter: None,
+            datastore: None,
+            datastore_xpath_filter: None,
+            periodic: None,
+            on_change: None,
+        }
+    }
+}
+
+/// Changes parameters of a dynamic subscription without re-establishing it
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ModifySubscription {
+    #[serde(rename = "@xmlns")]
+    xmlns: String,
+    id: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_xpath_filter: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    datastore_xpath_filter: Option<PushXpathFilter>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    periodic: Option<Periodic>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    on_change: Option<OnChange>,
+}
+
+impl ModifySubscription {
+    pub fn new(id: u32) -> ModifySubscription {
+        ModifySubscription {
+            xmlns: SUBSCRIBED_NOTIFICATIONS_NAMESPACE.to_string(),
+            id,
+            stream_xpath_filter: None,
+            datastore_xpath_filter: None,
+            periodic: None,
+            on_change: None,
+        }
+    }
+
+    /// New period of periodic YANG Push subscription
+    pub fn period(mut self, period: Duration) -> ModifySubscription {
+        self.periodic = Some(Periodic {
+            xmlns: YANG_PUSH_NAMESPACE.to_string(),
+            period: centiseconds(period),
+        });
+        self
+    }
+
+    /// New dampening period of on-change YANG Push subscription
+    pub fn dampening_period(mut self, dampening_period: Duration) -> ModifySubscription {
+        self.on_change = Some(OnChange {
+            xmlns: YANG_PUSH_NAMESPACE.to_string(),
+            dampening_period: Some(centiseconds(dampening_period)),
+        });
+        self
+    }
+
+    /// New XPath filter of event stream subscription
+    pub fn stream_xpath_filter(mut self, xpath: &str) -> ModifySubscription {
+        self.stream_xpath_filter = Some(xpath.to_string());
+        self
+    }
+
+    /// New XPath filter of YANG Push subscription
+    pub fn datastore_xpath_filter(mut self, xpath: &str) -> ModifySubscription {
+        self.datastore_xpath_filter = Some(PushXpathFilter {
+            xmlns: YANG_PUSH_NAMESPACE.to_string(),
+            xpath: xpath.to_string(),
+        });
+        self
+    }
+}
+
+/// Operation targeting a dynamic subscription by its id
+#[derive(Debug, Serialize)]
+pub struct SubscriptionId {
+    #[serde(rename = "@xmlns")]
+    xmlns: String,
+    id: u32,
+}
+
+impl SubscriptionId {
+    /// Id for delete-subscription, in ietf-subscribed-notifications namespace
+    pub(crate) fn subscribed_notifications(id: u32) -> SubscriptionId {
+        SubscriptionId {
+            xmlns: SUBSCRIBED_NOTIFICATIONS_NAMESPACE.to_string(),
+            id,
+        }
+    }
+
+    /// Id for resync-subscription, in ietf-yang-push namespace
+    pub(crate) fn yang_push(id: u32) -> SubscriptionId {
+        SubscriptionId {
+            xmlns: YANG_PUSH_NAMESPACE.to_string(),
+            id,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct PushDatastore {
+    #[serde(rename = "@xmlns")]
+    xmlns: String,
+    #[serde(rename = "@xmlns:ds")]
+    xmlns_ds: String,
+    #[serde(rename = "$text")]
+    identity: String,
+}
+
+impl PushDatastore {
+    fn new(datastore: &str) -> PushDatastore {
+        PushDatastore {
+            xmlns: YANG_PUSH_NAMESPACE.to_string(),
+            xmlns_ds: DATASTORES_NAMESPACE.to_string(),
+            identity: format!("ds:{}", datastore),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct PushXpathFilter {
+    #[serde(rename = "@xmlns")]
+    xmlns: String,
+    #[serde(rename = "$text")]
+    xpath: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Periodic {
+    #[serde(rename = "@xmlns")]
+    xmlns: String,
+    period: u32,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct OnChange {
+    #[serde(rename = "@xmlns")]
+    xmlns: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dampening_period: Option<u32>,
+}
+
+/// Reply to establish-subscription
+#[derive(Debug, Deserialize)]
+pub(crate) struct EstablishSubscriptionReply {
+    pub(crate) id: u32,
+}
+
+/// Subscription parameters in RFC8639/RFC8641 are given in centiseconds
+fn centiseconds(duration: Duration) -> u32 {
+    (duration.as_millis() / 10).min(u32::MAX.into()) as u32
+}
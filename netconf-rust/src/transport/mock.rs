@@ -0,0 +1,185 @@
+//! In-memory transport for unit testing code built on [`Connection`](crate::Connection)
+//! without a device, enabled with the `test-util` feature.
+
+use crate::error::{Error, Result};
+use crate::message::root_element;
+use crate::transport::{MessageLimits, Transport};
+use std::collections::VecDeque;
+use std::io::{self, Cursor, Read};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const BASE_CAPABILITY: &str = "urn:ietf:params:netconf:base:1.0";
+
+type Handler = Box<dyn FnMut(&str) -> Option<String> + Send>;
+
+/// Transport answering rpcs with replies produced by a handler.
+///
+/// Hello is answered automatically with session-id 1 and capabilities given with
+/// [`MockTransport::set_capabilities`]. Handler gets the serialized rpc and returns
+/// content of the `<rpc-reply>`, which is wrapped with matching message-id.
+/// Returning `None` sends no reply, so waiting for it fails with [`Error::Timeout`].
+pub struct MockTransport {
+    handler: Handler,
+    capabilities: Vec<String>,
+    incoming: VecDeque<String>,
+    sent: Arc<Mutex<Vec<String>>>,
+    timeout: Option<Duration>,
+}
+
+impl MockTransport {
+    pub fn new(handler: impl FnMut(&str) -> Option<String> + Send + 'static) -> MockTransport {
+        MockTransport {
+            handler: Box::new(handler),
+            capabilities: vec![BASE_CAPABILITY.to_string()],
+            incoming: VecDeque::new(),
+            sent: Arc::new(Mutex::new(Vec::new())),
+            timeout: None,
+        }
+    }
+
+    /// Expects rpcs in the given order, each containing the first string of the pair,
+    /// and replies with the second. Panics on unexpected or extra rpcs.
+    pub fn scripted<I, S, R>(script: I) -> MockTransport
+    where
+        I: IntoIterator<Item = (S, R)>,
+        S: Into<String>,
+        R: Into<String>,
+    {
+        let mut script: VecDeque<(String, String)> = script
+            .into_iter()
+            .map(|(request, reply)| (request.into(), reply.into()))
+            .collect();
+        MockTransport::new(move |rpc| match script.pop_front() {
+            Some((expected, reply)) if rpc.contains(&expected) => Some(reply),
+            Some((expected, _)) => panic!("expected rpc containing {}, got:\n{}", expected, rpc),
+            None => panic!("unexpected rpc:\n{}", rpc),
+        })
+    }
+
+    /// Capabilities advertised in hello, base 1.0 capability is always included
+    pub fn set_capabilities<S: Into<String>>(&mut self, capabilities: impl IntoIterator<Item = S>) {
+        self.capabilities = vec![BASE_CAPABILITY.to_string()];
+        self.capabilities
+            .extend(capabilities.into_iter().map(Into::into));
+    }
+
+    /// Queues message, e.g. a notification, to be received before replies to later rpcs
+    pub fn push_message(&mut self, message: impl Into<String>) {
+        self.incoming.push_back(message.into());
+    }
+
+    /// Messages sent through the transport, shared so that they can be inspected
+    /// after the transport is moved to a connection
+    pub fn sent(&self) -> Arc<Mutex<Vec<String>>> {
+        Arc::clone(&self.sent)
+    }
+
+    fn hello(&self) -> String {
+        let capabilities: String = self
+            .capabilities
+            .iter()
+            .map(|c| format!("<capability>{}</capability>", c))
+            .collect();
+        format!(
+            r#"<hello xmlns="urn:ietf:params:xml:ns:netconf:base:1.0"><capabilities>{}</capabilities><session-id>1</session-id></hello>"#,
+            capabilities
+        )
+    }
+}
+
+impl Transport for MockTransport {
+    fn send(&mut self, message: &str) -> Result<()> {
+        self.sent.lock().unwrap().push(message.to_string());
+        match root_element(message) {
+            Some((root, _)) if root == "hello" => {
+                // Server hello precedes messages queued before connecting
+                let hello = self.hello();
+                self.incoming.push_front(hello);
+            }
+            Some((_, message_id)) => {
+                if let Some(reply) = (self.handler)(message) {
+                    let message_id = message_id
+                        .map(|id| format!(r#" message-id="{}""#, id))
+                        .unwrap_or_default();
+                    self.incoming.push_back(format!(
+                        r#"<rpc-reply xmlns="urn:ietf:params:xml:ns:netconf:base:1.0"{}>{}</rpc-reply>"#,
+                        message_id, reply
+                    ));
+                }
+            }
+            None => {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "malformed message").into())
+            }
+        }
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Result<String> {
+        self.incoming
+            .pop_front()
+            .ok_or_else(|| Error::Io(io::ErrorKind::TimedOut.into()))
+    }
+
+    fn receive_stream(&mut self) -> Result<Box<dyn Read + '_>> {
+        let message = self.receive()?;
+        Ok(Box::new(Cursor::new(message.into_bytes())))
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    fn set_message_limits(&mut self, _limits: MessageLimits) {}
+
+    fn close(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn upgrade(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Connection;
+
+    #[test]
+    fn test_scripted_connection() {
+        let mut transport = MockTransport::scripted([
+            ("<get-config>", "<data><system/></data>"),
+            ("<close-session/>", "<ok/>"),
+        ]);
+        transport.set_capabilities(["urn:ietf:params:netconf:capability:candidate:1.0"]);
+        let sent = transport.sent();
+
+        let mut connection = Connection::new(transport).unwrap();
+        assert_eq!(connection.session_id(), 1);
+        let reply = connection.get_config("running").unwrap();
+        assert!(reply.raw().contains("<system/>"));
+        connection.shutdown().unwrap();
+        assert_eq!(sent.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_base_1_0_hello() {
+        let mut transport = MockTransport::new(|_| None);
+        transport.set_capabilities(["urn:ietf:params:netconf:base:1.1"]);
+        let sent = transport.sent();
+
+        Connection::new_base_1_0(transport).unwrap();
+        let hello = &sent.lock().unwrap()[0];
+        assert!(hello.contains("base:1.0"));
+        assert!(!hello.contains("base:1.1"));
+    }
+
+    #[test]
+    fn test_missing_reply_times_out() {
+        let mut connection = Connection::new(MockTransport::new(|_| None)).unwrap();
+        assert!(matches!(connection.commit(), Err(Error::Timeout)));
+    }
+}
@@ -1,10 +1,91 @@
 use crate::error::Result;
+use crate::wire::WireObserver;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+#[cfg(any(test, feature = "test-util"))]
+pub mod mock;
+pub mod openssh;
+#[cfg(feature = "russh")]
+pub mod russh;
+#[cfg(feature = "ssh2")]
 pub mod ssh;
+#[cfg(feature = "server")]
+pub mod tcp;
+
+/// Limits on size of received messages, guarding against misbehaving servers
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MessageLimits {
+    pub max_message_size: Option<usize>,
+    pub max_chunk_size: Option<usize>,
+}
+
+/// OpenSSH certificate `<key>-cert.pub` stored next to private key, if it exists
+pub fn certificate_file(private_key: &Path) -> Option<PathBuf> {
+    let mut name = private_key.file_name()?.to_os_string();
+    name.push("-cert.pub");
+    let path = private_key.with_file_name(name);
+    path.is_file().then_some(path)
+}
 
 /// Trait for NETCONF transport
 pub trait Transport: Send {
-    fn execute_rpc(&mut self, rpc: &str) -> Result<String>;
+    /// Sends single framed message
+    fn send(&mut self, message: &str) -> Result<()>;
+    /// Receives next framed message
+    fn receive(&mut self) -> Result<String>;
+    /// Returns reader yielding the next message body as it is received.
+    /// Reader must be read until EOF before next message is received.
+    fn receive_stream(&mut self) -> Result<Box<dyn Read + '_>>;
+    /// Timeout for blocking reads and writes, `None` waits forever
+    fn timeout(&self) -> Option<Duration>;
+    fn set_timeout(&mut self, timeout: Option<Duration>);
+    fn set_message_limits(&mut self, limits: MessageLimits);
+    /// Skips to the end of a message with malformed chunk instead of failing the session,
+    /// for transports with chunked framing
+    fn set_frame_recovery(&mut self, _recovery: bool) {}
+    /// Registers observer of raw framed data, for transports with framing
+    fn set_wire_observer(&mut self, _observer: Box<dyn WireObserver>) {}
+    /// Splits sent messages to chunks of at most `size` bytes, for transports with
+    /// chunked framing
+    fn set_max_outbound_chunk_size(&mut self, _size: Option<usize>) {}
+    /// Replaces invalid UTF-8 in received messages instead of failing with
+    /// [`Error::InvalidUtf8`](crate::error::Error::InvalidUtf8), for transports with framing
+    fn set_lossy_utf8(&mut self, _lossy: bool) {}
+    /// When off, sent messages are buffered until [`Transport::flush`] or the next receive,
+    /// so that pipelined messages are written together
+    fn set_autoflush(&mut self, _autoflush: bool) {}
+    /// Writes buffered messages
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
     fn close(&mut self) -> Result<()>;
     fn upgrade(&mut self);
+
+    fn execute_rpc(&mut self, rpc: &str) -> Result<String> {
+        self.send(rpc)?;
+        self.receive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_certificate_file() {
+        let dir = std::env::temp_dir().join(format!("netconf-cert-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let key = dir.join("id_ed25519");
+        assert_eq!(certificate_file(&key), None);
+
+        fs::write(dir.join("id_ed25519-cert.pub"), "").unwrap();
+        assert_eq!(
+            certificate_file(&key),
+            Some(dir.join("id_ed25519-cert.pub"))
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
 }
@@ -0,0 +1,195 @@
+//! Transport running the system `ssh` client as a subprocess, so that the whole
+//! OpenSSH configuration (ProxyJump, certificates, FIDO keys, ControlMaster) applies.
+
+use crate::error::Result;
+use crate::framer::Framer;
+use crate::transport::{MessageLimits, Transport};
+use crate::wire::WireObserver;
+use std::io::{self, Read};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+pub struct OpensshTransport {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    stdout: ChildOutput,
+    framer: Framer,
+}
+
+impl OpensshTransport {
+    /// Runs `ssh -s <destination> netconf`, destination is anything accepted by `ssh`,
+    /// e.g. `admin@router` or a host alias from `~/.ssh/config`
+    pub fn dial(destination: &str) -> Result<OpensshTransport> {
+        let mut command = Command::new("ssh");
+        command.args(["-s", destination, "netconf"]);
+        OpensshTransport::dial_command(command)
+    }
+
+    /// Runs given command, which must start netconf subsystem with its stdio,
+    /// e.g. `ssh` with extra options. Stderr is inherited for password prompts and errors.
+    pub fn dial_command(mut command: Command) -> Result<OpensshTransport> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()?;
+        let stdin = child.stdin.take();
+        let stdout = ChildOutput::start(child.stdout.take().unwrap());
+        Ok(OpensshTransport {
+            child,
+            stdin,
+            stdout,
+            framer: Framer::new(),
+        })
+    }
+}
+
+impl Transport for OpensshTransport {
+    fn send(&mut self, message: &str) -> Result<()> {
+        let stdin = self
+            .stdin
+            .as_mut()
+            .ok_or(io::Error::from(io::ErrorKind::BrokenPipe))?;
+        self.framer.write_xml(message, stdin)
+    }
+
+    fn receive(&mut self) -> Result<String> {
+        self.flush()?;
+        self.framer.read_xml(&mut self.stdout)
+    }
+
+    fn receive_stream(&mut self) -> Result<Box<dyn Read + '_>> {
+        self.flush()?;
+        Ok(Box::new(self.framer.message_reader(&mut self.stdout)))
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.stdout.timeout
+    }
+
+    fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.stdout.timeout = timeout;
+    }
+
+    fn set_message_limits(&mut self, limits: MessageLimits) {
+        self.framer.set_limits(limits);
+    }
+
+    fn set_frame_recovery(&mut self, recovery: bool) {
+        self.framer.set_recovery(recovery);
+    }
+
+    fn set_wire_observer(&mut self, observer: Box<dyn WireObserver>) {
+        self.framer.set_observer(observer);
+    }
+
+    fn set_max_outbound_chunk_size(&mut self, size: Option<usize>) {
+        self.framer.set_max_outbound_chunk_size(size);
+    }
+
+    fn set_lossy_utf8(&mut self, lossy: bool) {
+        self.framer.set_lossy_utf8(lossy);
+    }
+
+    fn set_autoflush(&mut self, autoflush: bool) {
+        self.framer.set_autoflush(autoflush);
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        let stdin = self
+            .stdin
+            .as_mut()
+            .ok_or(io::Error::from(io::ErrorKind::BrokenPipe))?;
+        self.framer.flush(stdin)
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.stdin = None;
+        let status = self.child.wait()?;
+        debug!("ssh exited with {}", status);
+        Ok(())
+    }
+
+    fn upgrade(&mut self) {
+        self.framer.upgrade();
+    }
+}
+
+impl Drop for OpensshTransport {
+    fn drop(&mut self) {
+        if let Ok(None) = self.child.try_wait() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+/// Output of the child read by background thread, so that reads can time out
+struct ChildOutput {
+    chunks: Receiver<io::Result<Vec<u8>>>,
+    pending: Vec<u8>,
+    timeout: Option<Duration>,
+}
+
+impl ChildOutput {
+    fn start(mut from: impl Read + Send + 'static) -> ChildOutput {
+        let (sender, chunks) = channel();
+        thread::spawn(move || loop {
+            let mut buffer = vec![0; 8192];
+            let result = from.read(&mut buffer).map(|bytes| {
+                buffer.truncate(bytes);
+                buffer
+            });
+            let done = !matches!(&result, Ok(chunk) if !chunk.is_empty());
+            if sender.send(result).is_err() || done {
+                return;
+            }
+        });
+        ChildOutput {
+            chunks,
+            pending: Vec::new(),
+            timeout: None,
+        }
+    }
+}
+
+impl Read for ChildOutput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pending.is_empty() {
+            let chunk = match self.timeout {
+                Some(timeout) => self.chunks.recv_timeout(timeout),
+                None => self.chunks.recv().map_err(RecvTimeoutError::from),
+            };
+            self.pending = match chunk {
+                Ok(chunk) => chunk?,
+                Err(RecvTimeoutError::Timeout) => return Err(io::ErrorKind::TimedOut.into()),
+                Err(RecvTimeoutError::Disconnected) => return Ok(0),
+            };
+        }
+        let len = buf.len().min(self.pending.len());
+        buf[..len].copy_from_slice(&self.pending[..len]);
+        self.pending.drain(..len);
+        Ok(len)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+
+    #[test]
+    fn test_framing_over_child_stdio() {
+        let mut transport = OpensshTransport::dial_command(Command::new("cat")).unwrap();
+        transport.send("<hello/>").unwrap();
+        assert_eq!(transport.receive().unwrap(), "<hello/>");
+
+        transport.set_timeout(Some(Duration::from_millis(50)));
+        assert!(
+            matches!(transport.receive(), Err(Error::Io(err)) if err.kind() == io::ErrorKind::TimedOut)
+        );
+        transport.close().unwrap();
+    }
+}
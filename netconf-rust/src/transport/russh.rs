@@ -0,0 +1,258 @@
+//! Pure Rust SSH transport, avoiding the libssh2 and OpenSSL C dependencies
+//! of the `ssh2` based transport. Build with `--no-default-features --features russh`
+//! to drop both.
+
+use crate::error::{Error, Result};
+use crate::framer::Framer;
+use crate::transport::{certificate_file, MessageLimits, Transport};
+use crate::wire::WireObserver;
+use russh::client::{self, Handle, Msg};
+use russh::keys::{
+    load_openssh_certificate, load_secret_key, PrivateKeyWithHashAlg, PublicKeyOrCertificate,
+};
+use russh::{ChannelStream, Disconnect};
+use std::future::Future;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::runtime::Runtime;
+
+/// SSH transport built on `russh`. Async session is driven by an internal
+/// runtime, so it is used through the same blocking [`Transport`] API.
+///
+/// Like `SSHTransport`, server host key is not verified.
+pub struct RusshTransport {
+    runtime: Runtime,
+    handle: Handle<Client>,
+    stream: ChannelStream<Msg>,
+    framer: Framer,
+    timeout: Option<Duration>,
+}
+
+struct Client;
+
+impl client::Handler for Client {
+    type Error = russh::Error;
+
+    async fn check_server_key(
+        &mut self,
+        server_public_key: &PublicKeyOrCertificate,
+    ) -> std::result::Result<bool, Self::Error> {
+        if let PublicKeyOrCertificate::PublicKey { key, .. } = server_public_key {
+            debug!("Server host key {}", key.fingerprint(Default::default()));
+        }
+        Ok(true)
+    }
+}
+
+impl RusshTransport {
+    pub fn dial(addr: &str, user_name: &str, password: &str) -> Result<RusshTransport> {
+        let runtime = new_runtime()?;
+        let mut handle = runtime.block_on(connect(addr))?;
+        let auth = runtime.block_on(handle.authenticate_password(user_name, password))?;
+        if !auth.success() {
+            return Err(Error::AuthenticationFailed(user_name.to_string()));
+        }
+        connect_internal(runtime, handle)
+    }
+
+    /// Authenticates with private key read from `key_path`, decrypted with `passphrase` if given.
+    /// OpenSSH certificate found next to the key is used instead of the plain public key,
+    /// see [`certificate_file`].
+    pub fn dial_publickey(
+        addr: &str,
+        user_name: &str,
+        key_path: &Path,
+        passphrase: Option<&str>,
+    ) -> Result<RusshTransport> {
+        let key = Arc::new(load_secret_key(key_path, passphrase).map_err(russh::Error::from)?);
+        let certificate = match certificate_file(key_path) {
+            Some(path) => {
+                debug!("Using certificate {}", path.display());
+                let certificate = load_openssh_certificate(path)
+                    .map_err(|err| russh::Error::from(russh::keys::Error::from(err)))?;
+                Some(certificate)
+            }
+            None => None,
+        };
+        let runtime = new_runtime()?;
+        let mut handle = runtime.block_on(connect(addr))?;
+        let auth = runtime.block_on(async {
+            if let Some(certificate) = certificate {
+                return handle
+                    .authenticate_openssh_cert(user_name, key, certificate)
+                    .await;
+            }
+            let hash_alg = handle.best_supported_rsa_hash().await?.flatten();
+            let key = PrivateKeyWithHashAlg::new(key, hash_alg);
+            handle.authenticate_publickey(user_name, key).await
+        })?;
+        if !auth.success() {
+            return Err(Error::AuthenticationFailed(user_name.to_string()));
+        }
+        connect_internal(runtime, handle)
+    }
+}
+
+impl Transport for RusshTransport {
+    fn send(&mut self, message: &str) -> Result<()> {
+        let mut stream = BlockingStream {
+            runtime: &self.runtime,
+            stream: &mut self.stream,
+            timeout: self.timeout,
+        };
+        self.framer.write_xml(message, &mut stream)
+    }
+
+    fn receive(&mut self) -> Result<String> {
+        self.flush()?;
+        let stream = BlockingStream {
+            runtime: &self.runtime,
+            stream: &mut self.stream,
+            timeout: self.timeout,
+        };
+        self.framer.read_xml(stream)
+    }
+
+    fn receive_stream(&mut self) -> Result<Box<dyn Read + '_>> {
+        self.flush()?;
+        let stream = BlockingStream {
+            runtime: &self.runtime,
+            stream: &mut self.stream,
+            timeout: self.timeout,
+        };
+        Ok(Box::new(self.framer.message_reader(stream)))
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    fn set_message_limits(&mut self, limits: MessageLimits) {
+        self.framer.set_limits(limits);
+    }
+
+    fn set_frame_recovery(&mut self, recovery: bool) {
+        self.framer.set_recovery(recovery);
+    }
+
+    fn set_wire_observer(&mut self, observer: Box<dyn WireObserver>) {
+        self.framer.set_observer(observer);
+    }
+
+    fn set_max_outbound_chunk_size(&mut self, size: Option<usize>) {
+        self.framer.set_max_outbound_chunk_size(size);
+    }
+
+    fn set_lossy_utf8(&mut self, lossy: bool) {
+        self.framer.set_lossy_utf8(lossy);
+    }
+
+    fn set_autoflush(&mut self, autoflush: bool) {
+        self.framer.set_autoflush(autoflush);
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        let mut stream = BlockingStream {
+            runtime: &self.runtime,
+            stream: &mut self.stream,
+            timeout: self.timeout,
+        };
+        self.framer.flush(&mut stream)
+    }
+
+    fn close(&mut self) -> Result<()> {
+        block_on(&self.runtime, self.timeout, self.stream.shutdown())?;
+        self.runtime.block_on(
+            self.handle
+                .disconnect(Disconnect::ByApplication, "Shutdown", ""),
+        )?;
+        Ok(())
+    }
+
+    fn upgrade(&mut self) {
+        self.framer.upgrade();
+    }
+}
+
+/// Blocking [`Read`] and [`Write`] over the async channel stream
+struct BlockingStream<'a> {
+    runtime: &'a Runtime,
+    stream: &'a mut ChannelStream<Msg>,
+    timeout: Option<Duration>,
+}
+
+impl Read for BlockingStream<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        block_on(self.runtime, self.timeout, self.stream.read(buf))
+    }
+}
+
+impl Write for BlockingStream<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        block_on(self.runtime, self.timeout, self.stream.write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        block_on(self.runtime, self.timeout, self.stream.flush())
+    }
+}
+
+/// Runs `future` to completion, failing with [`io::ErrorKind::TimedOut`] after `timeout`
+fn block_on<T>(
+    runtime: &Runtime,
+    timeout: Option<Duration>,
+    future: impl Future<Output = io::Result<T>>,
+) -> io::Result<T> {
+    runtime.block_on(async {
+        match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, future)
+                .await
+                .map_err(|_| io::Error::from(io::ErrorKind::TimedOut))?,
+            None => future.await,
+        }
+    })
+}
+
+fn new_runtime() -> Result<Runtime> {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .enable_all()
+        .build()?;
+    Ok(runtime)
+}
+
+async fn connect(addr: &str) -> Result<Handle<Client>> {
+    let config = client::Config {
+        inactivity_timeout: None,
+        ..Default::default()
+    };
+    client::connect(Arc::new(config), addr, Client)
+        .await
+        .map_err(|err| match err {
+            russh::Error::ConnectionTimeout => Error::ConnectTimeout,
+            russh::Error::IO(err) if err.kind() == io::ErrorKind::TimedOut => Error::ConnectTimeout,
+            err => Error::Russh(err),
+        })
+}
+
+fn connect_internal(runtime: Runtime, handle: Handle<Client>) -> Result<RusshTransport> {
+    let channel = runtime.block_on(async {
+        let channel = handle.channel_open_session().await?;
+        channel.request_subsystem(true, "netconf").await?;
+        Ok::<_, russh::Error>(channel)
+    })?;
+    Ok(RusshTransport {
+        stream: channel.into_stream(),
+        runtime,
+        handle,
+        framer: Framer::new(),
+        timeout: None,
+    })
+}
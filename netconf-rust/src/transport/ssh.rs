@@ -1,14 +1,163 @@
 use crate::error::{Error, Result};
 use crate::framer::Framer;
-use crate::transport::Transport;
-use ssh2::{Channel, Session};
+use crate::transport::{certificate_file, MessageLimits, Transport};
+use crate::wire::WireObserver;
+use ssh2::{BlockDirections, Channel, ErrorCode, Session};
 use std::io;
-use std::net::TcpStream;
+use std::io::Read;
+use std::net::{TcpStream, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, RawFd};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
 pub struct SSHTransport {
     session: Session,
     channel: Channel,
     framer: Framer,
+    keepalive: Option<Keepalive>,
+}
+
+/// Background thread sending SSH keepalive messages, stopped when dropped
+struct Keepalive {
+    _stop: Sender<()>,
+    failure: Arc<Mutex<Option<ssh2::Error>>>,
+    interval: Duration,
+}
+
+impl Keepalive {
+    fn start(session: Session, interval: Duration) -> Keepalive {
+        let (stop, stopped) = channel::<()>();
+        let failure = Arc::new(Mutex::new(None));
+        let thread_failure = Arc::clone(&failure);
+        session.set_keepalive(true, interval.as_secs().max(1) as u32);
+        thread::spawn(move || loop {
+            match stopped.recv_timeout(interval) {
+                Err(RecvTimeoutError::Timeout) => {}
+                _ => return,
+            }
+            if let Err(err) = session.keepalive_send() {
+                error!("Failed to send ssh keepalive: {}", err);
+                *thread_failure.lock().unwrap() = Some(err);
+                return;
+            }
+            trace!("Sent ssh keepalive");
+        });
+        Keepalive {
+            _stop: stop,
+            failure,
+            interval,
+        }
+    }
+}
+
+/// Channel reader sending keepalives while waiting for data. Blocking read holds the
+/// session, so the keepalive thread can not send them until a message is received.
+struct KeepaliveReader<'a> {
+    session: &'a Session,
+    channel: &'a mut Channel,
+    interval: Option<Duration>,
+}
+
+impl<'a> KeepaliveReader<'a> {
+    fn new(
+        session: &'a Session,
+        channel: &'a mut Channel,
+        keepalive: Option<&Keepalive>,
+    ) -> KeepaliveReader<'a> {
+        KeepaliveReader {
+            session,
+            channel,
+            interval: keepalive.map(|keepalive| keepalive.interval),
+        }
+    }
+}
+
+impl Read for KeepaliveReader<'_> {
+    /// Reads in slices of keepalive interval until session timeout has elapsed
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some(interval) = self.interval else {
+            return self.channel.read(buf);
+        };
+        let timeout = self.session.timeout();
+        let deadline =
+            (timeout != 0).then(|| Instant::now() + Duration::from_millis(timeout.into()));
+        loop {
+            let remaining =
+                deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            let wait = remaining.map_or(interval, |remaining| remaining.min(interval));
+            self.session
+                .set_timeout(wait.as_millis().clamp(1, u32::MAX.into()) as u32);
+            let result = self.channel.read(buf);
+            self.session.set_timeout(timeout);
+            match result {
+                Err(err)
+                    if err.kind() == io::ErrorKind::TimedOut
+                        && deadline.is_none_or(|deadline| Instant::now() < deadline) =>
+                {
+                    self.session.keepalive_send()?;
+                    trace!("Sent ssh keepalive while waiting for reply");
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Timeout of TCP connect and SSH handshake of [`SSHTransport::dial`]
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+const LIBSSH2_ERROR_TIMEOUT: i32 = -9;
+const LIBSSH2_ERROR_AUTHENTICATION_FAILED: i32 = -18;
+const LIBSSH2_ERROR_PUBLICKEY_UNVERIFIED: i32 = -19;
+
+/// Connects to `addr` and performs SSH handshake, each within `timeout`, timeouts fail
+/// with [`Error::ConnectTimeout`]
+fn handshake(addr: &str, timeout: Duration) -> Result<Session> {
+    let stream = tcp_connect(addr, timeout).map_err(|err| match err.kind() {
+        io::ErrorKind::TimedOut => Error::ConnectTimeout,
+        _ => Error::Io(err),
+    })?;
+    let mut sess = Session::new()?;
+    sess.set_timeout(timeout.as_millis().clamp(1, u32::MAX.into()) as u32);
+    sess.set_tcp_stream(stream);
+    sess.handshake().map_err(|err| match err.code() {
+        ErrorCode::Session(LIBSSH2_ERROR_TIMEOUT) => Error::ConnectTimeout,
+        _ => Error::Ssh(err),
+    })?;
+    Ok(sess)
+}
+
+/// Tries addresses `addr` resolves to until one connects, returning the last error
+fn tcp_connect(addr: &str, timeout: Duration) -> io::Result<TcpStream> {
+    let mut last_err = None;
+    for socket_addr in addr.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&socket_addr, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(err) => {
+                debug!("Could not connect to {}: {}", socket_addr, err);
+                last_err = Some(err);
+            }
+        }
+    }
+    Err(last_err.unwrap_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, format!("no address for {}", addr))
+    }))
+}
+
+fn auth_error(err: ssh2::Error, user_name: &str) -> Error {
+    match err.code() {
+        ErrorCode::Session(
+            LIBSSH2_ERROR_AUTHENTICATION_FAILED | LIBSSH2_ERROR_PUBLICKEY_UNVERIFIED,
+        ) => Error::AuthenticationFailed(user_name.to_string()),
+        ErrorCode::Session(LIBSSH2_ERROR_TIMEOUT) => Error::ConnectTimeout,
+        _ => Error::Ssh(err),
+    }
 }
 
 impl SSHTransport {
@@ -17,24 +166,139 @@ impl SSHTransport {
     }
 
     pub fn dial(addr: &str, user_name: &str, password: &str) -> Result<SSHTransport> {
-        let stream = TcpStream::connect(addr)?;
-        let mut sess = Session::new()?;
-        sess.set_timeout(10_000);
-        sess.set_tcp_stream(stream);
-        sess.handshake()?;
+        SSHTransport::dial_with_timeout(addr, user_name, password, DEFAULT_CONNECT_TIMEOUT)
+    }
 
-        sess.userauth_password(user_name, password)?;
+    /// Like [`SSHTransport::dial`], with `timeout` for TCP connect and SSH handshake
+    pub fn dial_with_timeout(
+        addr: &str,
+        user_name: &str,
+        password: &str,
+        timeout: Duration,
+    ) -> Result<SSHTransport> {
+        let sess = handshake(addr, timeout)?;
+        sess.userauth_password(user_name, password)
+            .map_err(|err| auth_error(err, user_name))?;
         connect_internal(sess)
     }
+
+    /// Authenticates with private key read from `key_path`, decrypted with `passphrase` if given.
+    /// OpenSSH certificate found next to the key is offered instead of the plain public key,
+    /// see [`certificate_file`].
+    pub fn dial_publickey(
+        addr: &str,
+        user_name: &str,
+        key_path: &Path,
+        passphrase: Option<&str>,
+    ) -> Result<SSHTransport> {
+        SSHTransport::dial_publickey_with_timeout(
+            addr,
+            user_name,
+            key_path,
+            passphrase,
+            DEFAULT_CONNECT_TIMEOUT,
+        )
+    }
+
+    /// Like [`SSHTransport::dial_publickey`], with `timeout` for TCP connect and SSH handshake
+    pub fn dial_publickey_with_timeout(
+        addr: &str,
+        user_name: &str,
+        key_path: &Path,
+        passphrase: Option<&str>,
+        timeout: Duration,
+    ) -> Result<SSHTransport> {
+        let sess = handshake(addr, timeout)?;
+        let certificate = certificate_file(key_path);
+        if let Some(path) = &certificate {
+            debug!("Using certificate {}", path.display());
+        }
+        sess.userauth_pubkey_file(user_name, certificate.as_deref(), key_path, passphrase)
+            .map_err(|err| auth_error(err, user_name))?;
+        connect_internal(sess)
+    }
+
+    /// Starts background thread sending SSH keepalive messages every `interval`,
+    /// so that idle sessions are not dropped by firewalls. While waiting for a reply
+    /// keepalives are sent by the reading thread instead. Failure to send keepalive
+    /// in the background is returned by the next rpc.
+    pub fn set_keepalive(&mut self, interval: Duration) {
+        self.keepalive = Some(Keepalive::start(self.session.clone(), interval));
+    }
+
+    fn check_keepalive(&self) -> Result<()> {
+        match &self.keepalive {
+            Some(keepalive) => match keepalive.failure.lock().unwrap().take() {
+                Some(err) => Err(Error::Ssh(err)),
+                None => Ok(()),
+            },
+            None => Ok(()),
+        }
+    }
 }
 
 impl Transport for SSHTransport {
-    fn execute_rpc(&mut self, rpc: &str) -> Result<String> {
-        self.framer.write_xml(rpc, &mut self.channel)?;
-        self.framer.read_xml(&mut self.channel)
+    fn send(&mut self, message: &str) -> Result<()> {
+        self.check_keepalive()?;
+        self.framer.write_xml(message, &mut self.channel)
+    }
+
+    fn receive(&mut self) -> Result<String> {
+        self.flush()?;
+        let reader =
+            KeepaliveReader::new(&self.session, &mut self.channel, self.keepalive.as_ref());
+        self.framer.read_xml(reader)
+    }
+
+    fn receive_stream(&mut self) -> Result<Box<dyn Read + '_>> {
+        self.flush()?;
+        let reader =
+            KeepaliveReader::new(&self.session, &mut self.channel, self.keepalive.as_ref());
+        Ok(Box::new(self.framer.message_reader(reader)))
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        match self.session.timeout() {
+            0 => None,
+            timeout => Some(Duration::from_millis(timeout.into())),
+        }
+    }
+
+    fn set_timeout(&mut self, timeout: Option<Duration>) {
+        let timeout = timeout.map_or(0, |t| t.as_millis().clamp(1, u32::MAX.into()) as u32);
+        self.session.set_timeout(timeout);
+    }
+
+    fn set_message_limits(&mut self, limits: MessageLimits) {
+        self.framer.set_limits(limits);
+    }
+
+    fn set_frame_recovery(&mut self, recovery: bool) {
+        self.framer.set_recovery(recovery);
+    }
+
+    fn set_wire_observer(&mut self, observer: Box<dyn WireObserver>) {
+        self.framer.set_observer(observer);
+    }
+
+    fn set_max_outbound_chunk_size(&mut self, size: Option<usize>) {
+        self.framer.set_max_outbound_chunk_size(size);
+    }
+
+    fn set_lossy_utf8(&mut self, lossy: bool) {
+        self.framer.set_lossy_utf8(lossy);
+    }
+
+    fn set_autoflush(&mut self, autoflush: bool) {
+        self.framer.set_autoflush(autoflush);
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.framer.flush(&mut self.channel)
     }
 
     fn close(&mut self) -> Result<()> {
+        self.keepalive = None;
         self.channel.send_eof()?;
         self.channel.wait_eof()?;
         self.channel.close()?;
@@ -57,9 +321,121 @@ fn connect_internal(session: Session) -> Result<SSHTransport> {
             session,
             channel,
             framer: Framer::new(),
+            keepalive: None,
         };
         Ok(transport)
     } else {
         Err(Error::Io(io::Error::last_os_error()))
     }
 }
+
+/// Opens direct-tcpip channel to `host:port` through authenticated `jump` session and
+/// returns local socket forwarded to it, to be passed to [`Session::set_tcp_stream`]
+/// of the target session. Jump hosts are chained by connecting the next jump session
+/// over the returned socket.
+#[cfg(unix)]
+pub fn jump_stream(jump: Session, host: &str, port: u16) -> Result<UnixStream> {
+    let channel = jump.channel_direct_tcpip(host, port, None)?;
+    let (local, forwarded) = UnixStream::pair()?;
+    forwarded.set_nonblocking(true)?;
+    jump.set_blocking(false);
+    let target = format!("{}:{}", host, port);
+    thread::spawn(move || {
+        if let Err(err) = forward(&jump, channel, forwarded) {
+            error!("Forwarding to {} through jump host failed: {}", target, err);
+        }
+        debug!("Stopped forwarding to {}", target);
+        drop(jump);
+    });
+    Ok(local)
+}
+
+/// Copies data both ways between non-blocking channel and socket until either is closed,
+/// waiting in poll while neither has data
+#[cfg(unix)]
+fn forward(session: &Session, mut channel: Channel, mut local: UnixStream) -> io::Result<()> {
+    let local_fd = local.as_raw_fd();
+    let mut buffer = [0; 16 * 1024];
+    loop {
+        // Channel may have data buffered by libssh2 that poll on the socket does not see
+        let mut idle = true;
+        match channel.read(&mut buffer) {
+            Ok(0) if channel.eof() => return Ok(()),
+            Ok(0) => {}
+            Ok(bytes) => {
+                write_all_nonblocking(&mut local, &buffer[..bytes], || {
+                    wait(session, local_fd, libc::POLLOUT)
+                })?;
+                idle = false;
+            }
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
+            Err(err) => return Err(err),
+        }
+        match local.read(&mut buffer) {
+            Ok(0) => return Ok(()),
+            Ok(bytes) => {
+                write_all_nonblocking(&mut channel, &buffer[..bytes], || {
+                    wait(session, local_fd, 0)
+                })?;
+                idle = false;
+            }
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
+            Err(err) => return Err(err),
+        }
+        if idle {
+            wait(session, local_fd, libc::POLLIN)?;
+        }
+    }
+}
+
+/// Writes all of `data`, calling `wait` while `to` would block
+#[cfg(unix)]
+fn write_all_nonblocking(
+    to: &mut impl io::Write,
+    mut data: &[u8],
+    wait: impl Fn() -> io::Result<()>,
+) -> io::Result<()> {
+    while !data.is_empty() {
+        match to.write(data) {
+            Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+            Ok(bytes) => data = &data[bytes..],
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => wait()?,
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
+
+/// Blocks until the session socket is ready in the directions libssh2 is blocked on,
+/// or `local` socket is ready for `events`
+#[cfg(unix)]
+fn wait(session: &Session, local: RawFd, events: libc::c_short) -> io::Result<()> {
+    let mut session_events = libc::POLLIN;
+    if matches!(
+        session.block_directions(),
+        BlockDirections::Outbound | BlockDirections::Both
+    ) {
+        session_events |= libc::POLLOUT;
+    }
+    let mut fds = [
+        libc::pollfd {
+            fd: session.as_raw_fd(),
+            events: session_events,
+            revents: 0,
+        },
+        libc::pollfd {
+            fd: local,
+            events,
+            revents: 0,
+        },
+    ];
+    // SAFETY: fds is a valid array of pollfd for the duration of the call
+    let ready = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, -1) };
+    if ready < 0 {
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,94 @@
+//! Unencrypted NETCONF over TCP, for testing against [`crate::server::Server`].
+//! Real devices require SSH or TLS.
+
+use crate::error::{Error, Result};
+use crate::framer::Framer;
+use crate::transport::{MessageLimits, Transport};
+use crate::wire::WireObserver;
+use std::io::{self, Read};
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+pub struct TcpTransport {
+    stream: TcpStream,
+    framer: Framer,
+}
+
+impl TcpTransport {
+    pub fn dial(addr: impl ToSocketAddrs) -> Result<TcpTransport> {
+        Ok(TcpTransport {
+            stream: TcpStream::connect(addr)?,
+            framer: Framer::new(),
+        })
+    }
+}
+
+impl Transport for TcpTransport {
+    fn send(&mut self, message: &str) -> Result<()> {
+        self.framer.write_xml(message, &mut self.stream)
+    }
+
+    fn receive(&mut self) -> Result<String> {
+        self.flush()?;
+        // Read timeout fails with WouldBlock on some platforms
+        self.framer
+            .read_xml(&mut self.stream)
+            .map_err(|err| match err {
+                Error::Io(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                    io::Error::from(io::ErrorKind::TimedOut).into()
+                }
+                err => err,
+            })
+    }
+
+    fn receive_stream(&mut self) -> Result<Box<dyn Read + '_>> {
+        self.flush()?;
+        Ok(Box::new(self.framer.message_reader(&mut self.stream)))
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.stream.read_timeout().ok().flatten()
+    }
+
+    fn set_timeout(&mut self, timeout: Option<Duration>) {
+        let _ = self.stream.set_read_timeout(timeout);
+        let _ = self.stream.set_write_timeout(timeout);
+    }
+
+    fn set_message_limits(&mut self, limits: MessageLimits) {
+        self.framer.set_limits(limits);
+    }
+
+    fn set_frame_recovery(&mut self, recovery: bool) {
+        self.framer.set_recovery(recovery);
+    }
+
+    fn set_wire_observer(&mut self, observer: Box<dyn WireObserver>) {
+        self.framer.set_observer(observer);
+    }
+
+    fn set_max_outbound_chunk_size(&mut self, size: Option<usize>) {
+        self.framer.set_max_outbound_chunk_size(size);
+    }
+
+    fn set_lossy_utf8(&mut self, lossy: bool) {
+        self.framer.set_lossy_utf8(lossy);
+    }
+
+    fn set_autoflush(&mut self, autoflush: bool) {
+        self.framer.set_autoflush(autoflush);
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.framer.flush(&mut self.stream)
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.stream.shutdown(Shutdown::Both)?;
+        Ok(())
+    }
+
+    fn upgrade(&mut self) {
+        self.framer.upgrade();
+    }
+}
@@ -0,0 +1,71 @@
+//! Capture of raw data exchanged with the server, e.g. for full session transcripts
+//! requested by vendor support.
+
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+/// Receives raw framed data, including chunk headers and message terminators.
+/// Sent data is reported one frame at a time, received data as it is read
+/// from the transport, so one call may contain a partial frame or several frames.
+pub trait WireObserver: Send {
+    fn on_frame(&mut self, direction: Direction, timestamp: SystemTime, data: &[u8]);
+}
+
+/// Observer writing transcript to a file or any other writer, each frame prefixed
+/// with direction and timestamp as seconds since Unix epoch
+pub struct WireLog<W> {
+    to: W,
+}
+
+impl<W: Write + Send> WireLog<W> {
+    pub fn new(to: W) -> WireLog<W> {
+        WireLog { to }
+    }
+}
+
+impl<W: Write + Send> WireObserver for WireLog<W> {
+    fn on_frame(&mut self, direction: Direction, timestamp: SystemTime, data: &[u8]) {
+        let timestamp = timestamp.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let arrow = match direction {
+            Direction::Sent => ">>>",
+            Direction::Received => "<<<",
+        };
+        let result = writeln!(
+            self.to,
+            "{} {}.{:03}",
+            arrow,
+            timestamp.as_secs(),
+            timestamp.subsec_millis()
+        )
+        .and_then(|_| self.to.write_all(data))
+        .and_then(|_| self.to.write_all(b"\n"))
+        .and_then(|_| self.to.flush());
+        if let Err(err) = result {
+            warn!("Failed to write wire log: {}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_wire_log() {
+        let mut log = WireLog::new(Vec::new());
+        let timestamp = UNIX_EPOCH + Duration::from_millis(1_714_557_600_250);
+        log.on_frame(Direction::Sent, timestamp, b"<rpc/>]]>]]>");
+        log.on_frame(Direction::Received, timestamp, b"<rpc-reply/>");
+        assert_eq!(
+            String::from_utf8(log.to).unwrap(),
+            ">>> 1714557600.250\n<rpc/>]]>]]>\n<<< 1714557600.250\n<rpc-reply/>\n"
+        );
+    }
+}